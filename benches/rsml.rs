@@ -0,0 +1,68 @@
+use std::fmt::Write;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use rbx_rsml::{lex_rsml, parse_rsml, parse_syntax};
+
+pub fn benchmark_rsml(c: &mut Criterion) {
+    let mut group = c.benchmark_group("RSML");
+
+    for rule_count in [100, 1_000, 10_000] {
+        let source = generate_stylesheet(rule_count);
+        group.throughput(Throughput::Bytes(source.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("lex", rule_count), &source, |b, source| {
+            b.iter(|| lex_rsml(source))
+        });
+
+        let tokens = lex_rsml(&source);
+        group.bench_with_input(
+            BenchmarkId::new("parse", rule_count),
+            &tokens,
+            |b, tokens| b.iter(|| parse_rsml(tokens)),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("syntax", rule_count),
+            &source,
+            |b, source| b.iter(|| parse_syntax(source)),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_rsml);
+criterion_main!(benches);
+
+/// Builds a stylesheet with `rule_count` top-level rules, each using the kinds
+/// of values that real stylesheets do.
+fn generate_stylesheet(rule_count: usize) -> String {
+    let mut source = String::from("-- Generated for benchmarking.\n$Gap = 4px;\n\n");
+
+    for index in 0..rule_count {
+        write!(
+            source,
+            "Frame{index} > TextLabel, .Tag{index}:hover {{
+    @priority {priority};
+    $Accent = tw:blue:500;
+    Size = udim2(100% - {index}px, 0, 24);
+    Position = udim2(0.5, 0, 0.5, $Gap);
+    BackgroundColor3 = rgb({red}, 128, 64);
+    TextXAlignment = .Left;
+    FontFace = font(\"rbxasset://fonts/families/GothamSSm.json\", \"Bold\");
+
+    ::UIPadding {{
+        PaddingLeft = udim(0, {index} * 2 + 1);
+    }}
+}}
+
+",
+            priority = index % 5,
+            red = index % 256,
+        )
+        .unwrap();
+    }
+
+    source
+}
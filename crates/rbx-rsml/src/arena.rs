@@ -1,30 +1,169 @@
+// Modules -------------------------------------------------------------------------------------------
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+// ---------------------------------------------------------------------------------------------------
+
+
+// Id ------------------------------------------------------------------------------------------------
+/// A handle to a value in an [`Arena<T>`]. The type parameter keeps handles into different arenas
+/// from being mixed up, but a handle is only meaningful for the arena which gave it out.
+pub struct Id<T> {
+    index: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Id<T> {
+    /// Makes a handle to the value at `index`, for when values are numbered some other way, like
+    /// the rules of a stylesheet in the order they appear.
+    pub const fn new(index: usize) -> Self {
+        Self { index, marker: PhantomData }
+    }
+
+    pub fn index(self) -> usize {
+        self.index
+    }
+}
+
+// These are written out by hand because deriving them would require `T` to implement them too.
+impl<T> Clone for Id<T> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<T> Copy for Id<T> {}
+
+impl<T> PartialEq for Id<T> {
+    fn eq(&self, other: &Self) -> bool { self.index == other.index }
+}
+
+impl<T> Eq for Id<T> {}
+
+impl<T> PartialOrd for Id<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> { Some(self.cmp(other)) }
+}
+
+impl<T> Ord for Id<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering { self.index.cmp(&other.index) }
+}
+
+impl<T> Hash for Id<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.index.hash(state) }
+}
+
+impl<T> fmt::Debug for Id<T> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "Id({})", self.index)
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Arena ---------------------------------------------------------------------------------------------
+/// Values stored by [`Id`]. Removing a value leaves a hole behind, so the handles of everything else
+/// stay valid.
 #[derive(Debug, Clone)]
 pub struct Arena<T> {
-    pub data: Vec<T>,
-    size: usize,
+    slots: Vec<Option<T>>,
+    len: usize,
 }
 
-impl<'a, T> Arena<T> {
+impl<T> Arena<T> {
     pub fn new() -> Self {
+        Self { slots: vec![], len: 0 }
+    }
 
-        Self {
-            data: vec![],
-            size: 0,
-        }
+    pub fn push(&mut self, value: T) -> Id<T> {
+        let id = Id::new(self.slots.len());
+        self.slots.push(Some(value));
+        self.len += 1;
+        id
     }
 
-    pub fn push(&mut self, value: T) -> usize {
-        let this_idx = self.size;
-        self.data.push(value);
-        self.size += 1;
-        this_idx
+    pub fn get(&self, id: Id<T>) -> Option<&T> {
+        self.slots.get(id.index)?.as_ref()
     }
 
-    pub fn get(&self, index: usize) -> Option<&T> {
-        self.data.get(index)
+    pub fn get_mut(&mut self, id: Id<T>) -> Option<&mut T> {
+        self.slots.get_mut(id.index)?.as_mut()
     }
 
-    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
-        self.data.get_mut(index)
+    pub fn contains(&self, id: Id<T>) -> bool {
+        self.get(id).is_some()
     }
-}
\ No newline at end of file
+
+    pub fn remove(&mut self, id: Id<T>) -> Option<T> {
+        let value = self.slots.get_mut(id.index)?.take()?;
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// How many values are in the arena, not counting removed ones.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Every value along with its handle, in the order they were pushed.
+    pub fn iter(&self) -> impl Iterator<Item = (Id<T>, &T)> {
+        self.slots.iter().enumerate()
+            .filter_map(|(index, slot)| Some((Id::new(index), slot.as_ref()?)))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Id<T>, &mut T)> {
+        self.slots.iter_mut().enumerate()
+            .filter_map(|(index, slot)| Some((Id::new(index), slot.as_mut()?)))
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = Id<T>> + '_ {
+        self.iter().map(|(id, _)| id)
+    }
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> std::ops::Index<Id<T>> for Arena<T> {
+    type Output = T;
+
+    fn index(&self, id: Id<T>) -> &T {
+        self.get(id).expect("no value for this id in the arena")
+    }
+}
+
+impl<T> std::ops::IndexMut<Id<T>> for Arena<T> {
+    fn index_mut(&mut self, id: Id<T>) -> &mut T {
+        self.get_mut(id).expect("no value for this id in the arena")
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn handles_survive_removal() {
+        let mut arena = Arena::new();
+        let first = arena.push("first");
+        let second = arena.push("second");
+        let third = arena.push("third");
+
+        assert_eq!(arena.remove(second), Some("second"));
+        assert_eq!(arena.remove(second), None);
+
+        assert_eq!(arena.len(), 2);
+        assert!(!arena.contains(second));
+        assert_eq!(arena[third], "third");
+        assert_eq!(arena.iter().collect::<Vec<_>>(), [(first, &"first"), (third, &"third")]);
+
+        for (_, value) in arena.iter_mut() { *value = "changed" }
+        assert_eq!(arena[first], "changed");
+    }
+}
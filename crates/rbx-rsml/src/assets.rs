@@ -0,0 +1,154 @@
+//! Asset manifests, which `asset("name")` looks assets up in.
+//!
+//! A manifest is a JSON object from names to assets, which are either asset ids or content URLs like
+//! `rbxasset://textures/ui/close.png`. Objects inside it group names, so `{ "icons": { "close": 123 } }`
+//! names an asset `icons/close`.
+//!
+//! `asset(123)` and `rbxasset("path")` become `Content` straight away. `asset("name")` becomes
+//! `Content` holding just the name, which has no `://` in it, and [`compile_stylesheet`] swaps it
+//! for what the manifest has.
+//!
+//! [`compile_stylesheet`]: crate::compile_stylesheet
+
+// Modules -------------------------------------------------------------------------------------------
+use crate::ast::{Rule, StyleSheet};
+
+use rbx_types::{Content, Variant};
+use serde_json::{Map, Value};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+// ---------------------------------------------------------------------------------------------------
+
+
+// Data ----------------------------------------------------------------------------------------------
+/// Why an asset manifest couldn't be read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetManifestError(pub String);
+
+impl fmt::Display for AssetManifestError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for AssetManifestError {}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Helpers -------------------------------------------------------------------------------------------
+/// The content URL for an asset id, as long as it's a whole number that isn't negative.
+pub(crate) fn asset_id_url(id: f64) -> Option<String> {
+    (id >= 0.0 && id.fract() == 0.0 && id <= u64::MAX as f64).then(|| format!("rbxassetid://{}", id as u64))
+}
+
+/// The manifest name that some content looks an asset up by, or `None` when it's already a URL.
+pub(crate) fn asset_name(content: &Content) -> Option<&str> {
+    let content: &str = content.as_ref();
+    (!content.contains("://")).then_some(content)
+}
+
+fn collect_assets(group: &Map<String, Value>, prefix: &str, assets: &mut BTreeMap<String, String>) -> Result<(), AssetManifestError> {
+    for (key, value) in group {
+        let name = format!("{}{}", prefix, key);
+
+        let url = match value {
+            Value::Object(group) => {
+                collect_assets(group, &format!("{}/", name), assets)?;
+                continue
+            },
+            Value::Number(id) => id.as_f64().and_then(asset_id_url),
+            Value::String(url) if url.contains("://") => Some(url.clone()),
+            Value::String(id) => id.parse::<f64>().ok().and_then(asset_id_url),
+            _ => None
+        };
+
+        match url {
+            Some(url) => assets.insert(name, url),
+            None => return Err(AssetManifestError(format!("`{}` has to be an asset id or a content URL", name)))
+        };
+    }
+
+    Ok(())
+}
+
+fn collect_names<'a>(values: &'a BTreeMap<String, Variant>, names: &mut BTreeSet<&'a str>) {
+    for value in values.values() {
+        if let Variant::Content(content) = value { names.extend(asset_name(content)); }
+    }
+}
+
+fn collect_rule_names<'a>(rule: &'a Rule, names: &mut BTreeSet<&'a str>) {
+    collect_names(&rule.variables, names);
+    collect_names(&rule.properties, names);
+    collect_names(&rule.pseudo_properties, names);
+
+    for child in &rule.rules { collect_rule_names(child, names); }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+/// Reads an asset manifest into content URLs by name, as described in the [module docs](self).
+pub fn parse_asset_manifest(json: &str) -> Result<BTreeMap<String, String>, AssetManifestError> {
+    let root = match serde_json::from_str(json) {
+        Ok(Value::Object(root)) => root,
+        Ok(_) => return Err(AssetManifestError(String::from("an asset manifest must be a JSON object"))),
+        Err(error) => return Err(AssetManifestError(format!("invalid JSON: {}", error)))
+    };
+
+    let mut assets = BTreeMap::new();
+    collect_assets(&root, "", &mut assets)?;
+
+    Ok(assets)
+}
+
+/// The names a stylesheet looks up in its asset manifest with `asset("name")`.
+pub fn asset_names(stylesheet: &StyleSheet) -> BTreeSet<&str> {
+    let mut names = BTreeSet::new();
+
+    collect_names(&stylesheet.variables, &mut names);
+    for rule in &stylesheet.rules { collect_rule_names(rule, &mut names); }
+
+    for sheet in &stylesheet.sheets {
+        collect_names(&sheet.variables, &mut names);
+        for rule in &sheet.rules { collect_rule_names(rule, &mut names); }
+    }
+
+    names
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ast::parse_ast;
+
+    #[test]
+    fn manifest() {
+        let json = r#"{ "logo": "rbxasset://textures/logo.png", "icons": { "close": 123, "open": "456" } }"#;
+
+        assert_eq!(parse_asset_manifest(json).unwrap(), BTreeMap::from([
+            (String::from("icons/close"), String::from("rbxassetid://123")),
+            (String::from("icons/open"), String::from("rbxassetid://456")),
+            (String::from("logo"), String::from("rbxasset://textures/logo.png")),
+        ]));
+
+        assert_eq!(
+            parse_asset_manifest(r#"{ "icons": { "close": 1.5 } }"#).unwrap_err().0,
+            "`icons/close` has to be an asset id or a content URL"
+        );
+    }
+
+    #[test]
+    fn names() {
+        let stylesheet = parse_ast("
+            $Logo = asset(\"logo\");
+            ImageButton { Image = asset(123); HoverImage = asset(\"icons/close\"); PressedImage = asset(\"0123\"); ::Child { Image = rbxasset(\"a.png\"); } }
+        ");
+
+        assert_eq!(stylesheet.rules[0].properties["Image"], Variant::Content(Content::from("rbxassetid://123")));
+        assert_eq!(stylesheet.rules[0].properties["PressedImage"], Variant::Content(Content::from("rbxassetid://123")));
+        assert_eq!(stylesheet.rules[0].rules[0].properties["Image"], Variant::Content(Content::from("rbxasset://a.png")));
+        assert_eq!(asset_names(&stylesheet).into_iter().collect::<Vec<_>>(), ["icons/close", "logo"]);
+    }
+}
@@ -0,0 +1,198 @@
+//! An owned version of a parsed stylesheet.
+//!
+//! `parse_rsml` borrows from the tokens it was given, so its tree can't outlive the source. This
+//! copies everything out of it, and can be serialized. The JSON form is meant for other tools to
+//! read, so changes to its shape bump [`AST_VERSION`].
+
+// Modules -------------------------------------------------------------------------------------------
+use crate::arena::Arena;
+use crate::lint::{derived_paths, design_token_paths, uses_utilities};
+use crate::parser::{parse_rsml, TokenTreeNode};
+use crate::syntax::parse_syntax;
+
+use rbx_types::Variant;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashMap};
+// ---------------------------------------------------------------------------------------------------
+
+
+// Data ----------------------------------------------------------------------------------------------
+/// The version of the serialized AST's shape.
+pub const AST_VERSION: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StyleSheet {
+    pub version: u32,
+    /// The stylesheets pulled in with `@derive`, as they are written.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub derives: Vec<String>,
+    /// The design token files pulled in with `@tokens`, as they are written.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub design_tokens: Vec<String>,
+    /// Whether the stylesheet asks for utility classes with `@utilities`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub utilities: bool,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, Variant>,
+    /// Rules in the order they appear in the source.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Rule>,
+    /// The `@sheet` blocks, in the order they appear in the source. A stylesheet with any of these
+    /// compiles into a Folder with a StyleSheet for each.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sheets: Vec<Sheet>,
+}
+
+/// An `@sheet Name { ... }` block, which becomes a StyleSheet of its own. The root variables of the
+/// stylesheet it's in apply to it too.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sheet {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, Variant>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    pub selector: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, Variant>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, Variant>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub pseudo_properties: BTreeMap<String, Variant>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Rule>,
+    /// Whether the rule isn't written out in the source as it is, like the rules of `@each` and `@if`
+    /// blocks and utility classes.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub generated: bool,
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Helpers -------------------------------------------------------------------------------------------
+fn owned_values(values: &HashMap<&str, Variant>) -> BTreeMap<String, Variant> {
+    values.iter().map(|(key, value)| (key.to_string(), value.clone())).collect()
+}
+
+/// The rules directly inside a node, in the order they appear in the source.
+fn child_rules(node: &TokenTreeNode, tree: &Arena<TokenTreeNode>) -> Vec<Rule> {
+    node.children().into_iter().map(|(selector, id)| {
+        let child = &tree[id];
+
+        Rule {
+            selector: selector.to_owned(),
+            priority: child.priority,
+            variables: owned_values(&child.variables),
+            properties: owned_values(&child.properties),
+            pseudo_properties: owned_values(&child.psuedo_properties),
+            rules: child_rules(child, tree),
+            generated: child.generated,
+        }
+    }).collect()
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+/// Parses RSML source into an owned [`StyleSheet`]. Like `parse_rsml`, anything that can't be
+/// understood is skipped; use `check_syntax` to find out about it.
+pub fn parse_ast(source: &str) -> StyleSheet {
+    let syntax = parse_syntax(source);
+
+    let tokens = syntax.tokens().into_iter().map(|token| token.token.clone()).collect::<Vec<_>>();
+    let tree = parse_rsml(&tokens);
+    let root = &tree[TokenTreeNode::ROOT];
+
+    StyleSheet {
+        version: AST_VERSION,
+        derives: derived_paths(&syntax).into_iter().map(str::to_owned).collect(),
+        design_tokens: design_token_paths(&syntax).into_iter().map(str::to_owned).collect(),
+        utilities: uses_utilities(&syntax),
+        variables: owned_values(&root.variables),
+        rules: child_rules(root, &tree),
+        sheets: root.sheets.iter().map(|(name, id)| Sheet {
+            name: name.to_string(),
+            variables: owned_values(&tree[*id].variables),
+            rules: child_rules(&tree[*id], &tree),
+        }).collect(),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_types::{Color3, UDim};
+
+    #[test]
+    fn owned_tree() {
+        let source = "@derive \"theme\";\n@tokens \"tokens.json\";\n@utilities;\n$Gap = 4px;\nFrame { @priority 3; BackgroundColor3 = #ff0000; Child { } }\nText { }";
+        let stylesheet = parse_ast(source);
+
+        assert_eq!(stylesheet.derives, ["theme"]);
+        assert_eq!(stylesheet.design_tokens, ["tokens.json"]);
+        assert!(stylesheet.utilities);
+        assert_eq!(stylesheet.variables["Gap"], Variant::UDim(UDim::new(0.0, 4)));
+
+        let selectors = stylesheet.rules.iter().map(|rule| rule.selector.as_str()).collect::<Vec<_>>();
+        assert_eq!(selectors, ["Frame", "Text"]);
+
+        let frame = &stylesheet.rules[0];
+        assert_eq!(frame.priority, Some(3));
+        assert_eq!(frame.properties["BackgroundColor3"], Variant::Color3(Color3::new(1.0, 0.0, 0.0)));
+        assert_eq!(frame.rules[0].selector, "Child");
+    }
+
+    #[test]
+    fn sheets() {
+        let stylesheet = parse_ast("$Gap = 4px;\n@sheet Button { $Tint = #fff; TextButton { Padding = $Gap; } }\n@sheet Card { Frame { } }");
+
+        assert!(stylesheet.rules.is_empty());
+        assert_eq!(stylesheet.variables.len(), 1);
+
+        let names = stylesheet.sheets.iter().map(|sheet| sheet.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["Button", "Card"]);
+
+        let button = &stylesheet.sheets[0];
+        assert_eq!(button.variables["Tint"], Variant::Color3(Color3::new(1.0, 1.0, 1.0)));
+        assert_eq!(button.rules[0].selector, "TextButton");
+        assert_eq!(button.rules[0].properties["Padding"], Variant::String(String::from("$Gap")));
+        assert_eq!(stylesheet.sheets[1].rules[0].selector, "Frame");
+    }
+
+    #[test]
+    fn generated_rules() {
+        let stylesheet = parse_ast("Frame { }\n@if 1 > 0 { Frame { Child { } } }\n@each $s in (4) { .p-$s { } }\nFrame { }");
+
+        let generated = stylesheet.rules.iter().map(|rule| rule.generated).collect::<Vec<_>>();
+        assert_eq!(generated, [false, true, true, false]);
+        assert!(stylesheet.rules[1].rules[0].generated);
+    }
+
+    #[test]
+    fn json_schema() {
+        let stylesheet = parse_ast("$Gap = 4px;\nFrame { Size = udim2(1, 0); }");
+        let json = serde_json::to_value(&stylesheet).unwrap();
+
+        assert_eq!(json, serde_json::json!({
+            "version": 5,
+            "variables": { "Gap": { "UDim": [0.0, 4] } },
+            "rules": [{
+                "selector": "Frame",
+                "properties": { "Size": { "UDim2": [[1.0, 0], [0.0, 0]] } }
+            }]
+        }));
+
+        assert_eq!(serde_json::from_value::<StyleSheet>(json).unwrap(), stylesheet);
+    }
+}
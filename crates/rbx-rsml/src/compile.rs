@@ -0,0 +1,315 @@
+// Modules -------------------------------------------------------------------------------------------
+use crate::assets::{asset_name, asset_names};
+use crate::ast::{parse_ast, Rule, StyleSheet};
+use crate::diagnostic::{check_syntax, Diagnostic, Severity};
+use crate::lexer::{DataType, TextType, Token};
+use crate::syntax::parse_syntax;
+
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use rbx_types::{Attributes, Variant};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+// ---------------------------------------------------------------------------------------------------
+
+
+// Data ----------------------------------------------------------------------------------------------
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompileOptions {
+    /// The name given to the StyleSheet instance, or to the Folder when there are `@sheet` blocks.
+    pub name: String,
+    /// Root variables from outside the stylesheet, like the design tokens it pulls in with
+    /// `@tokens`. The stylesheet's own root variables take precedence over these.
+    pub imported_variables: BTreeMap<String, Variant>,
+    /// Rules which come after the stylesheet's own, like the utility classes that `@utilities`
+    /// asks for.
+    pub extra_rules: Vec<Rule>,
+    /// Content URLs by name, which `asset("name")` is looked up in. [`compile_instance`] and
+    /// [`compile`] refuse names that aren't here, while [`compile_stylesheet`] leaves their values
+    /// out.
+    pub assets: BTreeMap<String, String>,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            name: String::from("StyleSheet"),
+            imported_variables: BTreeMap::new(),
+            extra_rules: vec![],
+            assets: BTreeMap::new(),
+        }
+    }
+}
+
+/// An instance produced by compiling a stylesheet. It mirrors Rojo's `InstanceSnapshot`, so tools
+/// can turn it into whatever tree they work with, or into a `WeakDom` with [`compile`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledInstance {
+    pub name: String,
+    pub class_name: &'static str,
+    pub properties: HashMap<String, Variant>,
+    pub children: Vec<CompiledInstance>,
+    /// Whether the instance comes from a rule that isn't written out in the source, like the rules
+    /// of `@each` and `@if` blocks. Edits to these can't be written back.
+    pub generated: bool,
+}
+
+impl CompiledInstance {
+    pub fn into_builder(self) -> InstanceBuilder {
+        InstanceBuilder::new(self.class_name)
+            .with_name(self.name)
+            .with_properties(self.properties)
+            .with_children(self.children.into_iter().map(CompiledInstance::into_builder))
+    }
+}
+
+/// The errors which stopped a stylesheet from compiling.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages = self.0.iter().map(|diagnostic| diagnostic.message.as_str()).collect::<Vec<_>>();
+
+        write!(formatter, "stylesheet has {} error(s): {}", self.0.len(), messages.join("; "))
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Helpers -------------------------------------------------------------------------------------------
+/// Attributes can't hold `Content`, so assets are written as the URL strings that Roblox turns into
+/// content when they're applied. Assets from the manifest are looked up first.
+fn attributes(values: &BTreeMap<String, Variant>, options: &CompileOptions) -> Attributes {
+    values.iter().filter_map(|(key, value)| {
+        let value = match value {
+            Variant::Content(content) => match asset_name(content) {
+                Some(name) => Variant::String(options.assets.get(name)?.clone()),
+                None => Variant::String(content.clone().into_string())
+            },
+            _ => value.clone()
+        };
+
+        Some((key.clone(), value))
+    }).collect()
+}
+
+fn style_rule(rule: &Rule, options: &CompileOptions) -> CompiledInstance {
+    let properties = HashMap::from([
+        (String::from("Selector"), Variant::String(rule.selector.clone())),
+        (String::from("Priority"), Variant::Int32(rule.priority.unwrap_or(0))),
+        (String::from("Attributes"), attributes(&rule.variables, options).into()),
+        (String::from("StyledProperties"), attributes(&rule.properties, options).into()),
+    ]);
+
+    CompiledInstance {
+        name: rule.selector.clone(),
+        class_name: "StyleRule",
+        properties,
+        children: rule.rules.iter().map(|child| style_rule(child, options)).collect(),
+        generated: rule.generated,
+    }
+}
+
+fn style_sheet(name: &str, variables: &BTreeMap<String, Variant>, rules: &[Rule], options: &CompileOptions) -> CompiledInstance {
+    CompiledInstance {
+        name: name.to_owned(),
+        class_name: "StyleSheet",
+        properties: HashMap::from([(String::from("Attributes"), attributes(variables, options).into())]),
+        children: rules.iter().chain(&options.extra_rules).map(|rule| style_rule(rule, options)).collect(),
+        generated: false,
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+/// Turns a parsed stylesheet into a StyleSheet with a StyleRule for every rule in it. Variables
+/// become attributes, and properties become the rules' `StyledProperties`.
+///
+/// A stylesheet with `@sheet` blocks becomes a Folder instead, holding a StyleSheet for each block.
+/// Every one of them gets the root variables and the extra rules, and rules outside the blocks are
+/// left out.
+pub fn compile_stylesheet(stylesheet: &StyleSheet, options: &CompileOptions) -> CompiledInstance {
+    let mut variables = options.imported_variables.clone();
+    variables.extend(stylesheet.variables.iter().map(|(name, value)| (name.clone(), value.clone())));
+
+    if stylesheet.sheets.is_empty() { return style_sheet(&options.name, &variables, &stylesheet.rules, options) }
+
+    let sheets = stylesheet.sheets.iter().map(|sheet| {
+        let mut variables = variables.clone();
+        variables.extend(sheet.variables.iter().map(|(name, value)| (name.clone(), value.clone())));
+
+        style_sheet(&sheet.name, &variables, &sheet.rules, options)
+    }).collect();
+
+    CompiledInstance {
+        name: options.name.clone(),
+        class_name: "Folder",
+        properties: HashMap::new(),
+        children: sheets,
+        generated: false,
+    }
+}
+
+/// Parses RSML source into a [`StyleSheet`], refusing source with syntax errors. Tools which compile
+/// the same source more than once can keep the result around and hand it to [`compile_stylesheet`].
+pub fn parse_stylesheet(source: &str) -> Result<StyleSheet, Diagnostics> {
+    let errors = check_syntax(&parse_syntax(source)).into_iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .collect::<Vec<_>>();
+    if !errors.is_empty() { return Err(Diagnostics(errors)) }
+
+    Ok(parse_ast(source))
+}
+
+/// Reports every `asset("name")` whose name isn't in the options' asset manifest.
+fn check_asset_names(source: &str, stylesheet: &StyleSheet, options: &CompileOptions) -> Vec<Diagnostic> {
+    let missing = asset_names(stylesheet).into_iter().filter(|name| !options.assets.contains_key(*name)).collect::<Vec<_>>();
+    if missing.is_empty() { return vec![] }
+
+    let syntax = parse_syntax(source);
+    let tokens = syntax.tokens();
+
+    missing.into_iter().map(|name| {
+        // Names made by `@each` loops aren't written out anywhere, so those point at the start.
+        let span = tokens.windows(3).find_map(|window| match (&window[0].token, &window[1].token, &window[2].token) {
+            (Token::Text(TextType::NonSpecial("asset")), Token::TupleOpen, Token::DataType(DataType::StringSingle(text)))
+                if *text == name => Some(window[2].span.clone()),
+            _ => None
+        }).unwrap_or(0..0);
+
+        Diagnostic::error(format!("`{}` isn't in the asset manifest", name), span)
+    }).collect()
+}
+
+/// Compiles RSML source with [`compile_stylesheet`], refusing source with syntax errors or assets
+/// that aren't in the asset manifest.
+pub fn compile_instance(source: &str, options: &CompileOptions) -> Result<CompiledInstance, Diagnostics> {
+    let stylesheet = parse_stylesheet(source)?;
+
+    let errors = check_asset_names(source, &stylesheet, options);
+    if !errors.is_empty() { return Err(Diagnostics(errors)) }
+
+    Ok(compile_stylesheet(&stylesheet, options))
+}
+
+/// Compiles RSML source into a `WeakDom` whose root is the StyleSheet.
+pub fn compile(source: &str, options: &CompileOptions) -> Result<WeakDom, Diagnostics> {
+    Ok(WeakDom::new(compile_instance(source, options)?.into_builder()))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utilities::{utility_rules, UtilityScale};
+
+    #[test]
+    fn style_rules_in_source_order() {
+        let source = "$Gap = 4px;\nTextButton { @priority 2; Size = udim2(1, 0); Hover { $Tint = #fff; } }\nFrame { }\nTextButton { }";
+        let stylesheet = compile_instance(source, &CompileOptions::default()).unwrap();
+
+        assert_eq!(stylesheet.name, "StyleSheet");
+        assert_eq!(stylesheet.class_name, "StyleSheet");
+
+        let Some(Variant::Attributes(root_attributes)) = stylesheet.properties.get("Attributes") else { panic!() };
+        assert!(root_attributes.get("Gap").is_some());
+
+        let names = stylesheet.children.iter().map(|child| child.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["TextButton", "Frame", "TextButton"]);
+
+        let button = &stylesheet.children[0];
+        assert_eq!(button.properties.get("Priority"), Some(&Variant::Int32(2)));
+        assert_eq!(button.properties.get("Selector"), Some(&Variant::String(String::from("TextButton"))));
+        assert_eq!(button.children[0].class_name, "StyleRule");
+    }
+
+    #[test]
+    fn weak_dom() {
+        let options = CompileOptions { name: String::from("Theme"), ..Default::default() };
+        let dom = compile("Frame { } TextLabel { }", &options).unwrap();
+
+        let root = dom.root();
+        assert_eq!(root.name, "Theme");
+        assert_eq!(root.class, "StyleSheet");
+        assert_eq!(root.children().len(), 2);
+    }
+
+    #[test]
+    fn sheets() {
+        let options = CompileOptions { name: String::from("Components"), ..Default::default() };
+        let folder = compile_instance("$Gap = 4;\n@sheet Button { $Gap = 8; TextButton { } }\n@sheet Card { Frame { } }", &options).unwrap();
+
+        assert_eq!(folder.name, "Components");
+        assert_eq!(folder.class_name, "Folder");
+
+        let names = folder.children.iter().map(|child| (child.name.as_str(), child.class_name)).collect::<Vec<_>>();
+        assert_eq!(names, [("Button", "StyleSheet"), ("Card", "StyleSheet")]);
+
+        let gap = |sheet: &CompiledInstance| match sheet.properties.get("Attributes") {
+            Some(Variant::Attributes(attributes)) => attributes.get("Gap").cloned(),
+            _ => None
+        };
+        assert_eq!(gap(&folder.children[0]), Some(Variant::Float32(8.0)));
+        assert_eq!(gap(&folder.children[1]), Some(Variant::Float32(4.0)));
+        assert_eq!(folder.children[1].children[0].name, "Frame");
+    }
+
+    #[test]
+    fn imported_variables() {
+        let options = CompileOptions {
+            imported_variables: BTreeMap::from([
+                (String::from("Gap"), Variant::Float32(8.0)),
+                (String::from("Tint"), Variant::Float32(1.0)),
+            ]),
+            ..Default::default()
+        };
+        let stylesheet = compile_instance("$Gap = 4;", &options).unwrap();
+
+        let Some(Variant::Attributes(attributes)) = stylesheet.properties.get("Attributes") else { panic!() };
+        assert_eq!(attributes.get("Gap"), Some(&Variant::Float32(4.0)));
+        assert_eq!(attributes.get("Tint"), Some(&Variant::Float32(1.0)));
+    }
+
+    #[test]
+    fn extra_rules() {
+        let options = CompileOptions {
+            extra_rules: utility_rules(["p-2", "Frame"], &UtilityScale::default()),
+            ..Default::default()
+        };
+        let stylesheet = compile_instance("Frame { }", &options).unwrap();
+
+        let names = stylesheet.children.iter().map(|child| child.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["Frame", ".p-2"]);
+        assert_eq!(stylesheet.children[1].children[0].name, "::UIPadding");
+    }
+
+    #[test]
+    fn assets() {
+        let options = CompileOptions {
+            assets: BTreeMap::from([(String::from("icons/close"), String::from("rbxassetid://456"))]),
+            ..Default::default()
+        };
+        let source = "ImageButton { Image = asset(123); HoverImage = asset(\"icons/close\"); PressedImage = asset(\"missing\"); }";
+
+        let errors = compile_instance(source, &options).unwrap_err();
+        assert_eq!(errors.to_string(), "stylesheet has 1 error(s): `missing` isn't in the asset manifest");
+        assert_eq!(&source[errors.0[0].span.clone()], "\"missing\"");
+
+        let stylesheet = compile_stylesheet(&parse_ast(source), &options);
+
+        let Some(Variant::Attributes(properties)) = stylesheet.children[0].properties.get("StyledProperties") else { panic!() };
+        assert_eq!(properties.get("Image"), Some(&Variant::String(String::from("rbxassetid://123"))));
+        assert_eq!(properties.get("HoverImage"), Some(&Variant::String(String::from("rbxassetid://456"))));
+        assert_eq!(properties.get("PressedImage"), None);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let errors = compile("Frame { Size = ; ", &CompileOptions::default()).unwrap_err();
+
+        assert_eq!(errors.to_string(), "stylesheet has 2 error(s): this rule is never closed; expected a value after `=`");
+    }
+}
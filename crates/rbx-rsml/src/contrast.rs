@@ -0,0 +1,361 @@
+// Modules -------------------------------------------------------------------------------------------
+use crate::arena::Arena;
+use crate::diagnostic::Diagnostic;
+use crate::lexer::{TextType, Token};
+use crate::lint::{LintContext, LintRule};
+use crate::parser::{parse_rsml, NodeId, TokenTreeNode};
+use crate::syntax::{ItemSyntax, RuleSyntax, StyleSheetSyntax, SyntaxToken};
+
+use rbx_types::{Color3, Variant};
+
+use std::collections::HashMap;
+use std::ops::Range;
+// ---------------------------------------------------------------------------------------------------
+
+
+// Data ----------------------------------------------------------------------------------------------
+/// How many variables deep a value is followed before giving up.
+const MAX_VARIABLE_DEPTH: usize = 8;
+
+/// Text at least this big counts as large text, which WCAG holds to a lower ratio. 18pt is 24px.
+const LARGE_TEXT_SIZE: f32 = 24.0;
+
+/// The WCAG conformance level which text contrast is checked against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContrastLevel {
+    #[default]
+    AA,
+    AAA,
+}
+
+impl ContrastLevel {
+    pub fn name(self) -> &'static str {
+        match self {
+            ContrastLevel::AA => "AA",
+            ContrastLevel::AAA => "AAA",
+        }
+    }
+
+    /// The lowest contrast ratio this level allows for normal and large text.
+    pub fn minimum_ratio(self, is_large_text: bool) -> f64 {
+        match (self, is_large_text) {
+            (ContrastLevel::AA, false) => 4.5,
+            (ContrastLevel::AA, true) => 3.0,
+            (ContrastLevel::AAA, false) => 7.0,
+            (ContrastLevel::AAA, true) => 4.5,
+        }
+    }
+}
+
+/// A rule along with everything needed to place it in the cascade.
+struct CascadeRule<'s, 'a, 't> {
+    syntax: &'s RuleSyntax<'a>,
+    node: NodeId<'t>,
+    priority: i32,
+}
+
+/// The properties that decide how readable text is, after the cascade has been applied.
+#[derive(Default)]
+struct TextStyle<'s, 'a> {
+    text_color: Option<(Color3, &'s RuleSyntax<'a>)>,
+    background_color: Option<Color3>,
+    text_transparency: f32,
+    background_transparency: f32,
+    text_size: Option<f32>,
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Colors --------------------------------------------------------------------------------------------
+fn relative_luminance(color: Color3) -> f64 {
+    let channel = |value: f32| {
+        let value = value as f64;
+        if value <= 0.03928 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+    };
+
+    0.2126 * channel(color.r) + 0.7152 * channel(color.g) + 0.0722 * channel(color.b)
+}
+
+/// The WCAG contrast ratio between two colors, from 1 up to 21.
+pub fn contrast_ratio(first: Color3, second: Color3) -> f64 {
+    let (first, second) = (relative_luminance(first), relative_luminance(second));
+    let (lighter, darker) = if first > second { (first, second) } else { (second, first) };
+
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+fn blend(over: Color3, under: Color3, transparency: f32) -> Color3 {
+    let mix = |over: f32, under: f32| over * (1.0 - transparency) + under * transparency;
+
+    Color3::new(mix(over.r, under.r), mix(over.g, under.g), mix(over.b, under.b))
+}
+
+/// The contrast of the text against its background. A see-through background could be over
+/// anything, so it is checked over both black and white and the worse result is used.
+fn text_contrast(text: Color3, background: Color3, text_transparency: f32, background_transparency: f32) -> f64 {
+    let backdrops: &[Color3] = match background_transparency > 0.0 {
+        true => &[Color3::new(0.0, 0.0, 0.0), Color3::new(1.0, 1.0, 1.0)],
+        false => &[Color3::new(0.0, 0.0, 0.0)]
+    };
+
+    backdrops.iter().map(|backdrop| {
+        let background = blend(background, *backdrop, background_transparency);
+        contrast_ratio(blend(text, background, text_transparency), background)
+    }).fold(f64::INFINITY, f64::min)
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Cascade -------------------------------------------------------------------------------------------
+/// The syntax of every rule by where its selector starts, including the rules inside of `@each`, `@if`
+/// and `@sheet` blocks.
+fn index_rules<'s, 'a>(items: &'s [ItemSyntax<'a>], rules: &mut HashMap<usize, &'s RuleSyntax<'a>>) {
+    for item in items {
+        match item {
+            ItemSyntax::Rule(rule) => {
+                rules.insert(rule.selector[0].span.start, rule);
+                index_rules(&rule.items, rules);
+            },
+            ItemSyntax::Control(control) => index_rules(&control.items, rules),
+            _ => ()
+        }
+    }
+}
+
+/// Groups the rules inside a node of the `parse_rsml` tree by the selectors of every rule around them.
+/// The tree has the rules that `@each` and `@if` blocks evaluate to, so each is paired with the
+/// syntax it was evaluated from through the token its selector starts at.
+fn collect_rules<'s, 'a, 't>(
+    tree: &Arena<TokenTreeNode<'t>>, parent: NodeId<'t>, path: &str,
+    tokens: &[&SyntaxToken<'a>], rules: &HashMap<usize, &'s RuleSyntax<'a>>,
+    cascades: &mut Vec<(String, Vec<CascadeRule<'s, 'a, 't>>)>
+) {
+    for (selector, node) in tree.children(parent) {
+        let Some(tree_node) = tree.get(node) else { continue };
+        let Some(rule) = tokens.get(tree_node.selector_token).and_then(|token| rules.get(&token.span.start)) else { continue };
+
+        // Macros are only applied where they are used.
+        if matches!(rule.selector[0].token, Token::MacroDeclaration) { continue }
+
+        let path = match path.is_empty() {
+            true => selector.to_owned(),
+            false => format!("{} {}", path, selector)
+        };
+
+        let cascade_rule = CascadeRule { syntax: rule, node, priority: tree_node.priority.unwrap_or(0) };
+
+        match cascades.iter_mut().find(|(cascade_path, _)| *cascade_path == path) {
+            Some((_, rules)) => rules.push(cascade_rule),
+            None => cascades.push((path.clone(), vec![cascade_rule]))
+        }
+
+        collect_rules(tree, node, &path, tokens, rules, cascades);
+    }
+}
+
+/// Follows `$variable` references up through the enclosing rules, then into derived stylesheets.
+fn resolve<'t>(tree: &'t Arena<TokenTreeNode<'t>>, context: &'t LintContext, node: NodeId<'t>, variant: &'t Variant) -> Option<&'t Variant> {
+    let mut variant = variant;
+    let mut node = node;
+
+    for _ in 0..MAX_VARIABLE_DEPTH {
+        let Variant::String(text) = variant else { return Some(variant) };
+        let Some(name) = text.strip_prefix('$') else { return Some(variant) };
+
+        let found = std::iter::once(node).chain(tree.ancestors(node))
+            .find_map(|scope| Some((tree.get(scope)?.variables.get(name)?, scope)));
+
+        (variant, node) = match found {
+            Some(found) => found,
+            None => (context.imported_values.get(name)?, TokenTreeNode::ROOT)
+        };
+    }
+
+    None
+}
+
+fn number(variant: &Variant) -> Option<f32> {
+    match variant {
+        Variant::Float32(number) => Some(*number),
+        Variant::Float64(number) => Some(*number as f32),
+        Variant::Int32(number) => Some(*number as f32),
+        _ => None
+    }
+}
+
+fn cascade_style<'s, 'a, 't>(rules: &[CascadeRule<'s, 'a, 't>], tree: &Arena<TokenTreeNode<'t>>, context: &LintContext) -> TextStyle<'s, 'a> {
+    let mut style = TextStyle::default();
+
+    // Higher priorities win, and later rules win between equal priorities.
+    let mut rules = rules.iter().collect::<Vec<_>>();
+    rules.sort_by_key(|rule| rule.priority);
+
+    for rule in rules {
+        let Some(node) = tree.get(rule.node) else { continue };
+        let property = |name: &str| node.properties.get(name).and_then(|value| resolve(tree, context, rule.node, value));
+
+        if let Some(Variant::Color3(color)) = property("TextColor3") { style.text_color = Some((*color, rule.syntax)); }
+        if let Some(Variant::Color3(color)) = property("BackgroundColor3") { style.background_color = Some(*color); }
+        if let Some(value) = property("TextTransparency").and_then(number) { style.text_transparency = value; }
+        if let Some(value) = property("BackgroundTransparency").and_then(number) { style.background_transparency = value; }
+        if let Some(value) = property("TextSize").and_then(number) { style.text_size = Some(value); }
+    }
+
+    style
+}
+
+fn text_color_span(rule: &RuleSyntax) -> Range<usize> {
+    rule.items.iter().rev()
+        .find_map(|item| match item {
+            ItemSyntax::Declaration(declaration) if *declaration.key_text_type() == TextType::NonSpecial("TextColor3") => {
+                Some(declaration.key.span.clone())
+            },
+            _ => None
+        })
+        .unwrap_or_else(|| rule.selector_span())
+}
+
+/// Reports every selector whose text ends up with too little contrast against its background.
+pub fn check_contrast(syntax: &StyleSheetSyntax, context: &LintContext) -> Vec<Diagnostic> {
+    let syntax_tokens = syntax.tokens();
+    let tokens = syntax_tokens.iter().map(|token| token.token.clone()).collect::<Vec<_>>();
+    let tree = parse_rsml(&tokens);
+
+    let mut rules = HashMap::new();
+    index_rules(&syntax.items, &mut rules);
+
+    // Every `@sheet` block becomes a StyleSheet of its own, so its rules cascade apart from the rest.
+    let mut cascades = vec![];
+    let scopes = std::iter::once(TokenTreeNode::ROOT).chain(tree[TokenTreeNode::ROOT].sheets.iter().map(|(_, sheet)| *sheet));
+
+    for scope in scopes {
+        let mut scope_cascades = vec![];
+        collect_rules(&tree, scope, "", &syntax_tokens, &rules, &mut scope_cascades);
+        cascades.extend(scope_cascades);
+    }
+
+    let mut diagnostics = vec![];
+
+    for (selector, rules) in &cascades {
+        let style = cascade_style(rules, &tree, context);
+
+        let (Some((text_color, rule)), Some(background_color)) = (style.text_color, style.background_color) else { continue };
+        if style.text_transparency >= 1.0 || style.background_transparency >= 1.0 { continue }
+
+        let ratio = text_contrast(text_color, background_color, style.text_transparency, style.background_transparency);
+        let is_large_text = style.text_size.is_some_and(|size| size >= LARGE_TEXT_SIZE);
+        let minimum = context.contrast.minimum_ratio(is_large_text);
+
+        if ratio >= minimum { continue }
+
+        diagnostics.push(Diagnostic::lint(
+            LintRule::LowContrast,
+            format!(
+                "text in `{}` has a contrast ratio of {:.2}:1, below the {}:1 that WCAG {} asks for",
+                selector, ratio, minimum, context.contrast.name()
+            ),
+            text_color_span(rule)
+        ));
+    }
+
+    diagnostics
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::parse_syntax;
+
+    fn check(source: &str, context: &LintContext) -> Vec<String> {
+        check_contrast(&parse_syntax(source), context).into_iter()
+            .map(|diagnostic| format!("{} {}", &source[diagnostic.span], diagnostic.message))
+            .collect()
+    }
+
+    #[test]
+    fn ratios() {
+        let black = Color3::new(0.0, 0.0, 0.0);
+        let white = Color3::new(1.0, 1.0, 1.0);
+
+        assert!((contrast_ratio(black, white) - 21.0).abs() < 1e-9);
+        assert!((contrast_ratio(white, white) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cascade() {
+        let source = "
+            $Muted = #777777;
+            TextLabel { TextColor3 = $Muted; }
+            TextLabel { BackgroundColor3 = css:white; }
+            TextButton { TextColor3 = tw:slate:900; BackgroundColor3 = #ffffff; }
+        ";
+
+        assert_eq!(check(source, &LintContext::default()), [
+            "TextColor3 text in `TextLabel` has a contrast ratio of 4.48:1, below the 4.5:1 that WCAG AA asks for"
+        ]);
+    }
+
+    #[test]
+    fn transparency_and_levels() {
+        let source = "
+            Frame {
+                TextLabel { TextColor3 = #000000; BackgroundColor3 = #ffffff; TextTransparency = 0.6; }
+                Title { TextColor3 = #808080; BackgroundColor3 = #ffffff; TextSize = 32; }
+                Hidden { TextColor3 = #ffffff; BackgroundColor3 = #ffffff; BackgroundTransparency = 1; }
+            }
+        ";
+
+        assert_eq!(check(source, &LintContext::default()), [
+            "TextColor3 text in `Frame TextLabel` has a contrast ratio of 2.85:1, below the 4.5:1 that WCAG AA asks for"
+        ]);
+
+        let context = LintContext { contrast: ContrastLevel::AAA, ..Default::default() };
+        assert_eq!(check(source, &context).len(), 2);
+    }
+
+    #[test]
+    fn control_blocks() {
+        let low = "TextLabel { TextColor3 = #777777; BackgroundColor3 = #ffffff; }";
+        let message = "TextColor3 text in `TextLabel` has a contrast ratio of 4.48:1, below the 4.5:1 that WCAG AA asks for";
+
+        for block in ["@each $s in (4, 8) { .p-$s { } }", "@if 1 > 0 { Frame { } }", "@if 1 < 0 { Frame { } } @else { Frame { } }"] {
+            assert_eq!(check(&format!("{}\n{}", block, low), &LintContext::default()), [message]);
+        }
+
+        let source = "@each $shade in (400, 900) { .text-$shade { TextColor3 = tw:slate:$shade; BackgroundColor3 = #ffffff; } }";
+        assert_eq!(check(source, &LintContext::default()), [
+            "TextColor3 text in `.text-400` has a contrast ratio of 2.56:1, below the 4.5:1 that WCAG AA asks for"
+        ]);
+    }
+
+    #[test]
+    fn sheets() {
+        let source = "
+            $Muted = #777777;
+            TextLabel { BackgroundColor3 = #ffffff; }
+            @sheet A { TextLabel { TextColor3 = $Muted; BackgroundColor3 = #ffffff; } }
+            @sheet B { TextLabel { TextColor3 = $Muted; } }
+        ";
+
+        assert_eq!(check(source, &LintContext::default()), [
+            "TextColor3 text in `TextLabel` has a contrast ratio of 4.48:1, below the 4.5:1 that WCAG AA asks for"
+        ]);
+    }
+
+    #[test]
+    fn imported_values() {
+        let source = "Frame { TextColor3 = $Text; BackgroundColor3 = $Surface; }";
+        let context = LintContext {
+            imported_values: HashMap::from([
+                (String::from("Text"), Variant::Color3(Color3::new(0.5, 0.5, 0.5))),
+                (String::from("Surface"), Variant::String(String::from("$Gray"))),
+                (String::from("Gray"), Variant::Color3(Color3::new(0.45, 0.45, 0.45))),
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!(check(source, &context).len(), 1);
+    }
+}
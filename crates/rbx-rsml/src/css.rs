@@ -0,0 +1,552 @@
+//! Converts a pragmatic subset of CSS into RSML, for UI that started out as a web prototype.
+//!
+//! Selectors can use element types, classes and ids, the descendant and `>` combinators, and the
+//! `:hover` and `:active` states. Element types become the closest Roblox class, so `button` becomes
+//! `TextButton`. The supported properties are `color`, `background-color`, `padding`,
+//! `border-radius`, `font-size`, `font-weight`, `font-family`, `width` and `height`, with lengths in
+//! `px` or `%`. Padding and corner radii style the `UIPadding` and `UICorner` inside the instance.
+//! Anything else is left out, with a warning pointing at it.
+
+// Modules -------------------------------------------------------------------------------------------
+use crate::diagnostic::Diagnostic;
+use crate::parser::css_color;
+
+use std::ops::Range;
+// ---------------------------------------------------------------------------------------------------
+
+
+// Data ----------------------------------------------------------------------------------------------
+/// RSML converted from CSS, and what couldn't be converted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CssConversion {
+    pub rsml: String,
+    /// Warnings about the parts of the CSS which were left out. Their spans point into the CSS.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// What the declarations of one CSS rule turn into.
+#[derive(Default)]
+struct RuleBody {
+    properties: Vec<(&'static str, String)>,
+    /// Top, right, bottom and left, like CSS.
+    padding: [Option<String>; 4],
+    corner_radius: Option<String>,
+    width: Option<(String, Range<usize>)>,
+    height: Option<(String, Range<usize>)>,
+    font_family: Option<String>,
+    font_weight: Option<&'static str>,
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Helpers -------------------------------------------------------------------------------------------
+/// Blanks out comments, keeping every other byte where it was so spans still line up.
+fn blank_comments(source: &str) -> String {
+    let mut bytes = source.as_bytes().to_vec();
+    let mut position = 0;
+
+    while let Some(start) = source[position..].find("/*").map(|start| position + start) {
+        let end = source[start + 2..].find("*/").map_or(source.len(), |end| start + 2 + end + 2);
+        bytes[start..end].fill(b' ');
+        position = end;
+    }
+
+    // Only whole comments were blanked, and spaces are ASCII, so this is still UTF-8.
+    String::from_utf8(bytes).unwrap()
+}
+
+/// Narrows `span` of `source` down to where its text starts and ends.
+fn trim_span(source: &str, span: Range<usize>) -> Range<usize> {
+    let text = &source[span.clone()];
+    let start = span.start + (text.len() - text.trim_start().len());
+
+    start..(start + text.trim().len())
+}
+
+fn element_class(element: &str) -> Option<&'static str> {
+    Some(match element {
+        "div" | "section" | "main" | "header" | "footer" | "nav" | "aside" | "article" | "form" | "ul" | "ol"
+        | "li" => "Frame",
+        "span" | "p" | "label" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => "TextLabel",
+        "button" | "a" => "TextButton",
+        "input" | "textarea" => "TextBox",
+        "img" => "ImageLabel",
+        _ => return None
+    })
+}
+
+fn convert_selector(selector: &str) -> Result<String, String> {
+    let mut output = String::new();
+    let mut combinator = None;
+    let mut rest = selector;
+
+    while !rest.is_empty() {
+        let trimmed = rest.trim_start();
+        if trimmed.len() != rest.len() {
+            if !output.is_empty() { combinator.get_or_insert(">>"); }
+            rest = trimmed;
+            continue
+        }
+
+        if let Some(after) = rest.strip_prefix('>') {
+            combinator = Some(">");
+            rest = after;
+            continue
+        }
+
+        if let Some(pseudo_element) = rest.strip_prefix("::") {
+            let name = pseudo_element.split(|char: char| !(char.is_ascii_alphanumeric() || char == '-')).next().unwrap_or("");
+            return Err(format!("`::{}` pseudo-elements aren't supported", name))
+        }
+
+        let prefix = rest.chars().next().filter(|char| matches!(char, '.' | '#' | ':'));
+        let after = &rest[prefix.map_or(0, char::len_utf8)..];
+
+        let name_len = after.find(|char: char| !(char.is_ascii_alphanumeric() || char == '-' || char == '_'))
+            .unwrap_or(after.len());
+        if name_len == 0 {
+            return Err(format!("`{}` isn't supported in selectors", &rest[..rest.chars().next().unwrap().len_utf8()]))
+        }
+
+        let (name, remaining) = after.split_at(name_len);
+
+        match combinator.take() {
+            Some(_) if output.is_empty() => return Err(format!("expected a selector before `{}`", rest)),
+            Some(combinator) => output.push_str(&format!(" {} ", combinator)),
+            None => ()
+        }
+
+        match prefix {
+            Some(':') => output.push_str(match name {
+                "hover" => ":Hover",
+                "active" => ":Press",
+                _ => return Err(format!("`:{}` isn't supported", name))
+            }),
+            Some(prefix) => {
+                output.push(prefix);
+                output.push_str(name);
+            },
+            None => match element_class(name) {
+                Some(class) => output.push_str(class),
+                None if name.starts_with(|char: char| char.is_ascii_uppercase()) => output.push_str(name),
+                None => return Err(format!("`{}` elements have no Roblox class to become", name))
+            }
+        }
+
+        rest = remaining;
+    }
+
+    match combinator {
+        Some(combinator) => Err(format!("expected a selector after `{}`", combinator)),
+        None => Ok(output)
+    }
+}
+
+fn number(text: &str) -> Option<f64> {
+    text.parse::<f64>().ok().filter(|number| number.is_finite())
+}
+
+/// A length in `px`, `%`, `rem` or `em`, as RSML writes it.
+fn length(value: &str) -> Option<String> {
+    if value == "0" { return Some(String::from("0px")) }
+
+    // `rem` comes before `em`, which it ends with.
+    let (amount, unit) = ["px", "%", "rem", "em"].into_iter()
+        .find_map(|unit| value.strip_suffix(unit).map(|amount| (amount, unit)))?;
+
+    Some(format!("{}{}", number(amount)?, unit))
+}
+
+fn color(value: &str) -> Option<String> {
+    if let Some(hex) = value.strip_prefix('#') {
+        let is_hex = hex.chars().all(|char| char.is_ascii_hexdigit());
+        return (is_hex && matches!(hex.len(), 3 | 4 | 6 | 8)).then(|| format!("#{}", hex.to_ascii_lowercase()))
+    }
+
+    if let Some(arguments) = value.strip_prefix("rgb(").or_else(|| value.strip_prefix("rgba(")) {
+        let channels = arguments.strip_suffix(')')?
+            .split(|char: char| char == ',' || char == '/' || char.is_whitespace())
+            .filter(|channel| !channel.is_empty())
+            .collect::<Vec<_>>();
+
+        let rgb = channels.get(..3)?.iter()
+            .map(|channel| number(channel).filter(|channel| (0.0..=255.0).contains(channel)))
+            .collect::<Option<Vec<_>>>()?;
+
+        let alpha = match channels.get(3) {
+            Some(alpha) => match alpha.strip_suffix('%') {
+                Some(percent) => number(percent)? / 100.0,
+                None => number(alpha)?
+            },
+            None => 1.0
+        };
+
+        if alpha == 1.0 { return Some(format!("rgb({}, {}, {})", rgb[0], rgb[1], rgb[2])) }
+
+        return Some(format!("rgba({}, {}, {}, {})", rgb[0], rgb[1], rgb[2], alpha.clamp(0.0, 1.0)))
+    }
+
+    let name = format!("css:{}", value.to_ascii_lowercase());
+    css_color(&name).map(|_| name)
+}
+
+fn font_weight(value: &str) -> Option<&'static str> {
+    let weight = match value {
+        "normal" => 400.0,
+        "bold" => 700.0,
+        _ => number(value)?
+    };
+
+    Some(match (weight / 100.0).round() as i64 {
+        ..=1 => "Thin",
+        2 => "ExtraLight",
+        3 => "Light",
+        4 => "Regular",
+        5 => "Medium",
+        6 => "SemiBold",
+        7 => "Bold",
+        8 => "ExtraBold",
+        _ => "Heavy"
+    })
+}
+
+/// The first family in a `font-family` list, as a Roblox font asset.
+fn font_family(value: &str) -> Option<String> {
+    let family = value.split(',').next()?.trim().trim_matches(|char| char == '"' || char == '\'');
+    if family.is_empty() { return None }
+
+    Some(format!("rbxasset://fonts/families/{}.json", family.replace(' ', "")))
+}
+
+/// Expands the one to four values of `padding` into top, right, bottom and left.
+fn padding_sides(value: &str) -> Option<[String; 4]> {
+    let values = value.split_whitespace().map(length).collect::<Option<Vec<_>>>()?;
+
+    let [top, right, bottom, left] = match values.as_slice() {
+        [all] => [all, all, all, all],
+        [vertical, horizontal] => [vertical, horizontal, vertical, horizontal],
+        [top, horizontal, bottom] => [top, horizontal, bottom, horizontal],
+        [top, right, bottom, left] => [top, right, bottom, left],
+        _ => return None
+    };
+
+    Some([top.clone(), right.clone(), bottom.clone(), left.clone()])
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Conversion ----------------------------------------------------------------------------------------
+impl RuleBody {
+    /// Converts a declaration. Returns `false` when its value can't be converted.
+    fn declare(&mut self, property: &str, value: &str, span: Range<usize>) -> bool {
+        let side = |index: usize, padding: &mut [Option<String>; 4]| -> bool {
+            match length(value) {
+                Some(length) => { padding[index] = Some(length); true },
+                None => false
+            }
+        };
+
+        match property {
+            "color" => match color(value) {
+                Some(color) => self.properties.push(("TextColor3", color)),
+                None => return false
+            },
+
+            "background-color" | "background" if value == "transparent" => {
+                self.properties.push(("BackgroundTransparency", String::from("1")));
+            },
+            "background-color" | "background" => match color(value) {
+                Some(color) => self.properties.push(("BackgroundColor3", color)),
+                None => return false
+            },
+
+            "padding" => match padding_sides(value) {
+                Some(sides) => self.padding = sides.map(Some),
+                None => return false
+            },
+            "padding-top" => return side(0, &mut self.padding),
+            "padding-right" => return side(1, &mut self.padding),
+            "padding-bottom" => return side(2, &mut self.padding),
+            "padding-left" => return side(3, &mut self.padding),
+
+            "border-radius" => match length(value) {
+                Some(radius) => self.corner_radius = Some(radius),
+                None => return false
+            },
+
+            "font-size" => match value.strip_suffix("px").and_then(number) {
+                Some(size) => self.properties.push(("TextSize", size.to_string())),
+                None => return false
+            },
+            "font-weight" => match font_weight(value) {
+                Some(weight) => self.font_weight = Some(weight),
+                None => return false
+            },
+            "font-family" => match font_family(value) {
+                Some(family) => self.font_family = Some(family),
+                None => return false
+            },
+
+            "width" | "height" => match length(value) {
+                Some(length) if property == "width" => self.width = Some((length, span)),
+                Some(length) => self.height = Some((length, span)),
+                None => return false
+            },
+
+            _ => unreachable!()
+        }
+
+        true
+    }
+
+    fn write(self, selector: &str, output: &mut String, diagnostics: &mut Vec<Diagnostic>) {
+        let mut properties = self.properties;
+
+        match (self.width, self.height) {
+            (Some((width, _)), Some((height, _))) => properties.push(("Size", format!("udim2({}, {})", width, height))),
+            (Some((_, span)), None) => diagnostics.push(Diagnostic::warning(
+                "`width` needs a `height` in the same rule, since they become `Size` together", span
+            )),
+            (None, Some((_, span))) => diagnostics.push(Diagnostic::warning(
+                "`height` needs a `width` in the same rule, since they become `Size` together", span
+            )),
+            (None, None) => ()
+        }
+
+        if self.font_family.is_some() || self.font_weight.is_some() {
+            let family = self.font_family.unwrap_or_else(|| String::from("rbxasset://fonts/families/SourceSansPro.json"));
+            properties.push(("FontFace", format!("font(\"{}\", \"{}\")", family, self.font_weight.unwrap_or("Regular"))));
+        }
+
+        let padding = ["PaddingTop", "PaddingRight", "PaddingBottom", "PaddingLeft"].into_iter()
+            .zip(self.padding)
+            .filter_map(|(key, value)| Some((key, value?)))
+            .collect::<Vec<_>>();
+
+        let mut children = vec![];
+        if !padding.is_empty() { children.push(("::UIPadding", padding)); }
+        if let Some(radius) = self.corner_radius { children.push(("::UICorner", vec![("CornerRadius", radius)])); }
+
+        if !output.is_empty() { output.push('\n'); }
+
+        if properties.is_empty() && children.is_empty() {
+            output.push_str(&format!("{} {{}}\n", selector));
+            return
+        }
+
+        output.push_str(&format!("{} {{\n", selector));
+        for (key, value) in properties { output.push_str(&format!("    {} = {};\n", key, value)); }
+
+        for (child, properties) in children {
+            output.push_str(&format!("    {} {{\n", child));
+            for (key, value) in properties { output.push_str(&format!("        {} = {};\n", key, value)); }
+            output.push_str("    }\n");
+        }
+
+        output.push_str("}\n");
+    }
+}
+
+/// Converts the declarations between a rule's braces, which start at `offset` in the source.
+fn convert_body(source: &str, body: Range<usize>, diagnostics: &mut Vec<Diagnostic>) -> RuleBody {
+    let mut rule_body = RuleBody::default();
+    let mut start = body.start;
+    let mut depth = 0;
+
+    for (index, char) in source[body.clone()].char_indices().chain([(body.len(), ';')]) {
+        match char {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ';' if depth <= 0 => {
+                let span = trim_span(source, start..body.start + index);
+                start = body.start + index + 1;
+                if span.is_empty() { continue }
+
+                let declaration = &source[span.clone()];
+                let Some((property, value)) = declaration.split_once(':') else {
+                    diagnostics.push(Diagnostic::warning(format!("expected `:` in `{}`", declaration), span));
+                    continue
+                };
+
+                let property = property.trim().to_ascii_lowercase();
+                let value = value.trim();
+                let value = value.strip_suffix("!important").map_or(value, str::trim_end);
+
+                let is_supported = matches!(property.as_str(),
+                    "color" | "background-color" | "background" | "padding" | "padding-top" | "padding-right"
+                    | "padding-bottom" | "padding-left" | "border-radius" | "font-size" | "font-weight" | "font-family"
+                    | "width" | "height"
+                );
+
+                if !is_supported {
+                    diagnostics.push(Diagnostic::warning(format!("`{}` isn't supported, so it was left out", property), span));
+                } else if !rule_body.declare(&property, value, span.clone()) {
+                    diagnostics.push(Diagnostic::warning(format!("`{}` can't be converted for `{}`", value, property), span));
+                }
+            },
+            _ => ()
+        }
+    }
+
+    rule_body
+}
+
+/// Skips an at-rule starting at `start`, along with its block if it has one. Returns where it ends.
+fn skip_at_rule(source: &str, start: usize) -> usize {
+    let end_of_statement = source[start..].find([';', '{']).map(|index| start + index);
+
+    match end_of_statement {
+        Some(index) if source[index..].starts_with('{') => {
+            let mut depth = 0;
+
+            for (offset, char) in source[index..].char_indices() {
+                match char {
+                    '{' => depth += 1,
+                    '}' if depth == 1 => return index + offset + 1,
+                    '}' => depth -= 1,
+                    _ => ()
+                }
+            }
+
+            source.len()
+        },
+        Some(index) => index + 1,
+        None => source.len()
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+/// Converts CSS into RSML, as described in the [module docs](self).
+pub fn css_to_rsml(css: &str) -> CssConversion {
+    let source = blank_comments(css);
+    let mut rsml = String::new();
+    let mut diagnostics = vec![];
+    let mut position = 0;
+
+    while let Some(start) = source[position..].find(|char: char| !char.is_whitespace()).map(|start| position + start) {
+
+        if source[start..].starts_with('@') {
+            let name_len = source[start + 1..].find(|char: char| !(char.is_ascii_alphanumeric() || char == '-'))
+                .unwrap_or(source.len() - start - 1);
+            let name = &source[start..start + 1 + name_len];
+
+            diagnostics.push(Diagnostic::warning(format!("`{}` rules aren't supported", name), start..start + 1 + name_len));
+            position = skip_at_rule(&source, start);
+            continue
+        }
+
+        let Some(open) = source[start..].find(['{', '}']).map(|open| start + open) else {
+            diagnostics.push(Diagnostic::warning("expected `{` after this selector", trim_span(&source, start..source.len())));
+            break
+        };
+
+        if source[open..].starts_with('}') {
+            diagnostics.push(Diagnostic::warning("unmatched `}`", open..open + 1));
+            position = open + 1;
+            continue
+        }
+
+        let close = source[open..].find('}').map_or(source.len(), |close| open + close);
+        let body = convert_body(&source, open + 1..close, &mut diagnostics);
+        position = (close + 1).min(source.len());
+
+        let mut selectors = vec![];
+        let mut selector_start = start;
+
+        for selector in source[start..open].split(',') {
+            let span = trim_span(&source, selector_start..selector_start + selector.len());
+            selector_start += selector.len() + 1;
+
+            match convert_selector(&source[span.clone()]) {
+                Ok(selector) => selectors.push(selector),
+                Err(message) => diagnostics.push(Diagnostic::warning(message, span))
+            }
+        }
+
+        if !selectors.is_empty() { body.write(&selectors.join(", "), &mut rsml, &mut diagnostics); }
+    }
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+
+    CssConversion { rsml, diagnostics }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::compile::{compile_instance, CompileOptions};
+
+    #[test]
+    fn conversion() {
+        let css = "
+            /* Cards */
+            .card, div#main > button:hover {
+                background-color: #1E293B;
+                color: rgba(255, 255, 255, 50%);
+                padding: 8px 0.75rem;
+                border-radius: 6px !important;
+                width: 100%; height: 40px;
+                font-weight: 600;
+            }
+            span { color: rebeccapurple; font-size: 14px; padding-left: 0; }
+        ";
+
+        let conversion = css_to_rsml(css);
+
+        assert_eq!(conversion.rsml, "\
+.card, Frame#main > TextButton:Hover {
+    BackgroundColor3 = #1e293b;
+    TextColor3 = rgba(255, 255, 255, 0.5);
+    Size = udim2(100%, 40px);
+    FontFace = font(\"rbxasset://fonts/families/SourceSansPro.json\", \"SemiBold\");
+    ::UIPadding {
+        PaddingTop = 8px;
+        PaddingRight = 0.75rem;
+        PaddingBottom = 8px;
+        PaddingLeft = 0.75rem;
+    }
+    ::UICorner {
+        CornerRadius = 6px;
+    }
+}
+
+TextLabel {
+    TextColor3 = css:rebeccapurple;
+    TextSize = 14;
+    ::UIPadding {
+        PaddingLeft = 0px;
+    }
+}
+");
+        assert!(conversion.diagnostics.is_empty());
+        assert!(compile_instance(&conversion.rsml, &CompileOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn unsupported() {
+        let css = "@media (min-width: 600px) { .a { color: red; } }\n\
+                   .a { display: flex; color: hsl(0, 100%, 50%); width: 10px; }\n\
+                   .b ~ .c, .d::before, video { color: red; }\n\
+                   .e { margin: 0 }";
+
+        let conversion = css_to_rsml(css);
+        let diagnostics = conversion.diagnostics.iter()
+            .map(|diagnostic| (diagnostic.message.as_str(), &css[diagnostic.span.clone()]))
+            .collect::<Vec<_>>();
+
+        assert_eq!(diagnostics, [
+            ("`@media` rules aren't supported", "@media"),
+            ("`display` isn't supported, so it was left out", "display: flex"),
+            ("`hsl(0, 100%, 50%)` can't be converted for `color`", "color: hsl(0, 100%, 50%)"),
+            ("`width` needs a `height` in the same rule, since they become `Size` together", "width: 10px"),
+            ("`~` isn't supported in selectors", ".b ~ .c"),
+            ("`::before` pseudo-elements aren't supported", ".d::before"),
+            ("`video` elements have no Roblox class to become", "video"),
+            ("`margin` isn't supported, so it was left out", "margin: 0"),
+        ]);
+
+        assert_eq!(conversion.rsml, ".a {}\n\n.e {}\n");
+    }
+}
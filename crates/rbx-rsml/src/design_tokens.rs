@@ -0,0 +1,311 @@
+//! Variables from W3C design token files, like the ones design tools export.
+//!
+//! Tokens are named after the groups they're in, joined with `-`, so `color.brand.primary` becomes
+//! `$color-brand-primary`. Colors become `Color3`s, `px` and `%` dimensions become `UDim`s, and
+//! font families become `Font`s. A typography token becomes a `Font` too, along with `-size` and
+//! `-line-height` numbers when it has them. Tokens of other types are left out.
+
+// Modules -------------------------------------------------------------------------------------------
+use rbx_types::{Color3, Font, FontStyle, FontWeight, UDim, Variant};
+use serde_json::{Map, Value};
+
+use std::collections::BTreeMap;
+use std::fmt;
+// ---------------------------------------------------------------------------------------------------
+
+
+// Data ----------------------------------------------------------------------------------------------
+/// Why a design token file couldn't be read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesignTokenError(pub String);
+
+impl fmt::Display for DesignTokenError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DesignTokenError {}
+
+struct DesignToken<'a> {
+    path: Vec<&'a str>,
+    token_type: Option<&'a str>,
+    value: &'a Value,
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Helpers -------------------------------------------------------------------------------------------
+fn variable_name(path: &[&str]) -> String {
+    path.join("-").chars()
+        .map(|char| if char.is_ascii_alphanumeric() || char == '_' || char == '-' { char } else { '-' })
+        .collect()
+}
+
+/// Every token in a group and the groups inside it, with the `$type` it inherits.
+fn collect_tokens<'a>(group: &'a Map<String, Value>, path: &[&'a str], inherited_type: Option<&'a str>, tokens: &mut Vec<DesignToken<'a>>) {
+    let group_type = group.get("$type").and_then(Value::as_str).or(inherited_type);
+
+    for (key, child) in group {
+        if key.starts_with('$') { continue }
+        let Value::Object(child_object) = child else { continue };
+
+        let mut child_path = path.to_vec();
+        child_path.push(key);
+
+        match child_object.get("$value") {
+            Some(value) => tokens.push(DesignToken {
+                path: child_path,
+                token_type: child_object.get("$type").and_then(Value::as_str).or(group_type),
+                value,
+            }),
+            None => collect_tokens(child_object, &child_path, group_type, tokens)
+        }
+    }
+}
+
+/// The `group.token` path in an alias like `"{group.token}"`.
+fn alias(value: &Value) -> Option<&str> {
+    value.as_str()?.strip_prefix('{')?.strip_suffix('}')
+}
+
+/// Follows aliases until a value that isn't one, refusing to go around in circles.
+fn resolve<'a>(tokens: &'a [DesignToken<'a>], value: &'a Value) -> Result<(&'a Value, Option<&'a str>), DesignTokenError> {
+    let mut value = value;
+    let mut token_type = None;
+
+    for _ in 0..=tokens.len() {
+        let Some(target) = alias(value) else { return Ok((value, token_type)) };
+
+        let token = tokens.iter().find(|token| token.path.join(".") == target)
+            .ok_or_else(|| DesignTokenError(format!("`{{{}}}` doesn't refer to a token", target)))?;
+
+        value = token.value;
+        token_type = token_type.or(token.token_type);
+    }
+
+    Err(DesignTokenError(format!("`{}` is an alias of itself", value.as_str().unwrap_or_default())))
+}
+
+fn hex_channel(hex: &str) -> Option<f32> {
+    Some(u8::from_str_radix(hex, 16).ok()? as f32 / 255.0)
+}
+
+/// Parses `#rgb` and `#rrggbb` colors. Alpha, from `#rgba` and `#rrggbbaa`, is left out.
+fn hex_color(hex: &str) -> Option<Color3> {
+    let hex = hex.strip_prefix('#')?;
+    if !hex.is_ascii() { return None }
+
+    let channels = match hex.len() {
+        3 | 4 => hex.chars().take(3).map(|char| hex_channel(&char.to_string().repeat(2))).collect::<Option<Vec<_>>>()?,
+        6 | 8 => (0..3).map(|index| hex_channel(&hex[index * 2..index * 2 + 2])).collect::<Option<Vec<_>>>()?,
+        _ => return None
+    };
+
+    Some(Color3::new(channels[0], channels[1], channels[2]))
+}
+
+fn color(value: &Value) -> Option<Color3> {
+    match value {
+        Value::String(hex) => hex_color(hex),
+
+        // The newer object form, `{ "colorSpace": "srgb", "components": [1, 0.5, 0], "hex": ... }`.
+        Value::Object(color) => match color.get("hex").and_then(Value::as_str) {
+            Some(hex) => hex_color(hex),
+            None => {
+                let components = color.get("components")?.as_array()?;
+                let channel = |index: usize| components.get(index)?.as_f64().map(|channel| channel as f32);
+
+                Some(Color3::new(channel(0)?, channel(1)?, channel(2)?))
+            }
+        },
+
+        _ => None
+    }
+}
+
+/// A dimension in `px` or `%`, written either as `"16px"` or as `{ "value": 16, "unit": "px" }`.
+fn dimension(value: &Value) -> Option<UDim> {
+    let (number, unit) = match value {
+        Value::String(dimension) => {
+            let number_len = dimension.find(|char: char| !(char.is_ascii_digit() || char == '.' || char == '-'))?;
+            (dimension[..number_len].parse::<f64>().ok()?, &dimension[number_len..])
+        },
+        Value::Object(dimension) => (dimension.get("value")?.as_f64()?, dimension.get("unit")?.as_str()?),
+        _ => return None
+    };
+
+    match unit {
+        "px" => Some(UDim::new(0.0, number.round() as i32)),
+        "%" => Some(UDim::new((number / 100.0) as f32, 0)),
+        _ => None
+    }
+}
+
+fn font_family(value: &Value) -> Option<String> {
+    let family = match value {
+        Value::Array(families) => families.first()?.as_str()?,
+        _ => value.as_str()?
+    };
+
+    if family.contains("://") { return Some(family.to_owned()) }
+
+    Some(format!("rbxasset://fonts/families/{}.json", family.replace(' ', "")))
+}
+
+fn font_weight(value: &Value) -> FontWeight {
+    let weight = match value {
+        Value::Number(weight) => weight.as_f64().unwrap_or(400.0),
+        Value::String(name) => match name.to_ascii_lowercase().replace(['-', ' '], "").as_str() {
+            "thin" | "hairline" => 100.0,
+            "extralight" | "ultralight" => 200.0,
+            "light" => 300.0,
+            "medium" => 500.0,
+            "semibold" | "demibold" => 600.0,
+            "bold" => 700.0,
+            "extrabold" | "ultrabold" => 800.0,
+            "black" | "heavy" | "extrablack" | "ultrablack" => 900.0,
+            _ => 400.0
+        },
+        _ => 400.0
+    };
+
+    match (weight / 100.0).round() as i64 {
+        ..=1 => FontWeight::Thin,
+        2 => FontWeight::ExtraLight,
+        3 => FontWeight::Light,
+        4 => FontWeight::Regular,
+        5 => FontWeight::Medium,
+        6 => FontWeight::SemiBold,
+        7 => FontWeight::Bold,
+        8 => FontWeight::ExtraBold,
+        _ => FontWeight::Heavy
+    }
+}
+
+fn number(value: &Value) -> Option<f32> {
+    value.as_f64().map(|number| number as f32)
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+/// Reads a W3C design token file into variables, named as described in the [module docs](self).
+pub fn parse_design_tokens(json: &str) -> Result<BTreeMap<String, Variant>, DesignTokenError> {
+    let root = match serde_json::from_str(json) {
+        Ok(Value::Object(root)) => root,
+        Ok(_) => return Err(DesignTokenError(String::from("design tokens must be a JSON object"))),
+        Err(error) => return Err(DesignTokenError(format!("invalid JSON: {}", error)))
+    };
+
+    let mut tokens = vec![];
+    collect_tokens(&root, &[], None, &mut tokens);
+
+    let mut variables = BTreeMap::new();
+
+    for token in &tokens {
+        let (value, aliased_type) = resolve(&tokens, token.value)?;
+        let name = variable_name(&token.path);
+
+        match token.token_type.or(aliased_type) {
+            Some("color") => if let Some(color) = color(value) {
+                variables.insert(name, Variant::Color3(color));
+            },
+
+            Some("dimension") => if let Some(udim) = dimension(value) {
+                variables.insert(name, Variant::UDim(udim));
+            },
+
+            Some("number") => if let Some(number) = number(value) {
+                variables.insert(name, Variant::Float32(number));
+            },
+
+            Some("fontFamily") => if let Some(family) = font_family(value) {
+                variables.insert(name, Variant::Font(Font::new(&family, FontWeight::Regular, FontStyle::Normal)));
+            },
+
+            Some("typography") => {
+                let Value::Object(typography) = value else { continue };
+                let part = |key: &str| typography.get(key).map(|part| resolve(&tokens, part).map(|(part, _)| part)).transpose();
+
+                let Some(family) = part("fontFamily")?.and_then(font_family) else { continue };
+                let weight = part("fontWeight")?.map(font_weight).unwrap_or(FontWeight::Regular);
+                let style = match part("fontStyle")?.and_then(Value::as_str) {
+                    Some("italic") => FontStyle::Italic,
+                    _ => FontStyle::Normal
+                };
+
+                if let Some(size) = part("fontSize")?.and_then(dimension) {
+                    variables.insert(format!("{}-size", name), Variant::Float32(size.offset as f32));
+                }
+                if let Some(line_height) = part("lineHeight")?.and_then(number) {
+                    variables.insert(format!("{}-line-height", name), Variant::Float32(line_height));
+                }
+
+                variables.insert(name, Variant::Font(Font::new(&family, weight, style)));
+            },
+
+            _ => ()
+        }
+    }
+
+    Ok(variables)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TOKENS: &str = r##"{
+        "color": {
+            "$type": "color",
+            "brand": {
+                "primary": { "$value": "#ff8000" },
+                "on primary": { "$value": { "colorSpace": "srgb", "components": [1, 1, 1] } }
+            },
+            "accent": { "$value": "{color.brand.primary}" }
+        },
+        "space": {
+            "sm": { "$type": "dimension", "$value": "8px" },
+            "half": { "$type": "dimension", "$value": { "value": 50, "unit": "%" } }
+        },
+        "font": {
+            "body": { "$type": "fontFamily", "$value": ["Source Sans Pro", "sans-serif"] },
+            "heading": {
+                "$type": "typography",
+                "$value": { "fontFamily": "{font.body}", "fontWeight": 700, "fontSize": "24px", "lineHeight": 1.2 }
+            }
+        },
+        "motion": { "fast": { "$type": "duration", "$value": "100ms" } }
+    }"##;
+
+    #[test]
+    fn variables() {
+        let variables = parse_design_tokens(TOKENS).unwrap();
+        let body = "rbxasset://fonts/families/SourceSansPro.json";
+
+        assert_eq!(variables.keys().collect::<Vec<_>>(), [
+            "color-accent", "color-brand-on-primary", "color-brand-primary", "font-body", "font-heading",
+            "font-heading-line-height", "font-heading-size", "space-half", "space-sm"
+        ]);
+
+        assert_eq!(variables["color-brand-primary"], Variant::Color3(Color3::new(1.0, 128.0 / 255.0, 0.0)));
+        assert_eq!(variables["color-accent"], variables["color-brand-primary"]);
+        assert_eq!(variables["color-brand-on-primary"], Variant::Color3(Color3::new(1.0, 1.0, 1.0)));
+        assert_eq!(variables["space-sm"], Variant::UDim(UDim::new(0.0, 8)));
+        assert_eq!(variables["space-half"], Variant::UDim(UDim::new(0.5, 0)));
+        assert_eq!(variables["font-body"], Variant::Font(Font::new(body, FontWeight::Regular, FontStyle::Normal)));
+        assert_eq!(variables["font-heading"], Variant::Font(Font::new(body, FontWeight::Bold, FontStyle::Normal)));
+        assert_eq!(variables["font-heading-size"], Variant::Float32(24.0));
+        assert_eq!(variables["font-heading-line-height"], Variant::Float32(1.2));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |json| parse_design_tokens(json).unwrap_err().0;
+
+        assert_eq!(error("[]"), "design tokens must be a JSON object");
+        assert_eq!(error(r#"{ "a": { "$type": "color", "$value": "{b}" } }"#), "`{b}` doesn't refer to a token");
+        assert_eq!(error(r#"{ "a": { "$value": "{a}" } }"#), "`{a}` is an alias of itself");
+    }
+}
@@ -0,0 +1,302 @@
+// Modules -------------------------------------------------------------------------------------------
+use crate::lexer::{DataType, TextType, Token};
+use crate::lint::LintRule;
+use crate::syntax::{ControlSyntax, ItemSyntax, StyleSheetSyntax, SyntaxToken, Trivia, TriviaKind};
+
+use std::collections::HashSet;
+use std::ops::Range;
+// ---------------------------------------------------------------------------------------------------
+
+
+// Data ----------------------------------------------------------------------------------------------
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in RSML source, pointing at the byte range it applies to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Range<usize>,
+    /// The lint rule which reported this, or `None` for syntax errors.
+    pub rule: Option<LintRule>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>, span: Range<usize>) -> Self {
+        Self { severity: Severity::Error, message: message.into(), span, rule: None }
+    }
+
+    pub fn warning(message: impl Into<String>, span: Range<usize>) -> Self {
+        Self { severity: Severity::Warning, message: message.into(), span, rule: None }
+    }
+
+    pub fn lint(rule: LintRule, message: impl Into<String>, span: Range<usize>) -> Self {
+        Self { severity: rule.default_severity(), message: message.into(), span, rule: Some(rule) }
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Checks --------------------------------------------------------------------------------------------
+fn check_trivia(trivia: &[Trivia], diagnostics: &mut Vec<Diagnostic>) {
+    for trivia in trivia {
+        if trivia.kind != TriviaKind::Unknown { continue }
+
+        diagnostics.push(Diagnostic::error(format!("unexpected `{}`", trivia.text.trim()), trivia.span.clone()));
+    }
+}
+
+fn check_tokens<'a>(tokens: impl IntoIterator<Item = &'a SyntaxToken<'a>>, diagnostics: &mut Vec<Diagnostic>) {
+    for token in tokens {
+        check_trivia(&token.leading_trivia, diagnostics);
+    }
+}
+
+fn is_text(token: Option<&SyntaxToken>, text: &str) -> bool {
+    matches!(token.map(|token| &token.token), Some(Token::Text(TextType::NonSpecial(token_text))) if *token_text == text)
+}
+
+/// Checks that the ids in `asset(...)` and `font(...)` are whole numbers, since anything else would
+/// be quietly dropped.
+fn check_asset_ids(value: &[SyntaxToken], diagnostics: &mut Vec<Diagnostic>) {
+    for window in value.windows(3) {
+        let (Token::Text(TextType::NonSpecial("asset" | "font")), Token::TupleOpen, Token::DataType(DataType::Number(id))) =
+            (&window[0].token, &window[1].token, &window[2].token) else { continue };
+
+        if *id < 0.0 || id.fract() != 0.0 {
+            diagnostics.push(Diagnostic::error(format!("`{}` isn't an asset id, which has to be a whole number", window[2].text), window[2].span.clone()));
+        }
+    }
+}
+
+/// Checks the header of a control directive. `previous` is the item before it, which an `@else` has
+/// to follow.
+fn check_control(control: &ControlSyntax, previous: Option<&ItemSyntax>, diagnostics: &mut Vec<Diagnostic>) {
+    let header = &control.header;
+
+    let message = match control.keyword.token {
+        Token::EachDeclaration => {
+            let is_valid = matches!(header.first().map(|token| &token.token), Some(Token::Text(TextType::Variable(_))))
+                && is_text(header.get(1), "in")
+                && matches!(header.get(2).map(|token| &token.token), Some(Token::TupleOpen))
+                && matches!(header.last().map(|token| &token.token), Some(Token::TupleClose));
+
+            if !is_valid {
+                "expected `$variable in (values)` after `@each`"
+            } else if header.len() == 4 {
+                "`@each` has no values to loop over"
+            } else { return }
+        },
+
+        Token::IfDeclaration if header.is_empty() => "expected a condition after `@if`",
+
+        Token::SheetDeclaration => {
+            let is_valid = header.len() == 1 && matches!(header[0].token, Token::Text(TextType::NonSpecial(_)));
+
+            if is_valid { return }
+            "expected a name after `@sheet`"
+        },
+
+        Token::ElseDeclaration => {
+            let follows_if = match previous {
+                Some(ItemSyntax::Control(previous)) => match previous.keyword.token {
+                    Token::IfDeclaration => true,
+                    Token::ElseDeclaration => is_text(previous.header.first(), "if"),
+                    _ => false
+                },
+                _ => false
+            };
+
+            if !follows_if {
+                "`@else` has to come right after an `@if` or `@else if` block"
+            } else if !header.is_empty() && !(is_text(header.first(), "if") && header.len() > 1) {
+                "expected `{` or `if` and a condition after `@else`"
+            } else { return }
+        },
+
+        _ => return
+    };
+
+    diagnostics.push(Diagnostic::error(message, control.keyword.span.clone()));
+}
+
+/// Checks the `@sheet` blocks at the root of a stylesheet. Once there are any, rules have to be in
+/// one of them, since the stylesheet itself doesn't become a StyleSheet anymore.
+fn check_sheets(items: &[ItemSyntax], diagnostics: &mut Vec<Diagnostic>) {
+    let is_sheet = |item: &ItemSyntax| matches!(item, ItemSyntax::Control(control) if control.keyword.token == Token::SheetDeclaration);
+    if !items.iter().any(is_sheet) { return }
+
+    let mut names = HashSet::new();
+
+    for item in items {
+        match item {
+            ItemSyntax::Control(control) if control.keyword.token == Token::SheetDeclaration => {
+                let Some(name) = control.header.first() else { continue };
+
+                if !names.insert(name.text) {
+                    diagnostics.push(Diagnostic::error(format!("there's already a sheet named `{}`", name.text), name.span.clone()));
+                }
+            },
+
+            ItemSyntax::Rule(rule) => {
+                diagnostics.push(Diagnostic::error("rules have to be inside an `@sheet` block once there are any", rule.selector_span()));
+            },
+
+            _ => ()
+        }
+    }
+}
+
+fn check_items(items: &[ItemSyntax], at_root: bool, diagnostics: &mut Vec<Diagnostic>) {
+    for (index, item) in items.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|previous| &items[previous]);
+
+        match item {
+            ItemSyntax::Rule(rule) => {
+                check_tokens(rule.selector.iter().chain([&rule.open]), diagnostics);
+                check_items(&rule.items, false, diagnostics);
+
+                match &rule.close {
+                    Some(close) => check_tokens([close], diagnostics),
+                    None => diagnostics.push(Diagnostic::error("this rule is never closed", rule.open.span.clone()))
+                }
+            },
+
+            ItemSyntax::Declaration(declaration) => {
+                check_tokens([&declaration.key, &declaration.equals].into_iter().chain(&declaration.value).chain(&declaration.terminator), diagnostics);
+
+                if declaration.value.is_empty() {
+                    diagnostics.push(Diagnostic::error("expected a value after `=`", declaration.equals.span.clone()));
+                }
+
+                check_asset_ids(&declaration.value, diagnostics);
+            },
+
+            ItemSyntax::Priority(priority) => {
+                check_tokens([&priority.keyword].into_iter().chain(&priority.value).chain(&priority.terminator), diagnostics);
+
+                if priority.value.is_none() {
+                    diagnostics.push(Diagnostic::error("expected a number after `@priority`", priority.keyword.span.clone()));
+                }
+            },
+
+            ItemSyntax::Control(control) => {
+                check_tokens([&control.keyword].into_iter().chain(&control.header).chain([&control.open]), diagnostics);
+                check_control(control, previous, diagnostics);
+                check_items(&control.items, false, diagnostics);
+
+                if control.keyword.token == Token::SheetDeclaration && !at_root {
+                    diagnostics.push(Diagnostic::error("`@sheet` blocks can only be at the root of a stylesheet", control.keyword.span.clone()));
+                }
+
+                match &control.close {
+                    Some(close) => check_tokens([close], diagnostics),
+                    None => diagnostics.push(Diagnostic::error("this block is never closed", control.open.span.clone()))
+                }
+            },
+
+            ItemSyntax::Other(tokens) => {
+                check_tokens(tokens, diagnostics);
+
+                let first = &tokens[0];
+                let message = match first.token {
+                    // Stray semicolons and directives the compiler doesn't evaluate yet are harmless. `@tokens`
+                    // and `@utilities` are evaluated by whatever compiles the stylesheet, since they need
+                    // to read files.
+                    Token::SectionClose | Token::MacroDeclaration | Token::DeriveDeclaration | Token::TokensDeclaration
+                    | Token::UtilitiesDeclaration => continue,
+
+                    Token::ScopeClose => String::from("unmatched `}`"),
+                    Token::ScopeOpen => String::from("expected a selector before `{`"),
+                    Token::Text(_) => format!("expected `=` or `{{` after `{}`", first.text),
+                    _ => format!("unexpected `{}`", first.text)
+                };
+
+                diagnostics.push(Diagnostic::error(message, first.span.clone()));
+            }
+        }
+    }
+}
+
+/// Converts a byte offset into a 1-based line and column, counting columns in characters.
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+    (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+}
+
+/// Reports everything in a syntax tree which `parse_rsml` would skip over or misread.
+pub fn check_syntax(syntax: &StyleSheetSyntax) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    check_items(&syntax.items, true, &mut diagnostics);
+    check_sheets(&syntax.items, &mut diagnostics);
+    check_trivia(&syntax.trailing_trivia, &mut diagnostics);
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+    diagnostics
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::parse_syntax;
+
+    fn messages(source: &str) -> Vec<String> {
+        check_syntax(&parse_syntax(source)).into_iter().map(|diagnostic| diagnostic.message).collect()
+    }
+
+    #[test]
+    fn valid_source() {
+        assert!(messages("$Gap = 4px;\n@macro Big { Size = udim2(1, 0, 1, 0); }\nFrame { @priority 2; Child { A = 1 } }").is_empty());
+    }
+
+    #[test]
+    fn control_blocks() {
+        assert!(messages("@each $size in (4, 8) { .p-$size { } }\n@each $c in (red, blue) { }\n@if $A > 1 { } @else if not $B { } @else { }").is_empty());
+
+        assert_eq!(messages("@each $size (4, 8) { }\n@each $size in () { }\n@if { }\nFrame { }\n@else { }\n@if $A { } @else $B { }\n@if $A {"), [
+            "expected `$variable in (values)` after `@each`",
+            "`@each` has no values to loop over",
+            "expected a condition after `@if`",
+            "`@else` has to come right after an `@if` or `@else if` block",
+            "expected `{` or `if` and a condition after `@else`",
+            "this block is never closed",
+        ]);
+    }
+
+    #[test]
+    fn sheets() {
+        assert!(messages("$Gap = 4;\n@sheet Button { TextButton { } }\n@sheet Card { Frame { } }").is_empty());
+
+        assert_eq!(messages("@sheet Button { Frame { @sheet Inner { } } }\n@sheet { }\n@sheet Button { }\nFrame { }"), [
+            "`@sheet` blocks can only be at the root of a stylesheet",
+            "expected a name after `@sheet`",
+            "there's already a sheet named `Button`",
+            "rules have to be inside an `@sheet` block once there are any",
+        ]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(messages("Frame { A = ; ? B }\n} Frame {"), [
+            "expected a value after `=`",
+            "unexpected `?`",
+            "expected `=` or `{` after `B`",
+            "unmatched `}`",
+            "this rule is never closed",
+        ]);
+
+        assert_eq!(messages("ImageLabel { Image = asset(12.5); FontFace = font(-3); Icon = asset(\"icons/close\"); }"), [
+            "`12.5` isn't an asset id, which has to be a whole number",
+            "`-3` isn't an asset id, which has to be a whole number",
+        ]);
+    }
+}
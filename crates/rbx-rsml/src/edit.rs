@@ -0,0 +1,544 @@
+// Modules -------------------------------------------------------------------------------------------
+use crate::lexer::{TextType, Token};
+use crate::syntax::{parse_syntax, ItemSyntax};
+
+use rbx_types::{Color3, Font, FontStyle, FontWeight, UDim, Variant};
+
+use std::ops::Range;
+// ---------------------------------------------------------------------------------------------------
+
+
+// Data ----------------------------------------------------------------------------------------------
+/// A single change to make to a rule (or to the root scope) of an RSML source file.
+///
+/// Values are RSML source text, usually produced with [`variant_to_rsml`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleEdit {
+    /// Replaces the selector of the rule. Ignored for the root scope.
+    Selector(String),
+    /// Sets the `@priority` of the rule.
+    Priority(i32),
+    /// Sets (`Some`) or removes (`None`) a property declaration.
+    Property(String, Option<String>),
+    /// Sets (`Some`) or removes (`None`) a `$variable` declaration.
+    Variable(String, Option<String>),
+}
+
+#[derive(Debug)]
+struct DeclarationOutline {
+    name: String,
+    is_variable: bool,
+    span: Range<usize>,
+    value_span: Range<usize>,
+}
+
+/// The location of a rule and its direct declarations inside of the source text.
+#[derive(Debug)]
+struct RuleOutline {
+    selector: String,
+    selector_span: Range<usize>,
+    /// Everything between the rule's braces. For the root scope this is the whole source.
+    body_span: Range<usize>,
+    declarations: Vec<DeclarationOutline>,
+    /// The span of the number in the rule's `@priority` declaration.
+    priority_span: Option<Range<usize>>,
+    children: Vec<usize>,
+    /// Whether this is an `@sheet` block rather than a rule, which has no selector or priority.
+    is_sheet: bool,
+}
+
+impl RuleOutline {
+    fn new(selector: String, selector_span: Range<usize>, body_span: Range<usize>) -> Self {
+        Self {
+            selector,
+            selector_span,
+            body_span,
+            declarations: vec![],
+            priority_span: None,
+            children: vec![],
+            is_sheet: false,
+        }
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Outline -------------------------------------------------------------------------------------------
+fn outline_items(items: &[ItemSyntax], rule_idx: usize, source_len: usize, rules: &mut Vec<RuleOutline>) {
+    for item in items {
+        match item {
+            ItemSyntax::Rule(rule) => {
+                let child_idx = rules.len();
+                rules.push(RuleOutline::new(rule.selector_text(), rule.selector_span(), rule.body_span(source_len)));
+                rules[rule_idx].children.push(child_idx);
+
+                outline_items(&rule.items, child_idx, source_len, rules);
+            },
+
+            ItemSyntax::Declaration(declaration) => {
+                let (name, is_variable) = match declaration.key_text_type() {
+                    TextType::NonSpecial(name) => (name, false),
+                    TextType::Variable(name) => (name, true),
+                    _ => continue
+                };
+
+                rules[rule_idx].declarations.push(DeclarationOutline {
+                    name: name.to_string(),
+                    is_variable,
+                    span: declaration.span(),
+                    value_span: declaration.value_span(),
+                });
+            },
+
+            ItemSyntax::Priority(priority) => {
+                if let Some(value) = &priority.value {
+                    rules[rule_idx].priority_span = Some(value.span.clone());
+                }
+            },
+
+            // `@sheet` blocks are outlined like rules, with a selector like `@sheet Name` that no real
+            // rule can have.
+            ItemSyntax::Control(control) if control.keyword.token == Token::SheetDeclaration && rule_idx == 0 => {
+                let [name] = control.header.as_slice() else { continue };
+                let Token::Text(TextType::NonSpecial(name_text)) = name.token else { continue };
+
+                let body_end = control.close.as_ref().map_or(source_len, |close| close.span.start);
+
+                let child_idx = rules.len();
+                let mut outline = RuleOutline::new(
+                    sheet_segment(name_text), control.keyword.span.start..name.span.end, control.open.span.end..body_end
+                );
+                outline.is_sheet = true;
+                rules.push(outline);
+                rules[rule_idx].children.push(child_idx);
+
+                outline_items(&control.items, child_idx, source_len, rules);
+            },
+
+            // Rules and declarations inside `@each` and `@if` blocks only exist once the blocks are
+            // evaluated, so they can't be edited in place.
+            ItemSyntax::Control(_) | ItemSyntax::Other(_) => ()
+        }
+    }
+}
+
+/// The step of a rule path that goes into the `@sheet` block named `name`.
+pub fn sheet_segment(name: &str) -> String {
+    format!("@sheet {}", name)
+}
+
+/// Builds an outline of every rule in the source. The root scope is always at index `0`.
+fn outline_rsml(source: &str) -> Vec<RuleOutline> {
+    let syntax = parse_syntax(source);
+
+    let mut rules = vec![RuleOutline::new(String::new(), 0..0, 0..source.len())];
+    outline_items(&syntax.items, 0, source.len(), &mut rules);
+
+    rules
+}
+
+fn find_rule(rules: &[RuleOutline], rule_path: &[(&str, usize)]) -> Option<usize> {
+    let mut rule_idx = 0;
+
+    for (selector, nth) in rule_path {
+        rule_idx = *rules[rule_idx].children.iter()
+            .filter(|child_idx| rules[**child_idx].selector == *selector)
+            .nth(*nth)?;
+    }
+
+    Some(rule_idx)
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Whitespace ----------------------------------------------------------------------------------------
+fn line_start(source: &str, position: usize) -> usize {
+    source[..position].rfind('\n').map(|idx| idx + 1).unwrap_or(0)
+}
+
+fn line_indent(source: &str, position: usize) -> &str {
+    let start = line_start(source, position);
+    let line = &source[start..];
+    let indent_len = line.len() - line.trim_start_matches([' ', '\t']).len();
+
+    &line[..indent_len]
+}
+
+/// Guesses the unit of indentation the source uses, defaulting to four spaces.
+fn indent_unit(source: &str) -> String {
+    let mut smallest: Option<&str> = None;
+
+    for line in source.lines() {
+        if line.trim().is_empty() { continue }
+
+        let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+        if indent.is_empty() { continue }
+        if indent.starts_with('\t') { return String::from("\t") }
+
+        if smallest.is_none_or(|smallest| indent.len() < smallest.len()) {
+            smallest = Some(indent);
+        }
+    }
+
+    smallest.unwrap_or("    ").to_string()
+}
+
+/// Widens a span so that removing it also removes its line, as long as nothing else is on that line.
+fn expand_to_line(source: &str, span: Range<usize>) -> Range<usize> {
+    let start = line_start(source, span.start);
+    if !source[start..span.start].trim().is_empty() { return span }
+
+    let rest = &source[span.end..];
+    let line_end = rest.find('\n').map(|idx| span.end + idx + 1).unwrap_or(source.len());
+    if !source[span.end..line_end].trim().is_empty() { return span }
+
+    start..line_end
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Edit ----------------------------------------------------------------------------------------------
+fn apply_text_edits(source: &str, mut text_edits: Vec<(Range<usize>, String)>) -> String {
+    text_edits.sort_by(|(a, _), (b, _)| b.start.cmp(&a.start).then(b.end.cmp(&a.end)));
+
+    let mut output = source.to_string();
+    let mut previous_start = source.len();
+
+    for (span, replacement) in text_edits {
+        // Overlapping edits would corrupt the output, so the later of the two is dropped.
+        if span.end > previous_start { continue }
+
+        output.replace_range(span.clone(), &replacement);
+        previous_start = span.start;
+    }
+
+    output
+}
+
+fn declaration_text(name: &str, is_variable: bool, value: &str) -> String {
+    if is_variable {
+        format!("${} = {};", name, value)
+    } else {
+        format!("{} = {};", name, value)
+    }
+}
+
+/// Applies `edits` to the rule found by following `rule_path` from the root of the source.
+///
+/// Each entry of `rule_path` is a selector and which occurrence of that selector to pick among its
+/// siblings, in source order. An empty path refers to the root scope, and a path starting with
+/// [`sheet_segment`] goes into an `@sheet` block. Everything outside of the edited declarations,
+/// including comments, is kept as it was.
+///
+/// Returns `None` if the rule could not be found.
+pub fn edit_rsml(source: &str, rule_path: &[(&str, usize)], edits: &[RuleEdit]) -> Option<String> {
+    let rules = outline_rsml(source);
+    let rule_idx = find_rule(&rules, rule_path)?;
+    let rule = &rules[rule_idx];
+    let is_root = rule_idx == 0;
+
+    let mut text_edits: Vec<(Range<usize>, String)> = vec![];
+    let mut insertions: Vec<String> = vec![];
+
+    for edit in edits {
+        match edit {
+            RuleEdit::Selector(selector) => {
+                if !is_root && !rule.is_sheet { text_edits.push((rule.selector_span.clone(), selector.to_owned())) }
+            },
+
+            RuleEdit::Priority(priority) => match &rule.priority_span {
+                Some(span) => text_edits.push((span.clone(), priority.to_string())),
+                None => if !is_root && !rule.is_sheet && *priority != 0 {
+                    insertions.insert(0, format!("@priority {};", priority));
+                }
+            },
+
+            RuleEdit::Property(name, value) | RuleEdit::Variable(name, value) => {
+                let is_variable = matches!(edit, RuleEdit::Variable(_, _));
+
+                let existing = rule.declarations.iter()
+                    .filter(|declaration| declaration.is_variable == is_variable && declaration.name == *name)
+                    .collect::<Vec<&DeclarationOutline>>();
+
+                match value {
+                    Some(value) => match existing.last() {
+                        // The parser keeps the last assignment, so that is the one we update.
+                        Some(declaration) => text_edits.push((declaration.value_span.clone(), value.to_owned())),
+                        None => insertions.push(declaration_text(name, is_variable, value)),
+                    },
+
+                    None => for declaration in existing {
+                        text_edits.push((expand_to_line(source, declaration.span.clone()), String::new()));
+                    }
+                }
+            }
+        }
+    }
+
+    if !insertions.is_empty() {
+        let rule_indent = if is_root { "" } else { line_indent(source, rule.selector_span.start) };
+
+        let indent = if let Some(declaration) = rule.declarations.first() {
+            line_indent(source, declaration.span.start).to_string()
+        } else if let Some(child_idx) = rule.children.first() {
+            line_indent(source, rules[*child_idx].selector_span.start).to_string()
+        } else if is_root {
+            String::new()
+        } else {
+            format!("{}{}", rule_indent, indent_unit(source))
+        };
+
+        let lines = insertions.iter()
+            .map(|line| format!("{}{}", indent, line))
+            .collect::<Vec<String>>();
+
+        if let Some(declaration) = rule.declarations.last() {
+            let position = declaration.span.end;
+            text_edits.push((position..position, format!("\n{}", lines.join("\n"))));
+
+        } else if is_root {
+            text_edits.push((0..0, format!("{}\n", lines.join("\n"))));
+
+        } else if source[rule.body_span.clone()].trim().is_empty() {
+            text_edits.push((rule.body_span.clone(), format!("\n{}\n{}", lines.join("\n"), rule_indent)));
+
+        } else {
+            let position = rule.body_span.start;
+            text_edits.push((position..position, format!("\n{}", lines.join("\n"))));
+        }
+    }
+
+    Some(apply_text_edits(source, text_edits))
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Serialize Variants --------------------------------------------------------------------------------
+fn fmt_number(value: f64) -> String {
+    let rounded = (value * 1_000_000.0).round() / 1_000_000.0;
+
+    // Avoids writing out `-0`.
+    if rounded == 0.0 { return String::from("0") }
+
+    rounded.to_string()
+}
+
+fn fmt_udim(udim: &UDim) -> String {
+    let percent = ((udim.scale as f64) * 100.0 * 10_000.0).round() / 10_000.0;
+
+    if percent == 0.0 {
+        format!("{}px", udim.offset)
+
+    } else if udim.offset == 0 {
+        format!("{}%", fmt_number(percent))
+
+    } else {
+        let sign = if udim.offset < 0 { "-" } else { "+" };
+        format!("{}% {} {}px", fmt_number(percent), sign, udim.offset.unsigned_abs())
+    }
+}
+
+fn fmt_color3(color: &Color3) -> String {
+    let channels = [color.r, color.g, color.b].map(|channel| channel as f64 * 255.0);
+
+    let is_byte_aligned = channels.iter()
+        .all(|channel| (0.0..=255.0).contains(channel) && (channel - channel.round()).abs() < 0.01);
+
+    if is_byte_aligned {
+        let [r, g, b] = channels.map(|channel| channel.round() as u8);
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+
+    } else {
+        format!("color3({}, {}, {})", fmt_number(color.r as f64), fmt_number(color.g as f64), fmt_number(color.b as f64))
+    }
+}
+
+fn fmt_string(value: &str) -> Option<String> {
+    if value.contains(['\n', '\r', '\x0c']) { return None }
+
+    if !value.contains('"') {
+        Some(format!("\"{}\"", value))
+    } else if !value.contains('\'') {
+        Some(format!("'{}'", value))
+    } else {
+        None
+    }
+}
+
+fn is_enum_path(value: &str) -> bool {
+    let Some(path) = value.strip_prefix("Enum.") else { return false };
+
+    !path.is_empty() && path.split('.').all(|part| {
+        !part.is_empty() && part.chars().all(|char| char.is_ascii_alphanumeric() || char == '_')
+    })
+}
+
+fn fmt_font(font: &Font) -> Option<String> {
+    let family = fmt_string(&font.family)?;
+
+    let weight = match font.weight {
+        FontWeight::Thin => "Thin",
+        FontWeight::ExtraLight => "ExtraLight",
+        FontWeight::Light => "Light",
+        FontWeight::Regular => "Regular",
+        FontWeight::Medium => "Medium",
+        FontWeight::SemiBold => "SemiBold",
+        FontWeight::Bold => "Bold",
+        FontWeight::ExtraBold => "ExtraBold",
+        FontWeight::Heavy => "Heavy",
+    };
+
+    let style = match font.style {
+        FontStyle::Normal => "Normal",
+        FontStyle::Italic => "Italic",
+    };
+
+    if font.weight == FontWeight::Regular && font.style == FontStyle::Normal {
+        Some(format!("font({})", family))
+    } else {
+        Some(format!("font({}, \"{}\", \"{}\")", family, weight, style))
+    }
+}
+
+/// Converts a value into RSML source text that parses back into the same value.
+///
+/// Returns `None` for values which RSML has no way of expressing.
+pub fn variant_to_rsml(variant: &Variant) -> Option<String> {
+    match variant {
+        Variant::String(value) => {
+            if is_enum_path(value) { Some(value.to_owned()) } else { fmt_string(value) }
+        },
+
+        Variant::Bool(value) => Some(value.to_string()),
+        Variant::Float32(value) => Some(fmt_number(*value as f64)),
+        Variant::Float64(value) => Some(fmt_number(*value)),
+        Variant::Int32(value) => Some(value.to_string()),
+        Variant::Int64(value) => Some(value.to_string()),
+
+        Variant::UDim(value) => Some(fmt_udim(value)),
+        Variant::UDim2(value) => Some(format!("udim2({}, {})", fmt_udim(&value.x), fmt_udim(&value.y))),
+
+        Variant::Vector2(value) => Some(format!("vec2({}, {})", fmt_number(value.x as f64), fmt_number(value.y as f64))),
+        Variant::Vector3(value) => Some(format!(
+            "vec3({}, {}, {})", fmt_number(value.x as f64), fmt_number(value.y as f64), fmt_number(value.z as f64)
+        )),
+        Variant::Rect(value) => Some(format!(
+            "rect({}, {}, {}, {})",
+            fmt_number(value.min.x as f64), fmt_number(value.min.y as f64),
+            fmt_number(value.max.x as f64), fmt_number(value.max.y as f64)
+        )),
+
+        Variant::Color3(value) => Some(fmt_color3(value)),
+        Variant::Font(value) => fmt_font(value),
+
+        _ => None
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{lex_rsml, parse_rsml, TokenTreeNode};
+
+    use rbx_types::{UDim2, Vector2};
+
+    #[test]
+    fn replaces_value_and_keeps_comments() {
+        let source = "-- Buttons\nTextButton {\n    BackgroundColor3 = #ff0000; -- red\n    TextSize = 12;\n}\n";
+
+        let edited = edit_rsml(source, &[("TextButton", 0)], &[
+            RuleEdit::Property("TextSize".into(), Some("14".into()))
+        ]).unwrap();
+
+        assert_eq!(edited, "-- Buttons\nTextButton {\n    BackgroundColor3 = #ff0000; -- red\n    TextSize = 14;\n}\n");
+    }
+
+    #[test]
+    fn inserts_into_empty_rule() {
+        let edited = edit_rsml("Frame {\n    TextButton {  }\n}\n", &[("Frame", 0), ("TextButton", 0)], &[
+            RuleEdit::Priority(2),
+            RuleEdit::Property("TextSize".into(), Some("14".into())),
+        ]).unwrap();
+
+        assert_eq!(edited, "Frame {\n    TextButton {\n        @priority 2;\n        TextSize = 14;\n    }\n}\n");
+    }
+
+    #[test]
+    fn removes_declaration_line() {
+        let edited = edit_rsml("Frame {\n    A = 1;\n    B = 2;\n}\n", &[("Frame", 0)], &[
+            RuleEdit::Property("A".into(), None)
+        ]).unwrap();
+
+        assert_eq!(edited, "Frame {\n    B = 2;\n}\n");
+    }
+
+    #[test]
+    fn targets_duplicate_selectors_by_index() {
+        let edited = edit_rsml("Frame { A = 1; }\nFrame { A = 2; }\n", &[("Frame", 1)], &[
+            RuleEdit::Selector("Frame > TextLabel".into()),
+            RuleEdit::Property("A".into(), Some("3".into())),
+        ]).unwrap();
+
+        assert_eq!(edited, "Frame { A = 1; }\nFrame > TextLabel { A = 3; }\n");
+    }
+
+    #[test]
+    fn root_variables() {
+        let edited = edit_rsml("$Primary = #ffffff;\n\nFrame {}\n", &[], &[
+            RuleEdit::Variable("Primary".into(), Some("#000000".into())),
+            RuleEdit::Variable("Gap".into(), Some("4px".into())),
+        ]).unwrap();
+
+        assert_eq!(edited, "$Primary = #000000;\n$Gap = 4px;\n\nFrame {}\n");
+    }
+
+    #[test]
+    fn sheets() {
+        let source = "$Gap = 4;\n@sheet Button {\n    TextButton { A = 1; }\n}\n@sheet Card {\n    TextButton { A = 1; }\n}\n";
+
+        let edited = edit_rsml(source, &[(&sheet_segment("Card"), 0), ("TextButton", 0)], &[
+            RuleEdit::Property("A".into(), Some("2".into())),
+        ]).unwrap();
+        assert_eq!(edited, "$Gap = 4;\n@sheet Button {\n    TextButton { A = 1; }\n}\n@sheet Card {\n    TextButton { A = 2; }\n}\n");
+
+        let edited = edit_rsml(source, &[(&sheet_segment("Button"), 0)], &[
+            RuleEdit::Selector("Frame".into()),
+            RuleEdit::Variable("Gap".into(), Some("8".into())),
+        ]).unwrap();
+        assert_eq!(edited, "$Gap = 4;\n@sheet Button {\n    $Gap = 8;\n    TextButton { A = 1; }\n}\n@sheet Card {\n    TextButton { A = 1; }\n}\n");
+
+        assert_eq!(edit_rsml(source, &[("TextButton", 0)], &[]), None);
+    }
+
+    #[test]
+    fn missing_rule() {
+        assert_eq!(edit_rsml("Frame {}", &[("Frame", 1)], &[]), None);
+    }
+
+    #[test]
+    fn variants_round_trip() {
+        let variants = [
+            Variant::Float32(0.25),
+            Variant::String("Hello".into()),
+            Variant::String("Enum.Font.Arial".into()),
+            Variant::Color3(Color3::new(1.0, 0.0, 0.2)),
+            Variant::UDim(UDim::new(0.5, -10)),
+            Variant::UDim2(UDim2::new(UDim::new(1.0, 0), UDim::new(0.0, 24))),
+            Variant::Vector2(Vector2::new(1.0, 2.0)),
+        ];
+
+        for variant in variants {
+            let source = format!("Frame {{ Value = {}; }}", variant_to_rsml(&variant).unwrap());
+            let tokens = lex_rsml(&source);
+            let arena = parse_rsml(&tokens);
+
+            let (_, rule) = arena.children(TokenTreeNode::ROOT)[0];
+            let parsed = arena[rule].properties.get("Value").unwrap();
+
+            assert_eq!(parsed, &variant, "{}", source);
+        }
+    }
+}
@@ -0,0 +1,497 @@
+// Modules -------------------------------------------------------------------------------------------
+use crate::lexer::{DataType, TextType, Token};
+use crate::syntax::{parse_syntax, ControlSyntax, DeclarationSyntax, ItemSyntax, RuleSyntax, SyntaxToken, Trivia, TriviaKind};
+// ---------------------------------------------------------------------------------------------------
+
+
+// Data ----------------------------------------------------------------------------------------------
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// The text used for one level of indentation.
+    pub indent: String,
+    /// Sorts the property declarations of each rule alphabetically. Blank lines split properties
+    /// into groups which are sorted separately.
+    pub sort_properties: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: String::from("    "),
+            sort_properties: false,
+        }
+    }
+}
+
+struct Formatter<'a> {
+    options: &'a FormatOptions,
+    output: String,
+    depth: usize,
+    /// Whether the last line written to the output still needs to be ended.
+    line_open: bool,
+    /// Whether the current line ends in a line comment, so nothing else can be added to it.
+    line_has_comment: bool,
+    /// Whether nothing has been written since the start of the file or the current rule.
+    at_block_start: bool,
+    /// Decides whether the next line gets a blank line in front of it, regardless of the source.
+    blank_line_override: Option<bool>,
+}
+
+/// An item along with the comments that belong to it.
+struct Entry<'a, 'b> {
+    item: &'b ItemSyntax<'a>,
+    /// Trivia on the lines before the item.
+    leading: &'b [Trivia<'a>],
+    /// Comments after the item on the same line.
+    trailing: &'b [Trivia<'a>],
+    blank_line_override: Option<bool>,
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Literals ------------------------------------------------------------------------------------------
+fn is_signed_number(token: &SyntaxToken) -> bool {
+    matches!(token.token, Token::DataType(
+        DataType::Number(_) | DataType::NumberOffset(_) | DataType::NumberScale(_) | DataType::NumberRem(_) | DataType::NumberEm(_)
+    )) && token.text.starts_with(['+', '-'])
+}
+
+/// Writes a number without a sign, redundant zeros or a trailing `.`.
+fn normalize_number(text: &str) -> String {
+    let unsigned = text.trim_start_matches(['+', '-']);
+    let digits_len = unsigned.find(|char: char| !char.is_ascii_digit() && char != '.').unwrap_or(unsigned.len());
+    let (digits, suffix) = unsigned.split_at(digits_len);
+
+    let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+    let integer = integer.trim_start_matches('0');
+    let integer = if integer.is_empty() { "0" } else { integer };
+    let fraction = fraction.trim_end_matches('0');
+
+    if fraction.is_empty() {
+        format!("{}{}", integer, suffix)
+    } else {
+        format!("{}.{}{}", integer, fraction, suffix)
+    }
+}
+
+fn normalize_hex(text: &str) -> String {
+    let digits = text[1..].to_lowercase();
+
+    if matches!(digits.len(), 3 | 4) {
+        format!("#{}", digits.chars().flat_map(|char| [char, char]).collect::<String>())
+    } else {
+        format!("#{}", digits)
+    }
+}
+
+fn normalize_literal(token: &SyntaxToken) -> String {
+    match &token.token {
+        Token::DataType(
+            DataType::Number(_) | DataType::NumberOffset(_) | DataType::NumberScale(_) | DataType::NumberRem(_) | DataType::NumberEm(_)
+        ) => {
+            let number = normalize_number(token.text);
+            if number.trim_end_matches(|char: char| char.is_ascii_alphabetic() || char == '%') == "0" { return number }
+
+            if token.text.starts_with('-') { format!("-{}", number) } else { number }
+        },
+
+        Token::DataType(DataType::ColorHex(_)) => normalize_hex(token.text),
+
+        _ => token.text.to_string()
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Spacing -------------------------------------------------------------------------------------------
+fn is_operand(token: &Token) -> bool {
+    matches!(token, Token::DataType(_) | Token::Text(_) | Token::EnumKeyword | Token::TupleClose)
+}
+
+/// Joins tokens using canonical spacing: single spaces around binary operators and combinators, a
+/// space after each comma, and nothing inside of parentheses. Tokens that were written right next to
+/// each other, like `TextButton:hover`, stay that way.
+fn join_tokens(tokens: &[SyntaxToken], is_value: bool) -> String {
+    let mut output = String::new();
+    let mut previous: Option<&SyntaxToken> = None;
+    let mut previous_is_unary = false;
+
+    for token in tokens {
+        let mut text = if is_value { normalize_literal(token) } else { token.text.to_string() };
+        let mut is_unary = false;
+        let mut is_binary = false;
+
+        // A signed number right after an operand is really a binary operation, eg. `10px-5px`.
+        if is_value && is_signed_number(token) && previous.is_some_and(|previous| is_operand(&previous.token)) {
+            let operator = if token.text.starts_with('-') { "-" } else { "+" };
+            text = format!("{} {}", operator, normalize_number(token.text));
+            is_binary = true;
+        }
+
+        let has_space = match previous {
+            None => false,
+            Some(_) if is_binary => true,
+            Some(previous) => match (&previous.token, &token.token) {
+                (_, Token::ListDelimiter | Token::SectionClose | Token::TupleClose) => false,
+                (Token::TupleOpen, _) => false,
+                (Token::ListDelimiter, _) => true,
+                // Control directives use words as operators, eg. `in (4, 8)` and `not ($A)`.
+                (Token::Text(TextType::NonSpecial("in" | "not" | "and" | "or")), Token::TupleOpen) => true,
+                (Token::Text(_), Token::TupleOpen) => false,
+                (Token::ScopeToChildren | Token::ScopeToDescendants, _)
+                | (_, Token::ScopeToChildren | Token::ScopeToDescendants) => true,
+                (Token::Operator(_), _) if previous_is_unary => false,
+                (Token::Operator(_), _) | (_, Token::Operator(_)) => true,
+                (Token::Comparison(_), _) | (_, Token::Comparison(_)) => true,
+                _ => !token.leading_trivia.is_empty()
+            }
+        };
+
+        if let Token::Operator(_) = token.token {
+            is_unary = !previous.is_some_and(|previous| is_operand(&previous.token));
+        }
+
+        if has_space { output.push(' '); }
+        output.push_str(&text);
+
+        previous = Some(token);
+        previous_is_unary = is_unary;
+    }
+
+    output
+}
+
+fn has_blank_line(trivia: &[Trivia]) -> bool {
+    trivia.iter().any(|trivia| trivia.kind == TriviaKind::Whitespace && trivia.text.matches('\n').count() >= 2)
+}
+
+/// Splits trivia into the part on the same line as whatever came before it, and the rest.
+fn split_trailing<'a, 'b>(trivia: &'b [Trivia<'a>]) -> (&'b [Trivia<'a>], &'b [Trivia<'a>]) {
+    let split_idx = trivia.iter()
+        .position(|trivia| trivia.kind == TriviaKind::Whitespace && trivia.text.contains('\n'))
+        .unwrap_or(trivia.len());
+
+    trivia.split_at(split_idx)
+}
+
+fn is_property_declaration(item: &ItemSyntax) -> bool {
+    matches!(item, ItemSyntax::Declaration(declaration) if matches!(declaration.key_text_type(), TextType::NonSpecial(_)))
+}
+
+fn declaration_key<'a>(item: &ItemSyntax<'a>) -> String {
+    item.first_token().text.to_lowercase()
+}
+
+/// Sorts each group of property declarations, keeping blank lines where they were.
+fn sort_entries(entries: &mut [Entry]) {
+    let mut start = 0;
+
+    while start < entries.len() {
+        if !is_property_declaration(entries[start].item) {
+            start += 1;
+            continue;
+        }
+
+        let mut end = start + 1;
+        while end < entries.len() && is_property_declaration(entries[end].item) && !has_blank_line(entries[end].leading) {
+            end += 1;
+        }
+
+        let group_has_blank_line = has_blank_line(entries[start].leading);
+        let group = &mut entries[start..end];
+        group.sort_by_key(|entry| declaration_key(entry.item));
+
+        for (idx, entry) in group.iter_mut().enumerate() {
+            entry.blank_line_override = Some(idx == 0 && group_has_blank_line);
+        }
+
+        start = end;
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Formatter -----------------------------------------------------------------------------------------
+impl<'a> Formatter<'a> {
+    fn new(options: &'a FormatOptions) -> Self {
+        Self {
+            options,
+            output: String::new(),
+            depth: 0,
+            line_open: false,
+            line_has_comment: false,
+            at_block_start: true,
+            blank_line_override: None,
+        }
+    }
+
+    fn start_line(&mut self, blank_line_before: bool) {
+        let blank_line_before = self.blank_line_override.take().unwrap_or(blank_line_before);
+
+        if self.line_open { self.output.push('\n'); }
+        if blank_line_before && !self.at_block_start { self.output.push('\n'); }
+
+        self.output.push_str(&self.options.indent.repeat(self.depth));
+        self.line_open = true;
+        self.line_has_comment = false;
+        self.at_block_start = false;
+    }
+
+    /// Writes comments on their own lines. Returns how many line breaks came after the last one, so
+    /// that the next item can keep a blank line in front of it.
+    fn leading_trivia(&mut self, trivia: &[Trivia]) -> usize {
+        let mut newlines = 0;
+
+        for trivia in trivia {
+            if trivia.kind == TriviaKind::Whitespace {
+                newlines += trivia.text.matches('\n').count();
+                continue;
+            }
+
+            self.start_line(newlines >= 2);
+            self.output.push_str(trivia.text.trim_end());
+            self.line_has_comment = trivia.kind == TriviaKind::LineComment;
+            newlines = 0;
+        }
+
+        newlines
+    }
+
+    /// Writes comments at the end of the current line.
+    fn trailing_trivia(&mut self, trivia: &[Trivia]) {
+        for trivia in trivia {
+            if trivia.kind == TriviaKind::Whitespace { continue }
+
+            self.output.push(' ');
+            self.output.push_str(trivia.text.trim_end());
+            self.line_has_comment |= trivia.kind == TriviaKind::LineComment;
+        }
+    }
+
+    /// Comments in the middle of an item are rare, so they get moved to the end of its line.
+    fn inner_trivia<'b>(&mut self, tokens: impl IntoIterator<Item = &'b SyntaxToken<'b>>) {
+        for token in tokens {
+            self.trailing_trivia(&token.leading_trivia);
+        }
+    }
+
+    fn block(&mut self, items: &[ItemSyntax], end_trivia: &[Trivia], is_root: bool) {
+        let first_trivia = items.first().map(|item| &item.first_token().leading_trivia[..]).unwrap_or(end_trivia);
+
+        // Comments on the same line as the opening brace.
+        if !is_root { self.trailing_trivia(split_trailing(first_trivia).0); }
+
+        let mut entries = items.iter().enumerate().map(|(idx, item)| {
+            let own_trivia = &item.first_token().leading_trivia[..];
+            let next_trivia = items.get(idx + 1).map(|item| &item.first_token().leading_trivia[..]).unwrap_or(end_trivia);
+
+            Entry {
+                item,
+                leading: if idx == 0 && is_root { own_trivia } else { split_trailing(own_trivia).1 },
+                trailing: split_trailing(next_trivia).0,
+                blank_line_override: None,
+            }
+        }).collect::<Vec<Entry>>();
+
+        if self.options.sort_properties { sort_entries(&mut entries); }
+
+        for entry in &entries {
+            self.blank_line_override = entry.blank_line_override;
+
+            let newlines = self.leading_trivia(entry.leading);
+            self.start_line(newlines >= 2);
+            self.item(entry.item);
+            self.trailing_trivia(entry.trailing);
+        }
+
+        // Comments after the last item.
+        if items.is_empty() && is_root {
+            self.leading_trivia(end_trivia);
+        } else {
+            self.leading_trivia(split_trailing(end_trivia).1);
+        }
+    }
+
+    fn item(&mut self, item: &ItemSyntax) {
+        match item {
+            ItemSyntax::Rule(rule) => self.rule(rule),
+
+            ItemSyntax::Declaration(declaration) => self.declaration(declaration),
+
+            ItemSyntax::Priority(priority) => {
+                self.output.push_str("@priority");
+
+                if let Some(value) = &priority.value {
+                    self.output.push(' ');
+                    self.output.push_str(&normalize_literal(value));
+                }
+
+                self.output.push(';');
+                self.inner_trivia(priority.value.iter().chain(&priority.terminator));
+            },
+
+            ItemSyntax::Control(control) => self.control(control),
+
+            ItemSyntax::Other(tokens) => {
+                self.output.push_str(&join_tokens(tokens, false));
+                self.inner_trivia(&tokens[1..]);
+            }
+        }
+    }
+
+    fn rule(&mut self, rule: &RuleSyntax) {
+        self.output.push_str(&join_tokens(&rule.selector, false));
+        self.output.push_str(" {");
+        self.inner_trivia(rule.selector[1..].iter().chain([&rule.open]));
+
+        self.body(&rule.items, rule.close.as_ref());
+    }
+
+    fn control(&mut self, control: &ControlSyntax) {
+        self.output.push_str(control.keyword.text);
+
+        if !control.header.is_empty() {
+            self.output.push(' ');
+            self.output.push_str(&join_tokens(&control.header, true));
+        }
+
+        self.output.push_str(" {");
+        self.inner_trivia(control.header.iter().chain([&control.open]));
+
+        self.body(&control.items, control.close.as_ref());
+    }
+
+    /// The items of a block and its closing brace, which share a line with the opening brace when
+    /// there are no items.
+    fn body(&mut self, items: &[ItemSyntax], close: Option<&SyntaxToken>) {
+        let end_trivia = close.map(|close| &close.leading_trivia[..]).unwrap_or(&[]);
+
+        self.depth += 1;
+        self.at_block_start = true;
+        self.block(items, end_trivia, false);
+        self.depth -= 1;
+
+        if close.is_none() { return }
+
+        if self.at_block_start && !self.line_has_comment {
+            self.output.push('}');
+            self.at_block_start = false;
+        } else {
+            self.start_line(false);
+            self.output.push('}');
+        }
+    }
+
+    fn declaration(&mut self, declaration: &DeclarationSyntax) {
+        self.output.push_str(declaration.key.text);
+        self.output.push_str(" =");
+
+        if !declaration.value.is_empty() {
+            self.output.push(' ');
+            self.output.push_str(&join_tokens(&declaration.value, true));
+        }
+
+        self.output.push(';');
+        self.inner_trivia([&declaration.equals].into_iter().chain(&declaration.value).chain(&declaration.terminator));
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+/// Formats RSML source: one item per line, consistent indentation and spacing, and normalized
+/// numbers and hex colors. Comments are kept, as are single blank lines between items.
+pub fn format_rsml(source: &str, options: &FormatOptions) -> String {
+    let syntax = parse_syntax(source);
+
+    let mut formatter = Formatter::new(options);
+    formatter.block(&syntax.items, &syntax.trailing_trivia, true);
+
+    if formatter.line_open { formatter.output.push('\n'); }
+
+    formatter.output
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn format(source: &str) -> String {
+        format_rsml(source, &FormatOptions::default())
+    }
+
+    #[test]
+    fn layout() {
+        let source = "-- Theme\n$Primary=#ABC;\n\n\n\nFrame>TextLabel,TextButton:hover{Size=udim2( 50%+4px ,0.50 );Font = font (16658221428)\n@priority 02\n  Child {   }}";
+
+        assert_eq!(format(source), "\
+-- Theme
+$Primary = #aabbcc;
+
+Frame > TextLabel, TextButton:hover {
+    Size = udim2(50% + 4px, 0.5);
+    Font = font(16658221428);
+    @priority 2;
+    Child {}
+}
+");
+    }
+
+    #[test]
+    fn comments() {
+        let source = "Frame { -- frames\n  A = 1; -- one\n\n  --[[ two ]]\n  B = 10px -5px;\n  -- end\n}\n-- eof";
+
+        assert_eq!(format(source), "\
+Frame { -- frames
+    A = 1; -- one
+
+    --[[ two ]]
+    B = 10px - 5px;
+    -- end
+}
+-- eof
+");
+    }
+
+    #[test]
+    fn control_blocks() {
+        let source = "@each $size in(4,8){.p-$size{Size=$size*1px;}}\n@if $A>=2 and not($B){}@else if $C!=01{Frame{}}@else{}";
+
+        assert_eq!(format(source), "\
+@each $size in (4, 8) {
+    .p-$size {
+        Size = $size * 1px;
+    }
+}
+@if $A >= 2 and not ($B) {}
+@else if $C != 1 {
+    Frame {}
+}
+@else {}
+");
+    }
+
+    #[test]
+    fn sorted_properties() {
+        let options = FormatOptions { sort_properties: true, ..Default::default() };
+        let source = "Frame {\n    C = 1;\n    A = 2; -- a\n\n    Z = 3;\n    B = 4;\n}\n";
+
+        assert_eq!(format_rsml(source, &options), "Frame {\n    A = 2; -- a\n    C = 1;\n\n    B = 4;\n    Z = 3;\n}\n");
+    }
+
+    #[test]
+    fn idempotent() {
+        let sources = [
+            "Frame{A=1 -- one\n+2;B=-.5;}",
+            "TextButton {  }\n\n\n-- trailing",
+            "Frame { -- note\n}",
+            "A = Enum.Font.Arial B { }",
+        ];
+
+        for source in sources {
+            let formatted = format(source);
+            assert_eq!(format(&formatted), formatted, "{}", source);
+        }
+    }
+}
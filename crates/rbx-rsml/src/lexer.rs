@@ -25,7 +25,13 @@ pub enum DataType<'a> {
     StringSingle(&'a str),
     NumberOffset(f64),
     NumberScale(f64),
+    /// A number of root text sizes, which the parser turns into an offset.
+    NumberRem(f64),
+    /// A number of text sizes of the rule it's in, which the parser turns into an offset.
+    NumberEm(f64),
     Number(f64),
+    /// `true` or `false`, which the lexer leaves as text for the parser to pick out.
+    Bool(bool),
 
     Tuple(usize),
     UDim(UDim),
@@ -34,7 +40,11 @@ pub enum DataType<'a> {
     Rect(Rect),
     Vec3(Vector3),
     Color3(Color3),
+    /// A color with an alpha channel from 0 to 1, where 1 is opaque.
+    ColorAlpha(Color3, f32),
     Font(Font),
+    /// An asset from `asset(...)` or `rbxasset(...)`, as a URL or a name in the asset manifest.
+    Content(String),
     OwnedString(String)
 }
 
@@ -48,6 +58,17 @@ pub enum Operator {
     Mod,
 }
 
+/// The comparisons that `@if` conditions can make. `>` is lexed as [`Token::ScopeToChildren`].
+#[derive(Debug, PartialEq, Clone, Copy, Eq, Hash)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
 #[derive(Logos, Debug, PartialEq, Clone)]
 #[logos(skip r"[\n\f\r ]+")] // Ignore this regex pattern between tokens
 pub enum Token<'a> {
@@ -61,7 +82,7 @@ pub enum Token<'a> {
 
     #[regex(r#"[a-zA-Z0-9"'_-]+"#, |lex| TextType::NonSpecial(lex.slice()), priority = 1)]
     #[regex(r#"#[a-zA-Z0-9"'_-]+"#, |lex| TextType::SelectorName(str_clip(lex.slice(), 1, 0)), priority = 1)]
-    #[regex(r#"\.[a-zA-Z0-9"'_-]+"#, |lex| TextType::SelectorStateOrEnumPart(str_clip(lex.slice(), 1, 0)), priority = 1)]
+    #[regex(r#"\.[a-zA-Z0-9"'_-]+"#, |lex| TextType::SelectorTagOrEnumPart(str_clip(lex.slice(), 1, 0)), priority = 1)]
     #[regex(r#":[a-zA-Z0-9"'_-]+"#, |lex| TextType::SelectorStateOrEnumPart(str_clip(lex.slice(), 1, 0)), priority = 1)]
     #[regex(r#"::[a-zA-Z0-9"'_-]+"#, |lex| TextType::SelectorPsuedo(str_clip(lex.slice(), 2, 0)), priority = 1)]
     #[regex(r#"\$![a-zA-Z0-9"'_-]+"#, |lex| TextType::Argument(str_clip(lex.slice(), 2, 0)), priority = 1)]
@@ -72,8 +93,8 @@ pub enum Token<'a> {
     #[token("Enum")]
     EnumKeyword,
 
-    #[regex(r"tw:(slate|gray|zinc|neutral|stone|red|orange|amber|yellow|lime|green|emerald|teal|cyan|sky|blue|indigo|violet|purple|fuchsia|pink|rose)(:(950|900|800|700|600|500|400|300|200|100|50))?", |lex| DataType::ColorTw(lex.slice()), priority = 2)]
-    #[regex(r"css:(aliceblue|antiquewhite|aqua|aquamarine|azure|beige|bisque|black|blanchedalmond|blue|blueviolet|brown|burlywood|cadetblue|chartreuse|chocolate|coral|cornflowerblue|cornsilk|crimson|cyan|darkblue|darkcyan|darkgoldenrod|darkgray|darkgreen|darkgrey|darkkhaki|darkmagenta|darkolivegreen|darkorange|darkorchid|darkred|darksalmon|darkseagreen|darkslateblue|darkslategray|darkslategrey|darkturquoise|darkviolet|deeppink|deepskyblue|dimgray|dimgrey|dodgerblue|firebrick|floralwhite|forestgreen|fuchsia|gainsboro|ghostwhite|goldenrod|gold|gray|green|greenyellow|grey|honeydew|hotpink|indianred|indigo|ivory|khaki|lavenderblush|lavender|lawngreen|lemonchiffon|lightblue|lightcoral|lightcyan|lightgoldenrodyellow|lightgray|lightgreen|lightgrey|lightpink|lightsalmon|lightseagreen|lightskyblue|lightslategray|lightslategrey|lightsteelblue|lightyellow|lime|limegreen|linen|magenta|maroon|mediumaquamarine|mediumblue|mediumorchid|mediumpurple|mediumseagreen|mediumslateblue|mediumspringgreen|mediumturquoise|mediumvioletred|midnightblue|mintcream|mistyrose|moccasin|navajowhite|navy|oldlace|olive|olivedrab|orange|orangered|orchid|palegoldenrod|palegreen|paleturquoise|palevioletred|papayawhip|peachpuff|peru|pink|plum|powderblue|purple|rebeccapurple|red|rosybrown|royalblue|saddlebrown|salmon|sandybrown|seagreen|seashell|sienna|silver|skyblue|slateblue|slategray|slategrey|snow|springgreen|steelblue|tan|teal|thistle|tomato|turquoise|violet|wheat|white|whitesmoke|yellow|yellowgreen)", |lex| DataType::ColorCss(lex.slice()), priority = 2)]
+    #[regex(r"tw:(slate|gray|zinc|neutral|stone|red|orange|amber|yellow|lime|green|emerald|teal|cyan|sky|blue|indigo|violet|purple|fuchsia|pink|rose)(:(950|900|800|700|600|500|400|300|200|100|50))?(/[0-9]+)?", |lex| DataType::ColorTw(lex.slice()), priority = 2)]
+    #[regex(r"css:(aliceblue|antiquewhite|aqua|aquamarine|azure|beige|bisque|black|blanchedalmond|blue|blueviolet|brown|burlywood|cadetblue|chartreuse|chocolate|coral|cornflowerblue|cornsilk|crimson|cyan|darkblue|darkcyan|darkgoldenrod|darkgray|darkgreen|darkgrey|darkkhaki|darkmagenta|darkolivegreen|darkorange|darkorchid|darkred|darksalmon|darkseagreen|darkslateblue|darkslategray|darkslategrey|darkturquoise|darkviolet|deeppink|deepskyblue|dimgray|dimgrey|dodgerblue|firebrick|floralwhite|forestgreen|fuchsia|gainsboro|ghostwhite|goldenrod|gold|gray|green|greenyellow|grey|honeydew|hotpink|indianred|indigo|ivory|khaki|lavenderblush|lavender|lawngreen|lemonchiffon|lightblue|lightcoral|lightcyan|lightgoldenrodyellow|lightgray|lightgreen|lightgrey|lightpink|lightsalmon|lightseagreen|lightskyblue|lightslategray|lightslategrey|lightsteelblue|lightyellow|lime|limegreen|linen|magenta|maroon|mediumaquamarine|mediumblue|mediumorchid|mediumpurple|mediumseagreen|mediumslateblue|mediumspringgreen|mediumturquoise|mediumvioletred|midnightblue|mintcream|mistyrose|moccasin|navajowhite|navy|oldlace|olive|olivedrab|orange|orangered|orchid|palegoldenrod|palegreen|paleturquoise|palevioletred|papayawhip|peachpuff|peru|pink|plum|powderblue|purple|rebeccapurple|red|rosybrown|royalblue|saddlebrown|salmon|sandybrown|seagreen|seashell|sienna|silver|skyblue|slateblue|slategray|slategrey|snow|springgreen|steelblue|tan|teal|thistle|tomato|turquoise|violet|wheat|white|whitesmoke|yellow|yellowgreen)(/[0-9]+)?", |lex| DataType::ColorCss(lex.slice()), priority = 2)]
     #[regex(r"#[0-9a-fA-F]+", |lex| DataType::ColorHex(lex.slice()))]
     #[regex(r#"'([^'\n\f\r])*'"#, |lex| DataType::StringSingle(str_clip(lex.slice(), 1, 1)))]
     #[regex(r#""([^"\n\f\r])*""#, |lex| DataType::StringSingle(str_clip(lex.slice(), 1, 1)))]
@@ -81,6 +102,8 @@ pub enum Token<'a> {
         Ok(float) => float,
         Err(_) => 0.0 
     }))]
+    #[regex(r"[+-]?([0-9]+([.][0-9]*)?|[.][0-9]+)rem", |lex| DataType::NumberRem(str_clip(lex.slice(), 0, 3).parse::<f64>().unwrap_or(0.0)))]
+    #[regex(r"[+-]?([0-9]+([.][0-9]*)?|[.][0-9]+)em", |lex| DataType::NumberEm(str_clip(lex.slice(), 0, 2).parse::<f64>().unwrap_or(0.0)))]
     #[regex(r"[+-]?([0-9]+([.][0-9]*)?|[.][0-9]+)%", |lex| DataType::NumberScale(match str_clip(lex.slice(), 0, 1).parse::<f64>() {
         Ok(float) => float / 100.0,
         Err(_) => 0.0
@@ -99,6 +122,13 @@ pub enum Token<'a> {
     #[token("%", |_| Operator::Mod)]
     Operator(Operator),
 
+    #[token("==", |_| Comparison::Equal)]
+    #[token("!=", |_| Comparison::NotEqual)]
+    #[token("<", |_| Comparison::Less)]
+    #[token("<=", |_| Comparison::LessOrEqual)]
+    #[token(">=", |_| Comparison::GreaterOrEqual)]
+    Comparison(Comparison),
+
     #[token("{")]
     ScopeOpen,
 
@@ -136,7 +166,25 @@ pub enum Token<'a> {
     PriorityDeclaration,
 
     #[token("@derive")]
-    DeriveDeclaration
+    DeriveDeclaration,
+
+    #[token("@tokens")]
+    TokensDeclaration,
+
+    #[token("@utilities")]
+    UtilitiesDeclaration,
+
+    #[token("@each")]
+    EachDeclaration,
+
+    #[token("@if")]
+    IfDeclaration,
+
+    #[token("@else")]
+    ElseDeclaration,
+
+    #[token("@sheet")]
+    SheetDeclaration
 }
 
 pub type RsmlLexer<'a> = logos::Lexer<'a, Token<'a>>;
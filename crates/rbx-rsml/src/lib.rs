@@ -3,11 +3,61 @@
 
 // Modules -------------------------------------------------------------------------------------------
 mod lexer;
-pub use lexer::lex_rsml;
+pub use lexer::{lex_rsml, Comparison, DataType, Operator, TextType, Token};
 
 mod parser;
-pub use parser::{parse_rsml, TokenTreeNode};
+pub use parser::{color_literal, parse_rsml, NodeId, TokenTreeNode};
 
 pub mod arena;
-pub use arena::Arena;
+pub use arena::{Arena, Id};
+
+mod visit;
+pub use visit::{walk_rule, walk_stylesheet, Ancestors, Descendants, Visitor};
+
+mod edit;
+pub use edit::{edit_rsml, sheet_segment, variant_to_rsml, RuleEdit};
+
+pub mod syntax;
+pub use syntax::parse_syntax;
+
+mod diagnostic;
+pub use diagnostic::{check_syntax, line_column, Diagnostic, Severity};
+
+mod lint;
+pub use lint::{
+    derived_paths, design_token_paths, lint_rsml, lint_stylesheets, root_variables, uses_utilities, variable_references,
+    LintContext, LintRule
+};
+
+mod paths;
+pub use paths::{derived_file_path, design_token_file_path, normalize_path};
+
+mod contrast;
+pub use contrast::{contrast_ratio, ContrastLevel};
+
+mod ast;
+pub use ast::{parse_ast, Rule, Sheet, StyleSheet, AST_VERSION};
+
+mod compile;
+pub use compile::{
+    compile, compile_instance, compile_stylesheet, parse_stylesheet, CompiledInstance, CompileOptions, Diagnostics
+};
+
+mod assets;
+pub use assets::{asset_names, parse_asset_manifest, AssetManifestError};
+
+mod design_tokens;
+pub use design_tokens::{parse_design_tokens, DesignTokenError};
+
+mod css;
+pub use css::{css_to_rsml, CssConversion};
+
+mod utilities;
+pub use utilities::{candidate_tags, utility_rule, utility_rules, UtilityScale};
+
+mod tags;
+pub use tags::{selector_names, SelectorNames};
+
+mod format;
+pub use format::{format_rsml, FormatOptions};
 // ---------------------------------------------------------------------------------------------------
\ No newline at end of file
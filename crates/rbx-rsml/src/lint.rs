@@ -0,0 +1,443 @@
+// Modules -------------------------------------------------------------------------------------------
+use crate::contrast::{check_contrast, ContrastLevel};
+use crate::diagnostic::{check_syntax, Diagnostic, Severity};
+use crate::lexer::{DataType, TextType, Token};
+use crate::parser::{parse_rsml, TokenTreeNode};
+use crate::paths::{derived_file_path, normalize_path};
+use crate::syntax::{parse_syntax, ItemSyntax, StyleSheetSyntax, SyntaxToken};
+
+use rbx_types::Variant;
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::Path;
+// ---------------------------------------------------------------------------------------------------
+
+
+// Data ----------------------------------------------------------------------------------------------
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintRule {
+    UndefinedVariable,
+    UnusedVariable,
+    DuplicateSelector,
+    DuplicateProperty,
+    ShadowedVariable,
+    LowContrast,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 6] = [
+        LintRule::UndefinedVariable,
+        LintRule::UnusedVariable,
+        LintRule::DuplicateSelector,
+        LintRule::DuplicateProperty,
+        LintRule::ShadowedVariable,
+        LintRule::LowContrast,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LintRule::UndefinedVariable => "undefined-variable",
+            LintRule::UnusedVariable => "unused-variable",
+            LintRule::DuplicateSelector => "duplicate-selector",
+            LintRule::DuplicateProperty => "duplicate-property",
+            LintRule::ShadowedVariable => "shadowed-variable",
+            LintRule::LowContrast => "low-contrast",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+
+    pub fn default_severity(self) -> Severity {
+        match self {
+            LintRule::UndefinedVariable => Severity::Error,
+            _ => Severity::Warning
+        }
+    }
+}
+
+/// What a linted file can see of the files around it.
+#[derive(Debug, Clone, Default)]
+pub struct LintContext {
+    /// Variables defined at the root of the stylesheets this one derives from.
+    pub imported_variables: HashSet<String>,
+    /// Root variables of this stylesheet which stylesheets deriving from it use, so they aren't
+    /// reported as unused.
+    pub exported_uses: HashSet<String>,
+    /// Values of the variables in `imported_variables`, where they are known.
+    pub imported_values: HashMap<String, Variant>,
+    /// The WCAG level that text contrast is checked against.
+    pub contrast: ContrastLevel,
+}
+
+struct Variable<'a> {
+    name: &'a str,
+    span: Range<usize>,
+    used: bool,
+}
+
+struct Linter<'c, 'a> {
+    context: &'c LintContext,
+    scopes: Vec<Vec<Variable<'a>>>,
+    diagnostics: Vec<Diagnostic>,
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Helpers -------------------------------------------------------------------------------------------
+fn variable_name<'a>(token: &SyntaxToken<'a>) -> Option<&'a str> {
+    match token.token {
+        Token::Text(TextType::Variable(name)) => Some(name),
+        _ => None
+    }
+}
+
+/// Tokens which can refer to variables, ie. everything except keys and selectors.
+fn value_tokens<'s, 'a>(item: &'s ItemSyntax<'a>) -> &'s [SyntaxToken<'a>] {
+    match item {
+        ItemSyntax::Declaration(declaration) => &declaration.value,
+        ItemSyntax::Other(tokens) => tokens,
+
+        // The loop variable of `@each $name in (...)` is declared rather than referenced.
+        ItemSyntax::Control(control) if control.keyword.token == Token::EachDeclaration => control.header.get(1..).unwrap_or(&[]),
+        ItemSyntax::Control(control) => &control.header,
+
+        _ => &[]
+    }
+}
+
+/// The items inside a rule or a control block.
+fn child_items<'s, 'a>(item: &'s ItemSyntax<'a>) -> &'s [ItemSyntax<'a>] {
+    match item {
+        ItemSyntax::Rule(rule) => &rule.items,
+        ItemSyntax::Control(control) => &control.items,
+        _ => &[]
+    }
+}
+
+fn collect_references<'a>(items: &[ItemSyntax<'a>], references: &mut HashSet<&'a str>) {
+    for item in items {
+        references.extend(value_tokens(item).iter().filter_map(variable_name));
+
+        collect_references(child_items(item), references);
+    }
+}
+
+/// The strings given to `directive` at the root, as in `@derive "path";`.
+fn directive_paths<'a>(syntax: &StyleSheetSyntax<'a>, directive: Token) -> Vec<&'a str> {
+    syntax.items.iter().filter_map(|item| {
+        let ItemSyntax::Other(tokens) = item else { return None };
+
+        match (&tokens[0].token, tokens.get(1).map(|token| &token.token)) {
+            (token, Some(Token::DataType(DataType::StringSingle(path)))) if *token == directive => Some(*path),
+            _ => None
+        }
+    }).collect()
+}
+
+/// The paths of the stylesheets that `@derive "path";` statements at the root pull in.
+pub fn derived_paths<'a>(syntax: &StyleSheetSyntax<'a>) -> Vec<&'a str> {
+    directive_paths(syntax, Token::DeriveDeclaration)
+}
+
+/// The paths of the design token files that `@tokens "path";` statements at the root pull in.
+pub fn design_token_paths<'a>(syntax: &StyleSheetSyntax<'a>) -> Vec<&'a str> {
+    directive_paths(syntax, Token::TokensDeclaration)
+}
+
+/// Whether a stylesheet asks for utility classes with `@utilities;` at its root.
+pub fn uses_utilities(syntax: &StyleSheetSyntax) -> bool {
+    syntax.items.iter().any(|item| matches!(item, ItemSyntax::Other(tokens) if tokens[0].token == Token::UtilitiesDeclaration))
+}
+
+/// The variables declared at the root of a stylesheet, which deriving stylesheets can use.
+pub fn root_variables<'a>(syntax: &StyleSheetSyntax<'a>) -> Vec<&'a str> {
+    syntax.items.iter().filter_map(|item| match item {
+        ItemSyntax::Declaration(declaration) => match declaration.key_text_type() {
+            TextType::Variable(name) => Some(*name),
+            _ => None
+        },
+        _ => None
+    }).collect()
+}
+
+/// Every variable referenced anywhere in a stylesheet.
+pub fn variable_references<'a>(syntax: &StyleSheetSyntax<'a>) -> HashSet<&'a str> {
+    let mut references = HashSet::new();
+    collect_references(&syntax.items, &mut references);
+
+    references
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Linter --------------------------------------------------------------------------------------------
+impl<'c, 'a> Linter<'c, 'a> {
+    fn report(&mut self, rule: LintRule, message: String, span: Range<usize>) {
+        self.diagnostics.push(Diagnostic::lint(rule, message, span));
+    }
+
+    fn is_declared_outside(&self, name: &str) -> bool {
+        self.scopes.iter().flatten().any(|variable| variable.name == name)
+    }
+
+    fn reference(&mut self, token: &SyntaxToken<'a>) {
+        // `rem` units are measured from the stylesheet's `$RootTextSize`, so they count as using it.
+        if let Token::DataType(DataType::NumberRem(_)) = token.token {
+            let root_text_size = self.scopes.first_mut()
+                .and_then(|scope| scope.iter_mut().find(|variable| variable.name == "RootTextSize"));
+            if let Some(variable) = root_text_size { variable.used = true }
+
+            return
+        }
+
+        let Some(name) = variable_name(token) else { return };
+
+        let declaration = self.scopes.iter_mut().rev()
+            .find_map(|scope| scope.iter_mut().find(|variable| variable.name == name));
+
+        match declaration {
+            Some(variable) => variable.used = true,
+
+            None if self.context.imported_variables.contains(name) => (),
+
+            None => self.report(
+                LintRule::UndefinedVariable,
+                format!("`${}` is not defined in this rule, an enclosing rule or a derived stylesheet", name),
+                token.span.clone()
+            )
+        }
+    }
+
+    fn scope(&mut self, items: &[ItemSyntax<'a>], is_root: bool) {
+        let mut variables = vec![];
+        let mut assigned = HashSet::new();
+        let mut selectors = HashMap::new();
+
+        for item in items {
+            match item {
+                ItemSyntax::Declaration(declaration) => {
+                    let key = &declaration.key;
+
+                    if !assigned.insert(key.text) {
+                        self.report(
+                            LintRule::DuplicateProperty,
+                            format!("`{}` is already assigned in this rule, so only the last value is used", key.text),
+                            key.span.clone()
+                        );
+                    }
+
+                    if let TextType::Variable(name) = declaration.key_text_type() {
+                        if self.is_declared_outside(name) {
+                            self.report(
+                                LintRule::ShadowedVariable,
+                                format!("`${}` shadows a variable from an enclosing scope", name),
+                                key.span.clone()
+                            );
+                        }
+
+                        // Only the first of several assignments gets reported if it is unused.
+                        if !variables.iter().any(|variable: &Variable| variable.name == *name) {
+                            variables.push(Variable { name, span: key.span.clone(), used: false });
+                        }
+                    }
+                },
+
+                ItemSyntax::Rule(rule) => {
+                    let selector = rule.selector_text();
+
+                    if selectors.insert(selector.clone(), ()).is_some() {
+                        self.report(
+                            LintRule::DuplicateSelector,
+                            format!("another rule in this scope already uses the selector `{}`", selector),
+                            rule.selector_span()
+                        );
+                    }
+                },
+
+                _ => ()
+            }
+        }
+
+        self.scopes.push(variables);
+
+        for item in items {
+            for token in value_tokens(item) { self.reference(token); }
+
+            match item {
+                ItemSyntax::Rule(rule) => self.scope(&rule.items, false),
+
+                // Control blocks get a scope of their own, holding the loop variable of `@each`.
+                ItemSyntax::Control(control) => {
+                    let loop_variable = match (&control.keyword.token, control.header.first()) {
+                        (Token::EachDeclaration, Some(token)) => variable_name(token)
+                            .map(|name| Variable { name, span: token.span.clone(), used: true }),
+                        _ => None
+                    };
+
+                    self.scopes.push(loop_variable.into_iter().collect());
+                    self.scope(&control.items, false);
+                    self.scopes.pop();
+                },
+
+                _ => ()
+            }
+        }
+
+        let variables = self.scopes.pop().unwrap();
+
+        for variable in variables {
+            if variable.used || (is_root && self.context.exported_uses.contains(variable.name)) { continue }
+
+            self.report(LintRule::UnusedVariable, format!("`${}` is never used", variable.name), variable.span);
+        }
+    }
+}
+
+/// Checks a stylesheet for syntax errors and for likely mistakes.
+pub fn lint_rsml(syntax: &StyleSheetSyntax, context: &LintContext) -> Vec<Diagnostic> {
+    let mut linter = Linter { context, scopes: vec![], diagnostics: check_syntax(syntax) };
+    linter.scope(&syntax.items, true);
+
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.extend(check_contrast(syntax, context));
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+
+    diagnostics
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Stylesheets ---------------------------------------------------------------------------------------
+/// Every stylesheet that the given one derives from, directly or through other stylesheets.
+fn derived_ancestors(derives: &[Vec<usize>], index: usize) -> Vec<usize> {
+    let mut visited = HashSet::new();
+    let mut stack = derives[index].clone();
+    let mut ancestors = vec![];
+
+    while let Some(ancestor) = stack.pop() {
+        if ancestor == index || !visited.insert(ancestor) { continue }
+
+        ancestors.push(ancestor);
+        stack.extend(&derives[ancestor]);
+    }
+
+    ancestors
+}
+
+/// Lints a set of stylesheets together, so that variables shared through `@derive` count as
+/// defined and used. Returns the diagnostics of each stylesheet in the order they were given.
+pub fn lint_stylesheets(stylesheets: &[(&Path, &str)], contrast: ContrastLevel) -> Vec<Vec<Diagnostic>> {
+    let syntaxes = stylesheets.iter().map(|(_, source)| parse_syntax(source)).collect::<Vec<_>>();
+
+    let indices = stylesheets.iter().enumerate()
+        .map(|(index, (path, _))| (normalize_path(path), index))
+        .collect::<HashMap<_, _>>();
+
+    let derives = stylesheets.iter().zip(&syntaxes).map(|((path, _), syntax)| {
+        derived_paths(syntax).into_iter()
+            .filter_map(|derived| indices.get(&derived_file_path(path, derived)).copied())
+            .collect::<Vec<_>>()
+    }).collect::<Vec<_>>();
+
+    let mut contexts = vec![LintContext { contrast, ..Default::default() }; stylesheets.len()];
+
+    for (index, syntax) in syntaxes.iter().enumerate() {
+        let references = variable_references(syntax);
+
+        for ancestor in derived_ancestors(&derives, index) {
+            let context = &mut contexts[index];
+            context.imported_variables.extend(root_variables(&syntaxes[ancestor]).into_iter().map(str::to_owned));
+
+            let tokens = syntaxes[ancestor].tokens().into_iter().map(|token| token.token.clone()).collect::<Vec<_>>();
+            if let Some(root) = parse_rsml(&tokens).get(TokenTreeNode::ROOT) {
+                context.imported_values.extend(root.variables.iter().map(|(name, value)| (name.to_string(), value.clone())));
+            }
+
+            contexts[ancestor].exported_uses.extend(references.iter().map(|name| name.to_string()));
+        }
+    }
+
+    syntaxes.iter().zip(&contexts).map(|(syntax, context)| lint_rsml(syntax, context)).collect()
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::parse_syntax;
+
+    fn lint(source: &str, context: &LintContext) -> Vec<(Option<LintRule>, String)> {
+        lint_rsml(&parse_syntax(source), context).into_iter()
+            .map(|diagnostic| (diagnostic.rule, source[diagnostic.span].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn variables() {
+        let source = "$Gap = 4px; $Unused = 1;\nFrame { $Gap = 8px; Size = $Gap; Child { Color = $Missing; } }";
+
+        assert_eq!(lint(source, &LintContext::default()), [
+            (Some(LintRule::UnusedVariable), String::from("$Gap")),
+            (Some(LintRule::UnusedVariable), String::from("$Unused")),
+            (Some(LintRule::ShadowedVariable), String::from("$Gap")),
+            (Some(LintRule::UndefinedVariable), String::from("$Missing")),
+        ]);
+    }
+
+    #[test]
+    fn root_text_size() {
+        let source = "$RootTextSize = 18;\nTextLabel { TextSize = 2rem; Padding = udim(0, 1em); }";
+
+        assert!(lint(source, &LintContext::default()).is_empty());
+        assert_eq!(lint("$RootTextSize = 18;\nTextLabel { TextSize = 2; }", &LintContext::default()), [
+            (Some(LintRule::UnusedVariable), String::from("$RootTextSize")),
+        ]);
+    }
+
+    #[test]
+    fn control_blocks() {
+        let source = "$Gap = 4px;\n@each $size in (1, $Gap) { .p-$size { Size = $size; } }\n@if $Missing { Frame { Size = $size; } }";
+
+        assert_eq!(lint(source, &LintContext::default()), [
+            (Some(LintRule::UndefinedVariable), String::from("$Missing")),
+            (Some(LintRule::UndefinedVariable), String::from("$size")),
+        ]);
+    }
+
+    #[test]
+    fn duplicates() {
+        let source = "Frame { Size = 1; Size = 2; } Frame { } TextLabel { Frame { } }";
+
+        assert_eq!(lint(source, &LintContext::default()), [
+            (Some(LintRule::DuplicateProperty), String::from("Size")),
+            (Some(LintRule::DuplicateSelector), String::from("Frame")),
+        ]);
+    }
+
+    #[test]
+    fn derived_stylesheets() {
+        let source = "@derive \"theme\";\n$Exported = 1;\nFrame { Color = $Accent; }";
+        let syntax = parse_syntax(source);
+        assert_eq!(derived_paths(&syntax), ["theme"]);
+
+        let context = LintContext {
+            imported_variables: HashSet::from([String::from("Accent")]),
+            exported_uses: HashSet::from([String::from("Exported")]),
+            ..Default::default()
+        };
+        assert!(lint(source, &context).is_empty());
+    }
+
+    #[test]
+    fn stylesheets_together() {
+        let theme = (Path::new("/theme.rsml"), "$Accent = #ff0000;");
+        let button = (Path::new("/ui/button.rsml"), "@derive \"../theme\";\nFrame { Color = $Accent; }");
+
+        assert_eq!(lint_stylesheets(&[theme, button], ContrastLevel::AA), [vec![], vec![]]);
+        assert_eq!(lint_stylesheets(&[button], ContrastLevel::AA)[0][0].rule, Some(LintRule::UndefinedVariable));
+    }
+}
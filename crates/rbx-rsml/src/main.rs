@@ -1,18 +1,604 @@
 // Modules -------------------------------------------------------------------------------------------
-use rbx_rsml::{lex_rsml, parse_rsml};
+use rbx_rsml::{
+    asset_names, check_syntax, compile, css_to_rsml, derived_file_path, derived_paths, design_token_file_path, design_token_paths,
+    format_rsml, line_column, lint_stylesheets, parse_asset_manifest, parse_ast, parse_design_tokens, parse_syntax, CompileOptions,
+    ContrastLevel, Diagnostic, FormatOptions, Severity
+};
 
-use std::fs;
+use anyhow::{bail, format_err, Context};
+use clap::Parser;
+use globset::Glob;
+use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::WeakDom;
+use serde_json::{json, Value};
+
+use std::collections::BTreeMap;
+use std::io::BufWriter;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+// ---------------------------------------------------------------------------------------------------
+
+
+// Commands ------------------------------------------------------------------------------------------
+/// Checks, compiles and formats RSML stylesheets.
+#[derive(Debug, Parser)]
+#[clap(name = "rbx_rsml", about)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Parser)]
+enum Command {
+    /// Reports syntax errors and lint warnings, and fails if there are any errors.
+    Check(CheckCommand),
+    /// Compiles stylesheets into rbxm, rbxmx or model.json files.
+    Compile(CompileCommand),
+    /// Prints the tokens of stylesheets as JSON.
+    Tokens(InputPaths),
+    /// Prints stylesheets as JSON, in the same shape as `rbx_rsml::StyleSheet`.
+    Ast(InputPaths),
+    /// Formats stylesheets in place.
+    Fmt(FmtCommand),
+    /// Converts CSS files into RSML stylesheets, warning about anything that can't be converted.
+    Css(CssCommand),
+}
+
+/// Stylesheets to work on, given as files, directories to search or glob patterns.
+#[derive(Debug, Parser)]
+struct InputPaths {
+    #[clap(required = true)]
+    paths: Vec<String>,
+}
+
+#[derive(Debug, Parser)]
+struct CheckCommand {
+    #[clap(flatten)]
+    inputs: InputPaths,
+
+    /// The WCAG level that text contrast is checked against, AA or AAA.
+    #[clap(long, default_value = "AA")]
+    contrast: ContrastArgument,
+}
+
+#[derive(Debug, Parser)]
+struct CompileCommand {
+    #[clap(flatten)]
+    inputs: InputPaths,
+
+    /// Where to write the compiled model. This is a file when compiling a single stylesheet, and a
+    /// directory otherwise. Defaults to next to each stylesheet.
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+
+    /// The kind of model to write: rbxm, rbxmx or model.json. Defaults to the extension of the
+    /// output file, or rbxm.
+    #[clap(long)]
+    format: Option<ModelFormat>,
+
+    /// An asset manifest to look up `asset("name")` in.
+    #[clap(long)]
+    assets: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser)]
+struct FmtCommand {
+    #[clap(flatten)]
+    inputs: InputPaths,
+
+    /// Lists stylesheets which aren't formatted instead of changing them.
+    #[clap(long)]
+    check: bool,
+
+    /// Sorts the properties in each rule alphabetically.
+    #[clap(long)]
+    sort_properties: bool,
+}
+
+#[derive(Debug, Parser)]
+struct CssCommand {
+    #[clap(flatten)]
+    inputs: InputPaths,
+
+    /// The directory to write the converted stylesheets to. Defaults to next to each CSS file.
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+struct ContrastArgument(ContrastLevel);
+
+impl FromStr for ContrastArgument {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "AA" => Ok(ContrastArgument(ContrastLevel::AA)),
+            "AAA" => Ok(ContrastArgument(ContrastLevel::AAA)),
+            _ => Err(format_err!("Invalid contrast level '{}'. Valid levels are: AA, AAA", source))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModelFormat {
+    Binary,
+    Xml,
+    Json,
+}
+
+impl ModelFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ModelFormat::Binary => "rbxm",
+            ModelFormat::Xml => "rbxmx",
+            ModelFormat::Json => "model.json",
+        }
+    }
+
+    fn from_path(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?;
+
+        [ModelFormat::Json, ModelFormat::Xml, ModelFormat::Binary].into_iter()
+            .find(|format| file_name.ends_with(&format!(".{}", format.extension())))
+    }
+}
+
+impl FromStr for ModelFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "rbxm" => Ok(ModelFormat::Binary),
+            "rbxmx" => Ok(ModelFormat::Xml),
+            "model.json" => Ok(ModelFormat::Json),
+            _ => Err(format_err!("Invalid model format '{}'. Valid formats are: rbxm, rbxmx, model.json", source))
+        }
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Inputs --------------------------------------------------------------------------------------------
+fn collect_files(directory: &Path, extension: &str, paths: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in fs_err::read_dir(directory)? {
+        let path = entry?.path();
+        let is_hidden = path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with('.'));
+
+        if path.is_dir() {
+            if !is_hidden { collect_files(&path, extension, paths)?; }
+        } else if path.extension().is_some_and(|path_extension| path_extension == extension) {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// The characters that make an input a glob pattern rather than a path.
+const GLOB_CHARACTERS: [char; 4] = ['*', '?', '[', '{'];
+
+/// The directory a glob pattern starts searching from: every component before the first wildcard.
+fn glob_base(pattern: &str) -> PathBuf {
+    Path::new(pattern).components()
+        .take_while(|component| !component.as_os_str().to_string_lossy().contains(GLOB_CHARACTERS))
+        .collect()
+}
+
+impl InputPaths {
+    /// The files the inputs refer to. Directories and glob patterns only find files with `extension`.
+    fn expand(&self, extension: &str) -> anyhow::Result<Vec<PathBuf>> {
+        let mut paths = vec![];
+
+        for input in &self.paths {
+            let path = Path::new(input);
+
+            if path.is_file() {
+                paths.push(path.to_path_buf());
+                continue
+            }
+
+            if path.is_dir() {
+                collect_files(path, extension, &mut paths)?;
+                continue
+            }
+
+            if !input.contains(GLOB_CHARACTERS) { bail!("No such file or directory '{}'", input) }
+
+            let matcher = Glob::new(input).with_context(|| format!("Invalid glob pattern '{}'", input))?.compile_matcher();
+            let base = glob_base(input);
+            let search_root = if base.as_os_str().is_empty() { Path::new(".") } else { base.as_path() };
+
+            let mut candidates = vec![];
+            collect_files(search_root, extension, &mut candidates)?;
+
+            let matched = candidates.into_iter()
+                .map(|candidate| candidate.strip_prefix(".").map(Path::to_path_buf).unwrap_or(candidate))
+                .filter(|candidate| matcher.is_match(candidate))
+                .collect::<Vec<_>>();
+
+            if matched.is_empty() { bail!("No .{} files match '{}'", extension, input) }
+            paths.extend(matched);
+        }
+
+        paths.sort();
+        paths.dedup();
+
+        Ok(paths)
+    }
+
+    fn read(&self, extension: &str) -> anyhow::Result<Vec<(PathBuf, String)>> {
+        self.expand(extension)?.into_iter()
+            .map(|path| {
+                let source = fs_err::read_to_string(&path)?;
+                Ok((path, source))
+            })
+            .collect()
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Output --------------------------------------------------------------------------------------------
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    }
+}
+
+fn print_diagnostic(path: &Path, source: &str, diagnostic: &Diagnostic) {
+    let (line, column) = line_column(source, diagnostic.span.start);
+
+    let label = match diagnostic.rule {
+        Some(rule) => format!("{}[{}]", severity_name(diagnostic.severity), rule.name()),
+        None => severity_name(diagnostic.severity).to_owned()
+    };
+
+    eprintln!("{}: {}\n  --> {}:{}:{}\n", label, diagnostic.message, path.display(), line, column);
+}
+
+fn span_json(span: Range<usize>) -> Value {
+    json!([span.start, span.end])
+}
+
+fn token_kind(debug: String) -> String {
+    match debug.find('(') {
+        Some(index) => debug[..index].to_owned(),
+        None => debug
+    }
+}
+
+/// The instance in the shape of a Rojo `.model.json` file. Properties are written fully qualified,
+/// so Rojo doesn't need to look their types up.
+fn model_json(dom: &WeakDom, referent: Ref, is_root: bool) -> Value {
+    let instance = dom.get_by_ref(referent).unwrap();
+    let properties = instance.properties.iter().collect::<BTreeMap<_, _>>();
+
+    let mut model = json!({ "className": instance.class, "properties": properties });
+
+    // Rojo names the top-level instance after the file.
+    if !is_root { model["name"] = json!(instance.name); }
+
+    if !instance.children().is_empty() {
+        model["children"] = instance.children().iter().map(|child| model_json(dom, *child, false)).collect();
+    }
+
+    model
+}
+
+fn write_model(dom: &WeakDom, format: ModelFormat, path: &Path) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs_err::create_dir_all(parent)?;
+    }
+
+    let mut file = BufWriter::new(fs_err::File::create(path)?);
+    let root = [dom.root_ref()];
+
+    match format {
+        ModelFormat::Binary => rbx_binary::to_writer(&mut file, dom, &root)?,
+        ModelFormat::Xml => rbx_xml::to_writer_default(&mut file, dom, &root)?,
+        ModelFormat::Json => serde_json::to_writer_pretty(&mut file, &model_json(dom, root[0], true))?,
+    }
+
+    Ok(())
+}
+
+/// The variables from the design token files that the stylesheet pulls in with `@tokens`.
+fn imported_design_tokens(path: &Path, source: &str) -> anyhow::Result<BTreeMap<String, Variant>> {
+    let mut variables = BTreeMap::new();
+
+    for tokens in design_token_paths(&parse_syntax(source)) {
+        let tokens_path = design_token_file_path(path, tokens);
+        let json = fs_err::read_to_string(&tokens_path)?;
+
+        variables.extend(parse_design_tokens(&json)
+            .with_context(|| format!("Could not read design tokens from {}", tokens_path.display()))?);
+    }
+
+    Ok(variables)
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Run -----------------------------------------------------------------------------------------------
+impl CheckCommand {
+    fn run(self) -> anyhow::Result<()> {
+        let mut files = self.inputs.read("rsml")?;
+        let checked = files.len();
+
+        // Stylesheets that are derived from are linted alongside, so their variables are known.
+        let mut index = 0;
+        while index < files.len() {
+            let derived = derived_paths(&parse_syntax(&files[index].1)).into_iter()
+                .map(|derived| derived_file_path(&files[index].0, derived))
+                .collect::<Vec<_>>();
+
+            for path in derived {
+                if files.iter().any(|(existing, _)| *existing == path) || !path.is_file() { continue }
+
+                let source = fs_err::read_to_string(&path)?;
+                files.push((path, source));
+            }
+
+            index += 1;
+        }
+
+        let stylesheets = files.iter().map(|(path, source)| (path.as_path(), source.as_str())).collect::<Vec<_>>();
+        let results = lint_stylesheets(&stylesheets, self.contrast.0);
+
+        let (mut errors, mut warnings) = (0, 0);
+
+        for ((path, source), diagnostics) in files.iter().zip(&results).take(checked) {
+            for diagnostic in diagnostics {
+                match diagnostic.severity {
+                    Severity::Error => errors += 1,
+                    Severity::Warning => warnings += 1,
+                }
+
+                print_diagnostic(path, source, diagnostic);
+            }
+        }
+
+        eprintln!("Checked {} stylesheet(s): {} error(s), {} warning(s)", checked, errors, warnings);
+
+        if errors > 0 { bail!("Found {} error(s) in stylesheets", errors) }
+
+        Ok(())
+    }
+}
+
+impl CompileCommand {
+    fn output_path(&self, input: &Path, format: ModelFormat, is_single: bool) -> PathBuf {
+        let stem = input.file_stem().unwrap().to_string_lossy();
+        let file_name = format!("{}.{}", stem, format.extension());
+
+        match &self.output {
+            Some(output) if is_single && ModelFormat::from_path(output).is_some() => output.clone(),
+            Some(output) => output.join(file_name),
+            None => input.with_file_name(file_name)
+        }
+    }
+
+    fn run(self) -> anyhow::Result<()> {
+        let files = self.inputs.read("rsml")?;
+        let is_single = files.len() == 1;
+
+        let format = self.format
+            .or_else(|| self.output.as_deref().filter(|_| is_single).and_then(ModelFormat::from_path))
+            .unwrap_or(ModelFormat::Binary);
+
+        let assets = match &self.assets {
+            Some(manifest) => parse_asset_manifest(&fs_err::read_to_string(manifest)?)
+                .with_context(|| format!("Could not read the asset manifest {}", manifest.display()))?,
+            None => BTreeMap::new()
+        };
+
+        let mut failed = 0;
+
+        for (path, source) in &files {
+            let stylesheet = parse_ast(source);
+            let missing = asset_names(&stylesheet).into_iter()
+                .filter(|name| !assets.contains_key(*name))
+                .collect::<Vec<_>>();
+
+            match (missing.is_empty(), &self.assets) {
+                (true, _) => (),
+                (false, Some(manifest)) => bail!("{} uses assets that aren't in {}: {}", path.display(), manifest.display(), missing.join(", ")),
+                (false, None) => bail!("{} looks up assets by name, so it needs an asset manifest from --assets", path.display())
+            }
+
+            let options = CompileOptions {
+                name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+                imported_variables: imported_design_tokens(path, source)?,
+                assets: assets.clone(),
+                ..Default::default()
+            };
+
+            let dom = match compile(source, &options) {
+                Ok(dom) => dom,
+                Err(diagnostics) => {
+                    for diagnostic in &diagnostics.0 { print_diagnostic(path, source, diagnostic); }
+                    failed += 1;
+                    continue
+                }
+            };
+
+            let output = self.output_path(path, format, is_single);
+            write_model(&dom, format, &output).with_context(|| format!("Could not write {}", output.display()))?;
+        }
+
+        if failed > 0 { bail!("{} stylesheet(s) could not be compiled", failed) }
+
+        Ok(())
+    }
+}
+
+impl FmtCommand {
+    fn run(self) -> anyhow::Result<()> {
+        let options = FormatOptions { sort_properties: self.sort_properties, ..Default::default() };
+        let mut unformatted = vec![];
+        let mut failed = 0;
+
+        for (path, source) in self.inputs.read("rsml")? {
+            // The formatter can't keep what it doesn't understand, so files with errors are left alone.
+            let errors = check_syntax(&parse_syntax(&source)).into_iter()
+                .filter(|diagnostic| diagnostic.severity == Severity::Error)
+                .collect::<Vec<_>>();
+            if !errors.is_empty() {
+                for diagnostic in &errors { print_diagnostic(&path, &source, diagnostic); }
+                failed += 1;
+                continue
+            }
+
+            let formatted = format_rsml(&source, &options);
+            if formatted == source { continue }
+
+            match self.check {
+                true => unformatted.push(path),
+                false => fs_err::write(&path, formatted)?
+            }
+        }
+
+        for path in &unformatted { eprintln!("{}", path.display()); }
+
+        if failed > 0 { bail!("{} stylesheet(s) have syntax errors and were not formatted", failed) }
+        if !unformatted.is_empty() { bail!("{} stylesheet(s) are not formatted", unformatted.len()) }
+
+        Ok(())
+    }
+}
+
+impl CssCommand {
+    fn run(self) -> anyhow::Result<()> {
+        let files = self.inputs.read("css")?;
+        let mut warnings = 0;
+
+        for (path, source) in &files {
+            let conversion = css_to_rsml(source);
+
+            for diagnostic in &conversion.diagnostics { print_diagnostic(path, source, diagnostic); }
+            warnings += conversion.diagnostics.len();
+
+            let file_name = format!("{}.rsml", path.file_stem().unwrap().to_string_lossy());
+            let output = match &self.output {
+                Some(output) => output.join(file_name),
+                None => path.with_file_name(file_name)
+            };
+
+            fs_err::write(&output, conversion.rsml)?;
+        }
+
+        eprintln!("Converted {} file(s): {} warning(s)", files.len(), warnings);
+
+        Ok(())
+    }
+}
+
+fn tokens(inputs: InputPaths) -> anyhow::Result<()> {
+    let files = inputs.read("rsml")?.into_iter().map(|(path, source)| {
+        let syntax = parse_syntax(&source);
+
+        let tokens = syntax.tokens().into_iter().map(|token| json!({
+            "kind": token_kind(format!("{:?}", token.token)),
+            "text": token.text,
+            "span": span_json(token.span.clone()),
+        })).collect::<Vec<_>>();
+
+        json!({ "path": path, "tokens": tokens })
+    }).collect::<Vec<_>>();
+
+    println!("{}", serde_json::to_string_pretty(&files)?);
+
+    Ok(())
+}
+
+fn ast(inputs: InputPaths) -> anyhow::Result<()> {
+    let files = inputs.read("rsml")?.into_iter().map(|(path, source)| {
+        json!({ "path": path, "ast": parse_ast(&source) })
+    }).collect::<Vec<_>>();
+
+    println!("{}", serde_json::to_string_pretty(&files)?);
+
+    Ok(())
+}
+
+fn run(command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::Check(command) => command.run(),
+        Command::Compile(command) => command.run(),
+        Command::Tokens(inputs) => tokens(inputs),
+        Command::Ast(inputs) => ast(inputs),
+        Command::Fmt(command) => command.run(),
+        Command::Css(command) => command.run(),
+    }
+}
 // ---------------------------------------------------------------------------------------------------
 
 
 fn main() {
-    let source = &fs::read_to_string("./src/styles.rsml").unwrap();
+    let cli = Cli::parse();
+
+    if let Err(error) = run(cli.command) {
+        eprintln!("error: {:?}", error);
+        process::exit(1);
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn glob_bases() {
+        assert_eq!(glob_base("src/ui/**/*.rsml"), Path::new("src/ui"));
+        assert_eq!(glob_base("*.rsml"), Path::new(""));
+    }
+
+    #[test]
+    fn missing_inputs() {
+        let inputs = InputPaths { paths: vec![String::from("nonexist.rsml")] };
+
+        let error = inputs.expand("rsml").unwrap_err();
+        assert_eq!(error.to_string(), "No such file or directory 'nonexist.rsml'");
+    }
+
+    #[test]
+    fn output_paths() {
+        let command = |output: Option<&str>| CompileCommand {
+            inputs: InputPaths { paths: vec![] },
+            output: output.map(PathBuf::from),
+            format: None,
+            assets: None,
+        };
+        let input = Path::new("src/theme.rsml");
+
+        assert_eq!(ModelFormat::from_path(Path::new("out/theme.model.json")), Some(ModelFormat::Json));
+        assert_eq!(command(None).output_path(input, ModelFormat::Binary, true), Path::new("src/theme.rbxm"));
+        assert_eq!(command(Some("out/a.rbxmx")).output_path(input, ModelFormat::Xml, true), Path::new("out/a.rbxmx"));
+        assert_eq!(command(Some("out")).output_path(input, ModelFormat::Json, false), Path::new("out/theme.model.json"));
+    }
+
+    #[test]
+    fn format_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let broken = dir.path().join("broken.rsml");
+        let messy = dir.path().join("messy.rsml");
+
+        fs_err::write(&broken, "Frame {  Size = 1;\n").unwrap();
+        fs_err::write(&messy, "Frame {Size=1;}\n").unwrap();
 
-    let tokens = lex_rsml(source);
-    println!("{:#?}", tokens);
+        let inputs = InputPaths { paths: vec![dir.path().display().to_string()] };
+        let command = FmtCommand { inputs, check: false, sort_properties: false };
 
-    let nodes = parse_rsml(&tokens);
-    println!("{:#?}", nodes);
+        assert!(command.run().is_err());
+        assert_eq!(fs_err::read_to_string(&broken).unwrap(), "Frame {  Size = 1;\n");
+        assert_eq!(fs_err::read_to_string(&messy).unwrap(), "Frame {\n    Size = 1;\n}\n");
+    }
 }
 
 
@@ -30,4 +616,4 @@ fn main() {
 | Number | NumberScale | NumberScale |
 | Number | NumberOffset | NumberScale |
 
-*/
\ No newline at end of file
+*/
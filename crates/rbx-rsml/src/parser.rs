@@ -1,28 +1,75 @@
 // Modules -------------------------------------------------------------------------------------------
-use crate::arena::Arena;
-use crate::lexer::{DataType, Operator, TextType, Token};
+use crate::arena::{Arena, Id};
+use crate::assets::asset_id_url;
+use crate::lexer::{Comparison, DataType, Operator, TextType, Token};
+use crate::syntax::{is_control_token, is_selector_token};
 
 use colors_transform::{Rgb, Color};
-use rbx_types::{Color3, Font, FontStyle, FontWeight, Rect, UDim, UDim2, Variant, Vector2, Vector3};
+use rbx_reflection::DataType as PropertyType;
+use rbx_types::{Color3, Content, Font, FontStyle, FontWeight, Rect, UDim, UDim2, Variant, VariantType, Vector2, Vector3};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::LazyLock;
 // ---------------------------------------------------------------------------------------------------
 
 
 // Globals -------------------------------------------------------------------------------------------
-const TAILWIND_COLORS: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
+static TAILWIND_COLORS: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
     serde_json::from_slice(include_bytes!("../tailwind_colors.json"))
         .expect("Could not read tailwind_colors.json file.")
 });
 
-const CSS_COLORS: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
+static CSS_COLORS: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
     serde_json::from_slice(include_bytes!("../css_colors.json"))
         .expect("Could not read css_colors.json file.")
 });
+
+/// The `*Transparency` property that goes with each `*Color3` property, like `BackgroundTransparency`
+/// for `BackgroundColor3`. A pair is any two of these which a class in the reflection database has.
+static TRANSPARENCY_PROPERTIES: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
+    let mut pairs = HashMap::new();
+
+    for class in rbx_reflection_database::get().classes.values() {
+        for name in class.properties.keys() {
+            let Some(prefix) = name.strip_suffix("Color3") else { continue };
+
+            let transparency = format!("{}Transparency", prefix);
+            if class.properties.contains_key(transparency.as_str()) { pairs.insert(name.to_string(), transparency); }
+        }
+    }
+
+    pairs
+});
+
+/// The properties which hold a number, like `TextSize`. Offsets set on these, like `2rem`, are set as
+/// their number of pixels instead. Names which any class has a property of another type with, like
+/// `Size`, are left out.
+static NUMBER_PROPERTIES: LazyLock<HashSet<String>> = LazyLock::new(|| {
+    let properties = rbx_reflection_database::get().classes.values().flat_map(|class| class.properties.values());
+
+    let mut numbers = HashSet::new();
+    let mut others = HashSet::new();
+
+    for property in properties {
+        match property.data_type {
+            PropertyType::Value(VariantType::Float32 | VariantType::Float64 | VariantType::Int32 | VariantType::Int64) => {
+                numbers.insert(property.name.to_string());
+            },
+            _ => { others.insert(property.name.to_string()); }
+        }
+    }
+
+    numbers.retain(|name| !others.contains(name));
+    numbers
+});
+
+/// The text size that `rem` and `em` are measured in when a stylesheet doesn't set `$RootTextSize`,
+/// which is the same as browsers use.
+const DEFAULT_TEXT_SIZE: f64 = 16.0;
 // ---------------------------------------------------------------------------------------------------
 
 
+
 // Data ----------------------------------------------------------------------------------------------
 #[derive(Clone, Debug)]
 enum EquationDataType<'a> {
@@ -35,16 +82,14 @@ enum EquationDataType<'a> {
 #[derive(Debug, Clone)]
 struct TupleDataType<'a> {
     name: Option<&'a str>,
-    data: Vec<DataType<'a>>,
-    parent_idx: Option<usize>
+    data: Vec<DataType<'a>>
 }
 
 impl<'a> TupleDataType<'a> {
-    fn new(name: Option<&'a str>, parent_idx: Option<usize>) -> Self {
+    fn new(name: Option<&'a str>) -> Self {
         Self {
             name,
-            data: vec![],
-            parent_idx
+            data: vec![]
         }
     }
 
@@ -57,15 +102,18 @@ impl<'a> TupleDataType<'a> {
     }
 }
 
+/// A handle to a rule in the tree built by [`parse_rsml`].
+pub type NodeId<'a> = Id<TokenTreeNode<'a>>;
+
 #[derive(Debug)]
-pub struct TokenTreeNodeRulesHashMap(pub HashMap<String, Vec<usize>>);
+pub struct TokenTreeNodeRulesHashMap<'a>(pub HashMap<String, Vec<NodeId<'a>>>);
 
-impl<'a> TokenTreeNodeRulesHashMap {
+impl<'a> TokenTreeNodeRulesHashMap<'a> {
     fn new() -> Self {
         Self(HashMap::new())
     }
 
-    fn insert(&mut self, selector: String, node_idx: usize) {
+    fn insert(&mut self, selector: String, node_idx: NodeId<'a>) {
         let rules = self.0.entry(selector).or_insert(vec![]);
 
         rules.push(node_idx);
@@ -80,29 +128,35 @@ impl<'a> TokenTreeNodeMacrosHashMap<'a> {
     fn new() -> Self {
         Self(HashMap::new())
     }
-
-    /*fn insert(&mut self, macro_name: &'a str, args: Option<u64>, node_idx: usize) {
-        let macro_hashmap = self.0.entry(macro_name).or_insert(HashMap::new());
-        let args_hashmap = macro_hashmap.entry(args).or_insert(vec![]);
-
-        args_hashmap.push(node_idx);
-    }*/
 }
 
 #[derive(Debug)]
-pub struct TokenTreeNode<'a> { 
+pub struct TokenTreeNode<'a> {
     pub properties: HashMap<&'a str, Variant>,
     pub variables: HashMap<&'a str, Variant>,
     pub psuedo_properties: HashMap<&'a str, Variant>,
-    pub rules: TokenTreeNodeRulesHashMap,
+    pub rules: TokenTreeNodeRulesHashMap<'a>,
     pub macros: TokenTreeNodeMacrosHashMap<'a>,
     pub default_args: Option<(Variant,)>,
     pub priority: Option<i32>,
-    pub parent_idx: usize
+    /// The `@sheet` blocks inside this node by name, in the order they appear in the source. Only the
+    /// root has any.
+    pub sheets: Vec<(&'a str, NodeId<'a>)>,
+    /// Whether the rule was declared inside of an `@each` or `@if` block, so it isn't written out in
+    /// the source as it is.
+    pub generated: bool,
+    /// The index of the first token of the rule's selector, or of the `@sheet` block's name, in the
+    /// tokens it was parsed from. Rules generated from the same source share it.
+    pub selector_token: usize,
+    /// The rule this one is inside of. The root is its own parent.
+    pub parent_idx: NodeId<'a>
 }
 
 impl<'a> TokenTreeNode<'a> {
-    fn new(parent_idx: usize) -> TokenTreeNode<'a> {
+    /// The node for the stylesheet itself, which every rule is inside of.
+    pub const ROOT: NodeId<'a> = Id::new(0);
+
+    fn new(parent_idx: NodeId<'a>) -> TokenTreeNode<'a> {
         TokenTreeNode {
             properties: HashMap::new(),
             variables: HashMap::new(),
@@ -111,140 +165,37 @@ impl<'a> TokenTreeNode<'a> {
             macros: TokenTreeNodeMacrosHashMap::new(),
             default_args: None,
             priority: None,
+            sheets: vec![],
+            generated: false,
+            selector_token: 0,
             parent_idx
         }
     }
 
-    fn insert_rule(&mut self, selector: String, node_idx: usize) {
+    fn insert_rule(&mut self, selector: String, node_idx: NodeId<'a>) {
         self.rules.insert(selector, node_idx);
     }
 
-    /*fn get_parent_from_arena<'b>(&'b self, arena: &'b Arena<TokenTreeNode<'b>>) -> &'b TokenTreeNode<'b> {
-        arena.get(self.parent_idx).unwrap()
-    }*/
-}
-
-struct Parser<'a> {
-    tokens: &'a Vec<Token<'a>>,
-    position: usize,
-
-    tree_node_arena: Arena<TokenTreeNode<'a>>,
-    current_tree_node_idx: usize,
-
-    tuple_data_type_arena: Arena<TupleDataType<'a>>
-}
-
-impl<'a> Parser<'a> {
-    fn new(tokens: &'a Vec<Token<'a>>) -> Self {
-        Self {
-            tokens,
-            position: 0,
-
-            tree_node_arena: Arena::new(),
-            current_tree_node_idx: 0,
-
-            tuple_data_type_arena: Arena::new()
-        }
-    }
-
-    fn get_token_at(&self, idx: usize) -> Option<&'a Token<'a>> {
-        self.tokens.get(idx)
-    }
-
-    fn _raw_advance(&mut self) -> Option<&'a Token<'a>> {
-        let advanced_position = self.position + 1;
-        self.position = advanced_position;
-
-        self.tokens.get(advanced_position)
-    }
-
-    fn advance(&mut self) -> Option<&'a Token<'a>> {
-        self._raw_advance();
-
-        if let Some(token) = self.get_token_at(self.position) { parse_multi_comment(token, self); }
-        if let Some(token) = self.get_token_at(self.position) { parse_single_comment(token, self); }
-
-        self.get_token_at(self.position)
-    }
-
-    fn peek(&mut self) -> Option<&'a Token<'a>> {
-        self.tokens.get(self.position + 1)
-    }
-
-
-    fn add_tree_node(&mut self, node: TokenTreeNode<'a>) -> usize {
-        let idx = self.tree_node_arena.push(node);
-        self.current_tree_node_idx = idx;
-        idx
-    }
-
-    fn get_tree_node_at(&mut self, idx: usize) -> Option<&TokenTreeNode<'_>> {
-        self.tree_node_arena.get(idx)
-    }
-
-    fn get_mut_tree_node_at(&mut self, idx: usize) -> Option<&mut TokenTreeNode<'a>> {
-        self.tree_node_arena.get_mut(idx)
-    }
-
-    fn backtrack(&self, backtrack_amount: usize) -> &'a [Token<'a>] {
-        let parser_position = self.position;
-
-        &self.tokens[parser_position-backtrack_amount..parser_position]
-    }
-}
-// ---------------------------------------------------------------------------------------------------
-
-
-// Parse Comments ------------------------------------------------------------------------------------
-fn parse_single_comment<'a>(token: &Token, parser: &'a mut Parser) -> Option<&'a Token<'a>> {
-    if matches!(token, Token::CommentSingle) {
-        return parser.advance()
-    };
-
-    None
-}
+    /// The rules directly inside this one with their selectors, in the order they appear in the
+    /// source.
+    pub fn children(&self) -> Vec<(&str, NodeId<'a>)> {
+        let mut children = self.rules.0.iter()
+            .flat_map(|(selector, nodes)| nodes.iter().map(move |node| (selector.as_str(), *node)))
+            .collect::<Vec<_>>();
 
-fn parse_multi_comment<'a>(token: &Token, parser: &'a mut Parser) -> Option<&'a Token<'a>>{
-    if matches!(token, Token::CommentMultiStart) {
-        loop {
-            let token = parser.advance()?;
-
-            if matches!(token, Token::CommentMultiEnd) {
-                return parser.advance()
-            }
-        }
+        children.sort_by_key(|(_, node)| *node);
+        children
     }
 
-    None
-}
-// ---------------------------------------------------------------------------------------------------
-
-
-// Parse Priority ------------------------------------------------------------------------------------
-fn parse_priority_value(token: &Token, parser: &mut Parser) -> Option<bool> {
-    if let Token::DataType(DataType::Number(value)) = token {
-        let current_node = parser.get_mut_tree_node_at(parser.current_tree_node_idx).unwrap();
-        current_node.priority = Some(value.round() as i32);
-
-        Some(true)
-        
-    } else {
-        return Some(false)
+    /// The rule this one is inside of, or `None` for the root.
+    pub fn parent(&self, id: NodeId<'a>) -> Option<NodeId<'a>> {
+        (self.parent_idx != id).then_some(self.parent_idx)
     }
 }
-
-fn parse_priority<'a>(token: &Token, mut parser: &mut Parser) -> Option<bool> {
-    if !matches!(token, Token::PriorityDeclaration) { return Some(false) }
-
-    let next_token = parser.advance()?;
-    if parse_priority_value(next_token, &mut parser)? { return Some(true) }
-
-    Some(true)
-}
 // ---------------------------------------------------------------------------------------------------
 
 
-// Parse Scope ---------------------------------------------------------------------------------------
+// Tokens --------------------------------------------------------------------------------------------
 fn token_to_string_as_enum<'a>(token: &Token<'a>) -> String {
     match token {
         Token::EnumKeyword => "Enum".to_owned(),
@@ -263,7 +214,7 @@ fn token_to_string_as_enum<'a>(token: &Token<'a>) -> String {
     }
 }
 
-fn token_to_string<'a>(token: &Token<'a>) -> String {
+pub(crate) fn token_to_string<'a>(token: &Token<'a>) -> String {
     match token {
         Token::Text(text_type) => match text_type {
             TextType::NonSpecial(text) => String::from(*text),
@@ -287,55 +238,41 @@ fn token_to_string<'a>(token: &Token<'a>) -> String {
     }
 }
 
-fn parse_scope_name(token: &Token, parser: &mut Parser, mut backtrack_amount: usize) -> Option<bool> {
-    if matches!(
-        token, Token::Text(_) | Token::ScopeToChildren | Token::ScopeToDescendants | Token::ListDelimiter
-    ) {
-        let next_token = parser.advance();
-
-        backtrack_amount += 1;
-
-        if let Some(next_token) = next_token {
-            if parse_scope_name(next_token, parser, backtrack_amount)? { return Some(true) }
-
-        } 
-    };
-
-    if backtrack_amount == 0 { return Some(false) }
-
-    if parse_scope_open(token, parser, backtrack_amount)? { return Some(true) }
-
-    Some(false)
+fn text_of<'a>(text_type: &TextType<'a>) -> &'a str {
+    match text_type {
+        TextType::NonSpecial(text)
+        | TextType::SelectorName(text)
+        | TextType::SelectorTagOrEnumPart(text)
+        | TextType::SelectorStateOrEnumPart(text)
+        | TextType::SelectorPsuedo(text)
+        | TextType::Argument(text)
+        | TextType::Variable(text)
+        | TextType::PsuedoProperty(text) => text
+    }
 }
 
-fn parse_scope_open(token: &Token, parser: &mut Parser, backtrack_amount: usize) -> Option<bool> {
-    if !matches!(token, Token::ScopeOpen) { return Some(false) }
-
-    let selector = parser.backtrack(backtrack_amount)
-        .iter() 
-        .map(token_to_string)
-        .collect::<Vec<String>>();
-
-    let old_node_idx = parser.current_tree_node_idx;
-    let new_node_idx = parser.add_tree_node(TokenTreeNode::new(old_node_idx));
-
-    let old_node = parser.get_mut_tree_node_at(old_node_idx).unwrap();
-    old_node.insert_rule(selector.join(" "), new_node_idx);
-
-    return Some(true)
+fn is_enum_token(token: &Token) -> bool {
+    matches!(
+        token, Token::EnumKeyword | Token::Text(TextType::SelectorStateOrEnumPart(_) | TextType::SelectorTagOrEnumPart(_))
+    )
 }
 
-fn parse_scope_close(token: &Token, parser: &mut Parser) -> Option<bool> {
-    if !matches!(token, Token::ScopeClose) { return Some(false) }
-
-    parser.current_tree_node_idx = parser.get_tree_node_at(parser.current_tree_node_idx).unwrap().parent_idx;
-
-    return Some(true);
+/// Drops comments, including everything between `--[[` and `]]`.
+/// The tokens which aren't comments, along with their indices in `tokens`.
+fn strip_comments<'a>(tokens: &'a [Token<'a>]) -> (Vec<&'a Token<'a>>, Vec<usize>) {
+    let mut in_comment = false;
+
+    tokens.iter().enumerate().filter(|(_, token)| match token {
+        Token::CommentMultiStart => { in_comment = true; false },
+        Token::CommentMultiEnd if in_comment => { in_comment = false; false },
+        Token::CommentSingle => false,
+        _ => !in_comment
+    }).map(|(idx, token)| (token, idx)).unzip()
 }
 // ---------------------------------------------------------------------------------------------------
 
 
-// Parse Tuple ---------------------------------------------------------------------------------------
+// Tuples --------------------------------------------------------------------------------------------
 fn tuple_to_vec2_data_type<'a>(tuple: &TupleDataType) -> DataType<'a> {
     let component_x = if let Some(component) = tuple.get(0) {
         match component {
@@ -344,7 +281,7 @@ fn tuple_to_vec2_data_type<'a>(tuple: &TupleDataType) -> DataType<'a> {
         }
     } else { 0.0 };
     
-    let component_y = if let Some(component) = tuple.get(0) {
+    let component_y = if let Some(component) = tuple.get(1) {
         match component {
             DataType::Number(number) => *number,
             _ => component_x
@@ -468,12 +405,20 @@ fn tuple_to_color3_data_type<'a>(tuple: &TupleDataType) -> DataType<'a> {
     DataType::Color3(Color3::new(component_r as f32, component_g as f32, component_b as f32))
 }
 
+/// `rgb(r, g, b)` with channels from 0 to 255, or `rgba(r, g, b, a)` with an alpha from 0 to 1 or
+/// a percentage. `rgb` takes an alpha too, like it does in CSS.
 fn tuple_to_rgb_data_type<'a>(tuple: &TupleDataType) -> DataType<'a> {
     let color = tuple_to_color3_data_type(tuple);
+    let DataType::Color3(color) = color else { unreachable!() };
+    let color = Color3::new(color.r / 255.0, color.g / 255.0, color.b / 255.0);
+
+    let alpha = match tuple.get(3) {
+        Some(DataType::Number(alpha) | DataType::NumberScale(alpha)) => *alpha as f32,
+        Some(DataType::UDim(udim)) if udim.offset == 0 => udim.scale,
+        _ => return DataType::Color3(color)
+    };
 
-    if let DataType::Color3(color) = color {
-        return DataType::Color3(Color3::new(color.r / 255.0, color.g / 255.0, color.b / 255.0))
-    } else { unreachable!() }
+    DataType::ColorAlpha(color, alpha.clamp(0.0, 1.0))
 }
 
 fn tuple_to_udim_data_type<'a>(tuple: &TupleDataType) -> DataType<'a> {
@@ -494,12 +439,13 @@ fn tuple_to_font_data_type<'a>(tuple: &TupleDataType) -> DataType<'a> {
     let font_name = if let Some(component) = tuple.get(0) {
         match component {
             DataType::StringSingle(str) => *str,
-            DataType::Number(num) => &format!("rbxassetid://{}", num),
+            DataType::Number(num) => &asset_id_url(*num).unwrap_or_else(|| String::from("rbxasset://fonts/families/SourceSansPro.json")),
+            DataType::Content(url) => url,
             _ => "rbxasset://fonts/families/SourceSansPro.json"
         }
     } else { "rbxasset://fonts/families/SourceSansPro.json" };
 
-    let font_weight = if let Some(component) = tuple.get(0) {
+    let font_weight = if let Some(component) = tuple.get(1) {
         match component {
             DataType::StringSingle(str) => match *str {
                 "Thin" => FontWeight::Thin,
@@ -516,7 +462,7 @@ fn tuple_to_font_data_type<'a>(tuple: &TupleDataType) -> DataType<'a> {
         }
     } else { FontWeight::Regular };
 
-    let font_style = if let Some(component) = tuple.get(0) {
+    let font_style = if let Some(component) = tuple.get(2) {
         match component {
             DataType::StringSingle(str) => match *str {
                 "Italic" => FontStyle::Italic,
@@ -529,6 +475,27 @@ fn tuple_to_font_data_type<'a>(tuple: &TupleDataType) -> DataType<'a> {
     DataType::Font(Font::new(font_name, font_weight, font_style))
 }
 
+/// `asset(123)` for an asset id, or `asset("name")` for an asset in the asset manifest. Ids can be
+/// quoted too, like `asset("123")`.
+fn tuple_to_asset_data_type<'a>(tuple: &TupleDataType) -> Option<DataType<'a>> {
+    match tuple.get(0)? {
+        DataType::Number(id) => Some(DataType::Content(asset_id_url(*id)?)),
+        DataType::StringSingle(id) if !id.is_empty() && id.bytes().all(|byte| byte.is_ascii_digit()) => {
+            Some(DataType::Content(format!("rbxassetid://{}", id.parse::<u64>().ok()?)))
+        },
+        DataType::StringSingle(name) => Some(DataType::Content(name.to_string())),
+        _ => None
+    }
+}
+
+/// `rbxasset("path")`, for a file that comes with Roblox.
+fn tuple_to_rbxasset_data_type<'a>(tuple: &TupleDataType) -> Option<DataType<'a>> {
+    match tuple.get(0)? {
+        DataType::StringSingle(path) => Some(DataType::Content(format!("rbxasset://{}", path))),
+        _ => None
+    }
+}
+
 fn parse_tuple_as_number<'a>(tuple: &TupleDataType<'a>) -> Option<DataType<'a>> {
     if tuple.data.len() != 1 { return None }
 
@@ -565,8 +532,10 @@ fn tuple_to_data_type<'a>(tuple: &TupleDataType<'a>) -> Option<DataType<'a>> {
             "vec3" => { return Some(tuple_to_vec3_data_type(tuple)) },
             "rect" => { return Some(tuple_to_rect_data_type(tuple)) },
             "color3" => { return Some(tuple_to_color3_data_type(tuple)) },
-            "rgb" => { return Some(tuple_to_rgb_data_type(tuple)) },
+            "rgb" | "rgba" => { return Some(tuple_to_rgb_data_type(tuple)) },
             "font" => { return Some(tuple_to_font_data_type(tuple)) },
+            "asset" => { return tuple_to_asset_data_type(tuple) },
+            "rbxasset" => { return tuple_to_rbxasset_data_type(tuple) },
 
             _ => { return None }
         };
@@ -586,155 +555,10 @@ fn tuple_to_data_type<'a>(tuple: &TupleDataType<'a>) -> Option<DataType<'a>> {
 
     None
 }
-
-fn parse_tuple_name<'a>(
-    token: &'a Token, mut parser: &mut Parser<'a>, root_tuple_idx: Option<usize>, tuple_idx: Option<usize>, only_if_name: Option<&str>
-) -> Option<usize> {
-    let (tuple_name, next_token) = if let Token::Text(tuple_name) = token {
-        let tuple_name: &'a str = match tuple_name {
-            TextType::NonSpecial(text)
-            | TextType::SelectorName(text)
-            | TextType::SelectorTagOrEnumPart(text)
-            | TextType::SelectorStateOrEnumPart(text)
-            | TextType::SelectorPsuedo(text)
-            | TextType::Argument(text)
-            | TextType::Variable(text)
-            | TextType::PsuedoProperty(text) => *text
-        };
-
-        if let Some(only_if_name) = only_if_name {
-            if tuple_name != only_if_name {
-                return None
-            }
-        }
-
-        (Some(tuple_name), parser.advance()?)
-
-    } else { (None, token) };
-
-    if let Some(tuple_idx) = parse_tuple_open(next_token, &mut parser, tuple_name, root_tuple_idx, tuple_idx) {
-        return Some(tuple_idx)
-    };
-
-    None
-}
-
-fn parse_tuple_open<'a>(
-    token: &Token, mut parser: &mut Parser<'a>, tuple_name: Option<&'a str>, root_tuple_idx: Option<usize>, parent_tuple_idx: Option<usize>
-) -> Option<usize> {
-    if !matches!(token, Token::TupleOpen) { return None }
-
-    let arena = &mut parser.tuple_data_type_arena;
-    let tuple = TupleDataType::new(tuple_name, parent_tuple_idx);
-
-    let tuple_idx = arena.push(tuple);
-    let some_tuple_idx = Some(tuple_idx);
-
-    let root_tuple_idx = root_tuple_idx.or(some_tuple_idx);
-
-    let next_token = parser.advance()?;
-  
-    if let Some (tuple_idx) = parse_tuple_data_type(next_token, &mut parser, root_tuple_idx, some_tuple_idx) {
-        return Some(tuple_idx)
-    };
-    if let Some(tuple_idx) = parse_tuple_delimiter(next_token, &mut parser, root_tuple_idx, some_tuple_idx) {
-        return Some(tuple_idx)
-    };
-    if let Some(tuple_idx) = parse_tuple_close(next_token, &mut parser, root_tuple_idx, some_tuple_idx) {
-        return Some(tuple_idx)
-    };
-    if let Some (tuple_idx) = parse_tuple_name(next_token, &mut parser, root_tuple_idx, some_tuple_idx, None) { return Some(tuple_idx) };
-
-    None
-}
-
-fn parse_tuple_data_type<'a>(
-    token: &'a Token, mut parser: &mut Parser<'a>, root_tuple_idx: Option<usize>, current_tuple_idx: Option<usize>
-) -> Option<usize> {
-    if let Some(data_type) = parse_data_type(token, parser, None) {
-        let arena = &mut parser.tuple_data_type_arena;
-        let tuple = arena.get_mut(current_tuple_idx.unwrap()).unwrap();
-
-        tuple.push(data_type);
-
-        let next_token = parser.advance()?;
-
-        if let Some(tuple_idx) = parse_tuple_delimiter(next_token, &mut parser, root_tuple_idx, current_tuple_idx) { return Some(tuple_idx) };
-        if let Some(tuple_idx) = parse_tuple_data_type(next_token, &mut parser, root_tuple_idx, current_tuple_idx) { return Some(tuple_idx) };
-        if let Some(tuple_idx) = parse_tuple_close(next_token, &mut parser, root_tuple_idx, current_tuple_idx) { return Some(tuple_idx) };
-        if let Some(tuple_idx) = parse_tuple_name(next_token, &mut parser, root_tuple_idx, current_tuple_idx, None) {
-            return Some(tuple_idx)
-        };
-    };
-
-    return None
-}
-
-fn parse_tuple_delimiter<'a>(
-    token: &Token, mut parser: &mut Parser<'a>, root_tuple_idx: Option<usize>, current_tuple_idx: Option<usize>
-) -> Option<usize> {
-    if !matches!(token, Token::ListDelimiter | Token::SectionClose) { return None }
-
-    let next_token = parser.advance()?;
-
-    // Handles cases where there are multiple delimiter tokens next to each other.
-    if let Some(tuple_idx) = parse_tuple_delimiter(next_token, &mut parser, root_tuple_idx, current_tuple_idx) { return Some(tuple_idx) };
-
-    if let Some(tuple_idx) = parse_tuple_data_type(next_token, &mut parser, root_tuple_idx, current_tuple_idx) { return Some(tuple_idx) };
-    if let Some(tuple_idx) = parse_tuple_close(next_token, &mut parser, root_tuple_idx, current_tuple_idx) { return Some(tuple_idx) };
-    if let Some(tuple_idx) = parse_tuple_name(next_token, &mut parser, root_tuple_idx, current_tuple_idx, None) {
-        return Some(tuple_idx)
-    };
-
-    None
-}
-
-fn parse_tuple_close<'a>(
-    token: &Token, mut parser: &mut Parser<'a>, root_tuple_idx: Option<usize>, current_tuple_idx: Option<usize>
-) -> Option<usize> {
-    if !matches!(token, Token::TupleClose) { return None }
-
-    let arena = &mut parser.tuple_data_type_arena;
-    let tuple = arena.get_mut(current_tuple_idx.unwrap()).unwrap();
-    let parent_tuple_idx = tuple.parent_idx;
-
-    if let Some(parent_tuple_idx) = parent_tuple_idx {
-        let data_type = tuple_to_data_type(tuple);
-        let parent_tuple = arena.get_mut(parent_tuple_idx).unwrap();
-
-        if let Some(data_type) = data_type {
-            parent_tuple.push(data_type);
-        }
-    }
-
-    // We are at the root of the tuple, no need to parse more tuple tokens.
-    if root_tuple_idx == current_tuple_idx {
-        return root_tuple_idx;
-    }
-
-    let parent_tuple_idx_with_fallback = parent_tuple_idx.or(current_tuple_idx);
-
-    let next_token = parser.advance()?;
-
-    if let Some(tuple_idx) = parse_tuple_data_type(next_token, &mut parser, root_tuple_idx, parent_tuple_idx_with_fallback) {
-        return Some(tuple_idx)
-    };
-    if let Some(tuple_idx) = parse_tuple_delimiter(next_token, &mut parser, root_tuple_idx, parent_tuple_idx_with_fallback) {
-        return Some(tuple_idx)
-    };
-    if let Some(tuple_idx) = parse_tuple_close(next_token, &mut parser, root_tuple_idx, parent_tuple_idx_with_fallback) {
-        return Some(tuple_idx)
-    };
-    if let Some(tuple_idx) = parse_tuple_name(next_token, &mut parser, root_tuple_idx, parent_tuple_idx_with_fallback, None) {
-        return Some(tuple_idx)
-    };
-
-    None
-}
 // ---------------------------------------------------------------------------------------------------
 
 
-// Parse Data Types ----------------------------------------------------------------------------------
+// Equations -----------------------------------------------------------------------------------------
 fn will_divide_by_zero(a: f64, b: f64) -> bool {
     if a != 0.0 && b != 0.0 { return false }
     return true
@@ -767,7 +591,9 @@ fn operator_indexes_in_stack<'a>(stack: &Vec<EquationDataType<'a>>, operator: &O
     indexes
 }
 
-fn resolve_equation_stack<'a>(stack: &mut Vec<EquationDataType<'a>>) -> DataType<'a> {
+/// Works out an equation's stack, or gives `None` when an operator is missing a number to work on,
+/// like in `3 *` or `2 + * 4`.
+fn resolve_equation_stack<'a>(stack: &mut Vec<EquationDataType<'a>>) -> Option<DataType<'a>> {
     for (operator, operator_fn) in [
         (Operator::Pow, power_f64 as fn(f64, f64) -> f64),
         (Operator::Div, divide_f64 as fn(f64, f64) -> f64),
@@ -781,7 +607,7 @@ fn resolve_equation_stack<'a>(stack: &mut Vec<EquationDataType<'a>>) -> DataType
             let mut occurrence_idx =  occurrence_idx - stack_offset;
 
             let right_idx = occurrence_idx + 1;
-            if right_idx > stack.len() { continue; }
+            if right_idx >= stack.len() { return None }
 
             stack_offset += 1;
             let right = stack.remove(right_idx);
@@ -797,9 +623,9 @@ fn resolve_equation_stack<'a>(stack: &mut Vec<EquationDataType<'a>>) -> DataType
                 stack.remove(left_idx)
             };
 
-            let right_value = if let EquationDataType::Number(num) 
-                | EquationDataType::NumberScale(num) 
-                | EquationDataType::NumberOffset(num) = right { num } else { unreachable!() };
+            let (EquationDataType::Number(right_value)
+                | EquationDataType::NumberScale(right_value)
+                | EquationDataType::NumberOffset(right_value)) = right else { return None };
 
              match left {
                 // If the left side is a scale then the result is a scale.
@@ -830,10 +656,10 @@ fn resolve_equation_stack<'a>(stack: &mut Vec<EquationDataType<'a>>) -> DataType
                         stack[occurrence_idx] = EquationDataType::Number(operator_fn(left_value, right_value));
                     },
 
-                    _ => ()
+                    _ => return None
                 }
 
-                _ => ()
+                _ => return None
             };
         }
     }
@@ -843,15 +669,14 @@ fn resolve_equation_stack<'a>(stack: &mut Vec<EquationDataType<'a>>) -> DataType
         (Operator::Sub, neg_f64 as fn(f64) -> f64),
     ] {
         let occurrences = operator_indexes_in_stack(stack, &operator);
-        let mut stack_offset: usize = 0;
 
-        for occurrence_idx in occurrences {
+        // Each earlier occurrence has removed one item from the stack before this one.
+        for (stack_offset, occurrence_idx) in occurrences.into_iter().enumerate() {
             let occurrence_idx = occurrence_idx - stack_offset;
 
             let right_idx = occurrence_idx + 1;
-            if right_idx > stack.len() { continue; }
+            if right_idx >= stack.len() { return None }
 
-            stack_offset += 1;
             let right = stack.remove(right_idx);
 
             match right {
@@ -864,7 +689,7 @@ fn resolve_equation_stack<'a>(stack: &mut Vec<EquationDataType<'a>>) -> DataType
                 EquationDataType::NumberOffset(right_value) => {
                     stack[occurrence_idx] = EquationDataType::NumberOffset(operator_fn(right_value));
                 },
-                _ => ()
+                _ => return None
             }
         }
     }
@@ -889,9 +714,9 @@ fn resolve_equation_stack<'a>(stack: &mut Vec<EquationDataType<'a>>) -> DataType
     }
 
     if !has_explicit_scale && !has_explicit_offset {
-        return DataType::Number(scale)
+        return Some(DataType::Number(scale))
 
-    } else { return DataType::UDim(UDim::new(scale as f32, offset)) }
+    } else { return Some(DataType::UDim(UDim::new(scale as f32, offset))) }
 }
 
 fn previous_token_operator<'a>(stack: &mut Vec<EquationDataType<'a>>) -> Option<&'a Operator> {
@@ -905,114 +730,45 @@ fn previous_token_operator<'a>(stack: &mut Vec<EquationDataType<'a>>) -> Option<
     } else { Some(&Operator::Mult) }
 }
 
-fn parse_equation_tuple_data_type<'a>(
-    token: &'a Token, parser: &mut Parser<'a>, stack: &mut Vec<EquationDataType<'a>>, only_if_name: Option<&str>
-) -> Option<usize> {
-    if let Some(tuple_idx) = parse_tuple_name(token, parser,None, None, only_if_name) {
-        let tuple_data_type = tuple_to_data_type(parser.tuple_data_type_arena.get(tuple_idx).unwrap());
-        
-        if let Some(tuple_data_type) = tuple_data_type {
-            match tuple_data_type {
-                DataType::UDim(udim) => {
-                    let (scale, offset) = (udim.scale, udim.offset);
-
-                    let previous_token_operator = previous_token_operator(stack);
-
-                    let (first_operator, second_operator) = {
-                        match previous_token_operator {
-                            None => {
-                                (None, EquationDataType::Operator(&Operator::Plus))
-                            },
-
-                            Some(operator) => (None, EquationDataType::Operator(operator))
-                        }
-                    };
-
-                    let apply_scale = scale != 0.0;
-                    if apply_scale {
-                        if let Some(first_operator) = first_operator.clone() {
-                            stack.push(first_operator);
-                        } 
-                        stack.push(EquationDataType::NumberScale(udim.scale as f64));
-                    }
-
-                    if offset != 0 {
-                        if apply_scale {
-                            stack.push(second_operator);
-
-                        } else {
-                            if let Some(first_operator) = first_operator {
-                                stack.push(first_operator);
-                            }
-                        };
+/// Adds a `udim(...)` or `(...)` inside of an equation to its stack. Returns `false` if the tuple
+/// isn't a number or udim.
+fn push_equation_tuple<'a>(tuple: &TupleDataType<'a>, stack: &mut Vec<EquationDataType<'a>>) -> bool {
+    tuple_to_data_type(tuple).is_some_and(|data_type| push_equation_value(data_type, stack))
+}
 
-                        stack.push(EquationDataType::NumberOffset(udim.offset as f64));
-                    }
-                },
+/// Adds a number or udim inside of an equation to its stack. A udim's offset is joined to its scale
+/// with whichever operator came before it. Returns `false` for anything else.
+fn push_equation_value<'a>(data_type: DataType<'a>, stack: &mut Vec<EquationDataType<'a>>) -> bool {
+    match data_type {
+        DataType::UDim(udim) => {
+            let operator = previous_token_operator(stack).unwrap_or(&Operator::Plus);
 
-                DataType::Number(number) => {
-                    stack.push(EquationDataType::Number(number as f64));
-                },
+            let apply_scale = udim.scale != 0.0;
+            if apply_scale { stack.push(EquationDataType::NumberScale(udim.scale as f64)) }
 
-                _ => ()
+            if udim.offset != 0 {
+                if apply_scale { stack.push(EquationDataType::Operator(operator)) }
+                stack.push(EquationDataType::NumberOffset(udim.offset as f64));
             }
-        }
-
-        return Some(tuple_idx)
-    }
-
-    None
-}
-
-fn parse_equation_latest_operator_data_type<'a>(
-    current_operator: &'a Operator, parser: &mut Parser<'a>, stack: &mut Vec<EquationDataType<'a>>
-) -> Option<bool> {
-    let next_token = parser.peek()?;
+        },
 
-    if let Token::Operator(next_operator) = next_token {
-        parser.advance();
+        DataType::Number(number) => stack.push(EquationDataType::Number(number)),
+        DataType::NumberOffset(number) => stack.push(EquationDataType::NumberOffset(number)),
+        DataType::NumberScale(number) => stack.push(EquationDataType::NumberScale(number)),
 
-        let next_operator = match (current_operator, next_operator) {
-            (Operator::Sub, Operator::Plus) => &Operator::Sub,
-            (Operator::Sub, Operator::Sub) => &Operator::Plus,
-            _ => next_operator
-        };
-
-        return parse_equation_latest_operator_data_type(next_operator, parser, stack);
-    } else {
-        stack.push(EquationDataType::Operator(current_operator));
+        _ => return false
     }
 
-    return Some(true)
+    true
 }
 
-fn parse_equation_data_types<'a>(token: &'a Token, parser: &mut Parser<'a>, stack: &mut Vec<EquationDataType<'a>>) -> Option<DataType<'a>> {
-    match token {
-        Token::DataType(data_type) => match data_type {
-            DataType::Number(num) => stack.push(EquationDataType::Number(*num)),
-            DataType::NumberOffset(num) => stack.push(EquationDataType::NumberOffset(*num)),
-            DataType::NumberScale(num) => stack.push(EquationDataType::NumberScale(*num)),
-            _ => { return None }
-        },
-
-        Token::Operator(operator) => { parse_equation_latest_operator_data_type(operator, parser, stack); },
-
-        _ => if parse_equation_tuple_data_type(token, parser, stack, Some("udim")).is_none() { return None }
-    };
-
-    let next_token = parser.advance()?;
-
-    if let Some(result) = parse_equation_data_types(next_token, parser, stack) { return Some(result) };
-
-
-    parser.position -= 1;
-    let solved = resolve_equation_stack(stack);
-    return Some(solved)
+fn is_numeric(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::Number(_) | DataType::NumberOffset(_) | DataType::NumberScale(_) | DataType::UDim(_))
 }
 // ---------------------------------------------------------------------------------------------------
 
 
-// Parse Assignment ----------------------------------------------------------------------------------
+// Literals ------------------------------------------------------------------------------------------
 fn data_type_to_variant(data_type: &DataType) -> Variant {
     match data_type {
         DataType::StringSingle(data_type) => Variant::String(data_type.to_string()),
@@ -1020,23 +776,53 @@ fn data_type_to_variant(data_type: &DataType) -> Variant {
         DataType::UDim(data_type) => Variant::UDim(*data_type),
         DataType::UDim2(data_type) => Variant::UDim2(*data_type),
         DataType::Vec2(data_type) => Variant::Vector2(*data_type),
-        DataType::Color3(data_type) => Variant::Color3(*data_type),
+        DataType::Vec3(data_type) => Variant::Vector3(*data_type),
+        DataType::Rect(data_type) => Variant::Rect(*data_type),
+        DataType::Color3(data_type) | DataType::ColorAlpha(data_type, _) => Variant::Color3(*data_type),
         DataType::Number(data_type) => Variant::Float32(*data_type as f32),
+        DataType::Bool(data_type) => Variant::Bool(*data_type),
         DataType::NumberOffset(data_type) => Variant::UDim(UDim::new(0.0, *data_type as i32)),
         DataType::NumberScale(data_type) => Variant::UDim(UDim::new(*data_type as f32, 0)),
         DataType::Font(font) => Variant::Font(font.clone()),
+        DataType::Content(url) => Variant::Content(Content::from(url.as_str())),
 
         _ => Variant::String(format!("{:#?}", "placeholder"))
     }
 }
 
+/// Parses `#RGB` and `#RRGGBB` colors, and `#RGBA` and `#RRGGBBAA` colors with an alpha channel.
 fn parse_hex<'a>(hex_str: &str) -> DataType<'a> {
-    let rgb = match Rgb::from_hex_str(hex_str) {
+    let digits = hex_str.trim_start_matches('#');
+
+    let (rgb_digits, alpha_digits) = match digits.len() {
+        4 => (&digits[..3], Some(digits[3..].repeat(2))),
+        8 => (&digits[..6], Some(digits[6..].to_owned())),
+        _ => (digits, None)
+    };
+
+    let rgb = match Rgb::from_hex_str(&format!("#{}", rgb_digits)) {
         Ok(ok_rgb) => ok_rgb,
         Err(_) => Rgb::from(255.0, 0.0, 249.0)
     };
+    let color = Color3::new(rgb.get_red() / 255.0, rgb.get_green() / 255.0, rgb.get_blue() / 255.0);
 
-    DataType::Color3(Color3::new(rgb.get_red() / 255.0, rgb.get_green() / 255.0, rgb.get_blue() / 255.0))
+    match alpha_digits.and_then(|alpha| u8::from_str_radix(&alpha, 16).ok()) {
+        Some(alpha) => DataType::ColorAlpha(color, alpha as f32 / 255.0),
+        None => DataType::Color3(color)
+    }
+}
+
+/// Parses a palette color which may end with an opacity percentage, like `tw:red:500/50`.
+fn parse_palette_color<'a>(palette: &HashMap<String, String>, name: &str) -> DataType<'a> {
+    let (name, opacity) = match name.split_once('/') {
+        Some((name, opacity)) => (name, opacity.parse::<f32>().ok()),
+        None => (name, None)
+    };
+
+    match (parse_hex(palette.get(name).unwrap()), opacity) {
+        (DataType::Color3(color), Some(opacity)) => DataType::ColorAlpha(color, opacity.clamp(0.0, 100.0) / 100.0),
+        (data_type, _) => data_type
+    }
 }
 
 fn parse_hex_data_type<'a>(token: &'a Token) -> Option<DataType<'a>> {
@@ -1049,7 +835,7 @@ fn parse_hex_data_type<'a>(token: &'a Token) -> Option<DataType<'a>> {
 
 fn parse_tailwind_color_data_type<'a>(token: &'a Token) -> Option<DataType<'a>> {
     if let Token::DataType(DataType::ColorTw(tailwind_color)) = token {
-        return Some(parse_hex(TAILWIND_COLORS.get(tailwind_color.to_owned()).unwrap()))
+        return Some(parse_palette_color(&TAILWIND_COLORS, tailwind_color))
     }
 
     None
@@ -1057,150 +843,1024 @@ fn parse_tailwind_color_data_type<'a>(token: &'a Token) -> Option<DataType<'a>>
 
 fn parse_css_color_data_type<'a>(token: &'a Token) -> Option<DataType<'a>> {
     if let Token::DataType(DataType::ColorCss(css_color)) = token {
-        return Some(parse_hex(CSS_COLORS.get(css_color.to_owned()).unwrap()))
+        return Some(parse_palette_color(&CSS_COLORS, css_color))
     }
 
     None
 }
 
-fn parse_enum_data_type<'a>(
-    token: &'a Token, parser: &mut Parser<'a>, key: Option<&'a TextType<'_>>, mut backtrack_amount: usize
-) -> Option<DataType<'a>> {
-    if matches!(
-        token, Token::EnumKeyword | Token::Text(TextType::SelectorStateOrEnumPart(_) | TextType::SelectorTagOrEnumPart(_))
-    ) {
-        backtrack_amount += 1;
+/// A Tailwind palette color, named like `tw:blue:500`.
+pub(crate) fn tailwind_color(name: &str) -> Option<Color3> {
+    match parse_hex(TAILWIND_COLORS.get(name)?) {
+        DataType::Color3(color) => Some(color),
+        _ => None
+    }
+}
+
+/// A CSS named color, like `css:rebeccapurple`.
+pub(crate) fn css_color(name: &str) -> Option<Color3> {
+    match parse_hex(CSS_COLORS.get(name)?) {
+        DataType::Color3(color) => Some(color),
+        _ => None
+    }
+}
+
+/// Whether `text` starts a `tw:` or `css:` color, like `tw` or `tw:red`.
+fn is_palette_prefix(text: &str) -> bool {
+    ["tw", "css"].iter().any(|prefix| text.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with(':')))
+}
+
+/// Resolves a hex, `tw:` or `css:` color literal, leaving out any alpha.
+pub fn color_literal(token: &Token) -> Option<Color3> {
+    let data_type = parse_hex_data_type(token)
+        .or_else(|| parse_tailwind_color_data_type(token))
+        .or_else(|| parse_css_color_data_type(token))?;
+
+    match data_type {
+        DataType::Color3(color) | DataType::ColorAlpha(color, _) => Some(color),
+        _ => None
+    }
+}
+// ---------------------------------------------------------------------------------------------------
 
-        let next_token = parser.advance();
 
-        if let Some(next_token) = next_token {
-            if let Some(enum_data_type) = parse_enum_data_type(next_token, parser, key, backtrack_amount) {
-                return Some(enum_data_type)
+// Control -------------------------------------------------------------------------------------------
+/// How a loop variable reads when it's part of a selector, as in `.p-$size`.
+fn interpolation_text(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Number(number) | DataType::NumberOffset(number) => number.to_string(),
+        DataType::NumberScale(number) => (number * 100.0).to_string(),
+        DataType::UDim(udim) if udim.scale == 0.0 => udim.offset.to_string(),
+        DataType::UDim(udim) if udim.offset == 0 => (udim.scale as f64 * 100.0).to_string(),
+        DataType::StringSingle(text) => text.to_string(),
+        DataType::OwnedString(text) => text.clone(),
+        DataType::Color3(color) => format!(
+            "{:02x}{:02x}{:02x}", (color.r * 255.0).round() as u8, (color.g * 255.0).round() as u8, (color.b * 255.0).round() as u8
+        ),
+        _ => String::new()
+    }
+}
+
+fn condition_number(value: &Variant) -> Option<f64> {
+    match value {
+        Variant::Float32(number) => Some(*number as f64),
+        Variant::UDim(udim) if udim.scale == 0.0 => Some(udim.offset as f64),
+        Variant::UDim(udim) if udim.offset == 0 => Some(udim.scale as f64),
+        _ => None
+    }
+}
+
+/// Whether a condition with no comparison in it holds. Variables which aren't defined, `false`, `0`
+/// and empty strings don't; anything else does.
+fn is_truthy(value: &Option<Variant>) -> bool {
+    match value {
+        None => false,
+        Some(Variant::Bool(value)) => *value,
+        Some(Variant::Float32(number)) => *number != 0.0,
+        Some(Variant::String(text)) => !text.is_empty(),
+        Some(_) => true
+    }
+}
+
+fn compare(comparison: Comparison, left: &Option<Variant>, right: &Option<Variant>) -> bool {
+    match comparison {
+        Comparison::Equal => left == right,
+        Comparison::NotEqual => left != right,
+
+        _ => {
+            let (Some(left), Some(right)) = (left.as_ref().and_then(condition_number), right.as_ref().and_then(condition_number))
+                else { return false };
+
+            match comparison {
+                Comparison::Less => left < right,
+                Comparison::LessOrEqual => left <= right,
+                Comparison::Greater => left > right,
+                _ => left >= right
             }
         }
-    };
+    }
+}
+// ---------------------------------------------------------------------------------------------------
 
-    if backtrack_amount == 0 { return None }
 
-    parser.position -= 1;
+// Parser --------------------------------------------------------------------------------------------
+struct Parser<'a> {
+    /// Every token apart from comments.
+    tokens: Vec<&'a Token<'a>>,
+    /// Where each of `tokens` is in the tokens the parser was given.
+    token_indices: Vec<usize>,
+    position: usize,
+    /// The variables of the `@each` loops being evaluated, innermost last.
+    bindings: Vec<(&'a str, DataType<'a>)>,
+    /// The alpha of variables set to colors with one, by the rule they're declared in. Variables only
+    /// hold the color, so this is what lets `BackgroundColor3 = $Overlay;` set the transparency too.
+    alphas: HashMap<(NodeId<'a>, &'a str), f32>,
+    /// How many `@each` and `@if` blocks are being evaluated.
+    control_depth: usize,
+
+    tree: Arena<TokenTreeNode<'a>>
+}
+
+impl<'a> Parser<'a> {
+    fn new(tokens: &'a [Token<'a>]) -> Self {
+        let (tokens, token_indices) = strip_comments(tokens);
 
-    let mut enum_data_type = parser.backtrack(backtrack_amount).to_vec();
+        Self {
+            tokens,
+            token_indices,
+            position: 0,
+            bindings: vec![],
+            alphas: HashMap::new(),
+            control_depth: 0,
+
+            tree: Arena::new()
+        }
+    }
+
+    fn peek(&self, ahead: usize) -> Option<&'a Token<'a>> {
+        self.tokens.get(self.position + ahead).copied()
+    }
+
+    fn bump(&mut self) -> Option<&'a Token<'a>> {
+        let token = self.peek(0)?;
+        self.position += 1;
+        Some(token)
+    }
 
-    if !matches!(enum_data_type[0], Token::EnumKeyword) {
-        enum_data_type.insert(0, Token::EnumKeyword);
+    fn node_mut(&mut self, node: NodeId<'a>) -> &mut TokenTreeNode<'a> {
+        self.tree.get_mut(node).unwrap()
     }
 
-    if enum_data_type.len() == 2 {
-        if let Some(key) = key {
-            enum_data_type.insert(1, Token::Text(key.to_owned()));
+    fn binding(&self, name: &str) -> Option<&DataType<'a>> {
+        self.bindings.iter().rev().find(|(bound, _)| *bound == name).map(|(_, data_type)| data_type)
+    }
+
+    /// The value of the variable declared in `node` or the closest rule around it.
+    fn variable(&self, node: NodeId<'a>, name: &str) -> Option<&Variant> {
+        let mut node = node;
+
+        loop {
+            let tree_node = self.tree.get(node)?;
+            if let Some(value) = tree_node.variables.get(name) { return Some(value) }
+
+            node = tree_node.parent(node)?;
         }
     }
 
-    Some(DataType::OwnedString(enum_data_type
-        .iter()
-        .map(token_to_string_as_enum)
-        .collect::<Vec<String>>()
-        .join(".")
-    ))
-}
+    /// The alpha of the variable that `node` sees by that name, if it was set to a color with one.
+    fn variable_alpha(&self, node: NodeId<'a>, name: &str) -> Option<f32> {
+        let mut node = node;
+
+        loop {
+            let tree_node = self.tree.get(node)?;
+            if tree_node.variables.contains_key(name) { return self.alphas.get(&(node, name)).copied() }
+
+            node = tree_node.parent(node)?;
+        }
+    }
 
-fn parse_data_type<'a>(token: &'a Token, parser: &mut Parser<'a>, key: Option<&'a TextType<'_>>) -> Option<DataType<'a>> {
-    if let Token::Text(TextType::Variable(text)) = token {
-        Some(DataType::OwnedString(format!("${}", text)))
+    /// Follows `value` through the variables it refers to, as seen from `node`. Variables which
+    /// aren't defined give `None`.
+    fn resolve(&self, node: NodeId<'a>, mut value: Variant) -> Option<Variant> {
+        let mut visited = vec![];
 
-    } else if let Some(data_type) = parse_equation_data_types(token, parser, &mut vec![]) {
-        Some(data_type)
+        // Variables can be set to other variables, which could go around in circles.
+        while let Variant::String(text) = &value {
+            let Some(name) = text.strip_prefix('$').map(str::to_owned) else { break };
+            if visited.contains(&name) { return None }
 
-    } else if let Some(tuple_idx) = parse_tuple_name(token, parser, None, None, None) {
-        if let Some(data_type) = tuple_to_data_type(parser.tuple_data_type_arena.get(tuple_idx).unwrap()) {
-            Some(data_type)
-        } else {
-            return None
+            value = self.variable(node, &name)?.clone();
+            visited.push(name);
         }
 
-    } else if let Some(enum_data_type) = parse_enum_data_type(token, parser, key, 0) {
-        Some(enum_data_type)
+        Some(value)
+    }
 
-    } else if let Some(hex_data_type) = parse_hex_data_type(token) {
-        Some(hex_data_type)
+    /// The size of a `rem`, which is the stylesheet's `$RootTextSize` as declared so far, or
+    /// `DEFAULT_TEXT_SIZE` without one.
+    fn root_text_size(&self, node: NodeId<'a>) -> f64 {
+        self.tree.get(TokenTreeNode::ROOT)
+            .and_then(|root| root.variables.get("RootTextSize").cloned())
+            .and_then(|value| self.resolve(node, value))
+            .as_ref().and_then(condition_number)
+            .unwrap_or(DEFAULT_TEXT_SIZE)
+    }
 
-    } else if let Some(tailwind_color_data_type) = parse_tailwind_color_data_type(token) {
-        Some(tailwind_color_data_type)
+    /// The size of an `em`, which is the `TextSize` of `node` or the closest rule around it that
+    /// sets one, falling back to the size of a `rem`.
+    fn text_size(&self, node: NodeId<'a>) -> f64 {
+        let mut current = node;
 
-    } else if let Some(css_color_data_type) = parse_css_color_data_type(token) {
-        Some(css_color_data_type)
-    
-    } else if let Token::DataType(data_type_value) = token {
-        Some(data_type_value.to_owned())
-    
-    } else {
-        return None
+        while let Some(tree_node) = self.tree.get(current) {
+            let size = tree_node.properties.get("TextSize").cloned()
+                .and_then(|value| self.resolve(node, value))
+                .as_ref().and_then(condition_number);
+            if let Some(size) = size { return size }
+
+            match tree_node.parent(current) {
+                Some(parent) => current = parent,
+                None => break
+            }
+        }
+
+        self.root_text_size(node)
     }
-}
 
-fn parse_assignment<'a>(token: &'a Token, parser: &mut Parser<'a>, key: &'a TextType) -> Option<bool> {
-    if let Some(data_type) = parse_data_type(token, parser, Some(key)) {
-        let variant = data_type_to_variant(&data_type);
+    fn is_declaration_start(&self, ahead: usize) -> bool {
+        matches!(self.peek(ahead), Some(Token::Text(_))) && matches!(self.peek(ahead + 1), Some(Token::Equals))
+    }
+
+    /// Returns how many selector tokens there are in a row, starting `ahead` tokens away.
+    fn selector_len(&self, ahead: usize) -> usize {
+        let mut len = 0;
+        while self.peek(ahead + len).is_some_and(is_selector_token) && !self.is_declaration_start(ahead + len) {
+            len += 1;
+        }
+
+        len
+    }
+
+    fn is_equation_start(&self) -> bool {
+        match self.peek(0) {
+            Some(Token::DataType(
+                DataType::Number(_) | DataType::NumberOffset(_) | DataType::NumberScale(_) | DataType::NumberRem(_) | DataType::NumberEm(_)
+            )) => true,
+            Some(Token::Operator(_) | Token::TupleOpen) => true,
+            Some(Token::Text(TextType::Variable(name))) => self.binding(name).is_some_and(is_numeric),
+            Some(Token::Text(name)) => text_of(name) == "udim" && matches!(self.peek(1), Some(Token::TupleOpen)),
+            _ => false
+        }
+    }
+
+    /// Evaluates items into `node` until the end of its rule, or of the stylesheet.
+    fn parse_items(&mut self, node: NodeId<'a>, in_rule: bool) {
+        while let Some(token) = self.peek(0) {
+            match token {
+                Token::ScopeClose => {
+                    self.bump();
+                    if in_rule { return }
+                },
+
+                Token::Text(key) if self.is_declaration_start(0) => {
+                    self.position += 2;
+                    self.parse_declaration(node, key);
+                },
+
+                Token::PriorityDeclaration => {
+                    self.bump();
+
+                    if let Some(Token::DataType(DataType::Number(priority))) = self.peek(0) {
+                        self.bump();
+                        self.node_mut(node).priority = Some(priority.round() as i32);
+                    }
+                },
+
+                Token::EachDeclaration => {
+                    self.bump();
+                    self.parse_each(node);
+                },
+
+                Token::IfDeclaration => {
+                    self.bump();
+                    self.parse_if(node);
+                },
+
+                Token::SheetDeclaration => {
+                    self.bump();
+                    self.parse_sheet(node);
+                },
+
+                // An `@else` that doesn't follow an `@if` is ignored, along with its block.
+                Token::ElseDeclaration => {
+                    self.bump();
+                    self.skip_control();
+                },
+
+                _ => match self.selector_len(0) {
+                    0 => { self.bump(); },
+
+                    // Skipping the whole selector means none of its tokens get looked at again.
+                    len if !matches!(self.peek(len), Some(Token::ScopeOpen)) => self.position += len,
+
+                    len => self.parse_rule(node, len)
+                }
+            }
+        }
+    }
+
+    fn parse_rule(&mut self, parent: NodeId<'a>, selector_len: usize) {
+        let mut parts: Vec<String> = vec![];
+
+        for token in &self.tokens[self.position..self.position + selector_len] {
+            // Loop variables are joined onto whatever comes before them, so `.p-$size` becomes `.p-4`.
+            let binding = match token {
+                Token::Text(TextType::Variable(name)) => self.binding(name),
+                _ => None
+            };
+
+            match (binding, parts.last_mut()) {
+                (Some(value), Some(last)) => last.push_str(&interpolation_text(value)),
+                (Some(value), None) => parts.push(interpolation_text(value)),
+                (None, _) => parts.push(token_to_string(token))
+            }
+        }
+
+        let selector = parts.join(" ");
+        let selector_token = self.token_indices[self.position];
+        self.position += selector_len + 1;
+
+        let node = self.tree.push(TokenTreeNode::new(parent));
+        self.node_mut(parent).insert_rule(selector, node);
+        self.node_mut(node).generated = self.control_depth > 0;
+        self.node_mut(node).selector_token = selector_token;
+
+        self.parse_items(node, true);
+    }
+
+    fn parse_declaration(&mut self, node: NodeId<'a>, key: &'a TextType<'a>) {
+        let Some(data_type) = self.parse_value(node, Some(key)) else { return };
+
+        let variant = match (key, &data_type) {
+            (TextType::NonSpecial(key), DataType::UDim(udim)) if udim.scale == 0.0 && NUMBER_PROPERTIES.contains(*key) => {
+                Variant::Float32(udim.offset as f32)
+            },
+            _ => data_type_to_variant(&data_type)
+        };
+
+        let alpha = match &data_type {
+            DataType::ColorAlpha(_, alpha) => Some(*alpha),
+            DataType::OwnedString(text) => text.strip_prefix('$').and_then(|name| self.variable_alpha(node, name)),
+            _ => None
+        };
+
+        if let TextType::Variable(name) = key {
+            match alpha {
+                Some(alpha) => self.alphas.insert((node, name), alpha),
+                None => self.alphas.remove(&(node, *name))
+            };
+        }
+
+        let node = self.node_mut(node);
+
+        // Roblox keeps opacity apart from color, so a color with alpha sets the paired transparency too.
+        if let (TextType::NonSpecial(key), Some(alpha)) = (key, alpha) {
+            if let Some(transparency) = TRANSPARENCY_PROPERTIES.get(*key) {
+                node.properties.insert(transparency, Variant::Float32(1.0 - alpha));
+            }
+        }
 
-        let current_node = parser.get_mut_tree_node_at(parser.current_tree_node_idx).unwrap();
-    
         match key {
-            TextType::NonSpecial(key) => current_node.properties.insert(key, variant),
-            TextType::Variable(key) => current_node.variables.insert(key, variant),
-            TextType::PsuedoProperty(key) => current_node.psuedo_properties.insert(key, variant),
-    
+            TextType::NonSpecial(key) => node.properties.insert(key, variant),
+            TextType::Variable(key) => node.variables.insert(key, variant),
+            TextType::PsuedoProperty(key) => node.psuedo_properties.insert(key, variant),
+
             _ => None
         };
+    }
 
-        return Some(true)
-    };
+    /// Parses the value at the next token, which is inside of `node`. `key` is the property being
+    /// assigned, which `.Item` enums are completed with. Nothing is consumed if there isn't a value
+    /// there.
+    fn parse_value(&mut self, node: NodeId<'a>, key: Option<&'a TextType<'a>>) -> Option<DataType<'a>> {
+        let token = self.peek(0)?;
 
-    Some(false)
-}
+        match token {
+            _ if self.is_equation_start() => self.parse_equation(node),
 
-fn parse_assignment_equals<'a>(token: &Token, mut parser: &mut Parser<'a>, key: &'a TextType) -> Option<bool> {
-    if !matches!(token, Token::Equals) { return Some(false) }
+            Token::Text(TextType::Variable(name)) => {
+                self.bump();
 
-    let next_token = parser.advance()?;
-    if parse_assignment(next_token, &mut parser, key)? { return Some(true) }
+                match self.binding(name) {
+                    Some(data_type) => Some(data_type.clone()),
+                    None => Some(DataType::OwnedString(format!("${}", name)))
+                }
+            },
 
-    Some(false)
-}
-// ---------------------------------------------------------------------------------------------------
+            Token::Text(name) if matches!(self.peek(1), Some(Token::TupleOpen)) => {
+                self.position += 2;
+                tuple_to_data_type(&self.parse_tuple(node, Some(text_of(name)))?)
+            },
+
+            _ if is_enum_token(token) => Some(self.parse_enum(key)),
+
+            // The lexer gives up on colors like `tw:red:$shade` partway through, leaving `tw:red` as text.
+            Token::Text(TextType::NonSpecial(text)) | Token::DataType(DataType::ColorTw(text) | DataType::ColorCss(text))
+                if is_palette_prefix(text) && matches!(self.peek(1), Some(Token::Colon)) => {
+                self.parse_interpolated_color()
+            },
+
+            Token::Text(TextType::NonSpecial(text @ ("true" | "false"))) => {
+                self.bump();
+                Some(DataType::Bool(*text == "true"))
+            },
+
+            Token::DataType(data_type) => {
+                self.bump();
+
+                Some(parse_hex_data_type(token)
+                    .or_else(|| parse_tailwind_color_data_type(token))
+                    .or_else(|| parse_css_color_data_type(token))
+                    .unwrap_or_else(|| data_type.to_owned()))
+            },
+
+            _ => None
+        }
+    }
+
+    /// Parses a `tw:` or `css:` color with loop variables in its name, like `tw:$color:500/50`, which the
+    /// lexer splits into pieces. Names that aren't in the palette give `None`.
+    fn parse_interpolated_color(&mut self) -> Option<DataType<'a>> {
+        let mut name = match self.bump()? {
+            Token::Text(TextType::NonSpecial(name)) | Token::DataType(DataType::ColorTw(name) | DataType::ColorCss(name)) => {
+                name.to_string()
+            },
+            _ => return None
+        };
+
+        loop {
+            match self.peek(0) {
+                Some(Token::Colon) => name.push(':'),
+                Some(Token::Text(TextType::SelectorStateOrEnumPart(part))) => name.push_str(&format!(":{}", part)),
+                Some(Token::Text(TextType::Variable(variable))) => name.push_str(&interpolation_text(self.binding(variable)?)),
+                _ => break
+            }
+            self.bump();
+        }
+
+        if let (Some(Token::Operator(Operator::Div)), Some(Token::DataType(DataType::Number(opacity)))) = (self.peek(0), self.peek(1)) {
+            name.push_str(&format!("/{}", opacity));
+            self.position += 2;
+        }
+
+        let palette = if name.starts_with("tw:") { &TAILWIND_COLORS } else { &CSS_COLORS };
+        if !palette.contains_key(name.split_once('/').map_or(name.as_str(), |(name, _)| name)) { return None }
+
+        Some(parse_palette_color(palette, &name))
+    }
+
+    /// Parses numbers, operators and udims for as long as they keep coming, then works them out.
+    /// `rem` and `em` numbers become offsets, measured from the text sizes `node` sees. Equations that
+    /// can't be worked out, like `(1 +)`, give `None` once the whole equation has been consumed.
+    fn parse_equation(&mut self, node: NodeId<'a>) -> Option<DataType<'a>> {
+        let mut stack = vec![];
+        let mut is_malformed = false;
+
+        while self.is_equation_start() {
+            match self.bump().unwrap() {
+                Token::DataType(DataType::Number(number)) => stack.push(EquationDataType::Number(*number)),
+                Token::DataType(DataType::NumberOffset(number)) => stack.push(EquationDataType::NumberOffset(*number)),
+                Token::DataType(DataType::NumberScale(number)) => stack.push(EquationDataType::NumberScale(*number)),
+                Token::DataType(DataType::NumberRem(number)) => {
+                    stack.push(EquationDataType::NumberOffset(number * self.root_text_size(node)))
+                },
+                Token::DataType(DataType::NumberEm(number)) => {
+                    stack.push(EquationDataType::NumberOffset(number * self.text_size(node)))
+                },
+
+                Token::Operator(operator) => self.parse_equation_operator(operator, &mut stack),
+
+                Token::TupleOpen => {
+                    let tuple = self.parse_tuple(node, None);
+                    is_malformed |= !tuple.is_some_and(|tuple| push_equation_tuple(&tuple, &mut stack));
+                },
+
+                Token::Text(TextType::Variable(name)) => {
+                    is_malformed |= !push_equation_value(self.binding(name).unwrap().clone(), &mut stack);
+                },
+
+                Token::Text(name) => {
+                    self.bump();
+                    let tuple = self.parse_tuple(node, Some(text_of(name)));
+                    is_malformed |= !tuple.is_some_and(|tuple| push_equation_tuple(&tuple, &mut stack));
+                },
+
+                _ => unreachable!()
+            }
+        }
+
+        if is_malformed { return None }
 
+        resolve_equation_stack(&mut stack)
+    }
+
+    /// Operators next to each other collapse into one, so `- -` adds and `- +` subtracts.
+    fn parse_equation_operator(&mut self, operator: &'a Operator, stack: &mut Vec<EquationDataType<'a>>) {
+        let mut operator = operator;
+
+        while let Some(Token::Operator(next_operator)) = self.peek(0) {
+            self.bump();
+
+            operator = match (operator, next_operator) {
+                (Operator::Sub, Operator::Plus) => &Operator::Sub,
+                (Operator::Sub, Operator::Sub) => &Operator::Plus,
+                _ => next_operator
+            };
+        }
+
+        stack.push(EquationDataType::Operator(operator));
+    }
+
+    /// Parses the values of a tuple whose `(` has been consumed, up to and including its `)`.
+    /// Returns `None` if it stops at something other than a value, or has a value that can't be
+    /// worked out, like `(1 +)`. The rest of the tuple is still consumed then.
+    fn parse_tuple(&mut self, node: NodeId<'a>, name: Option<&'a str>) -> Option<TupleDataType<'a>> {
+        let mut tuple = TupleDataType::new(name);
+        let mut is_malformed = false;
+
+        loop {
+            match self.peek(0)? {
+                Token::TupleClose => {
+                    self.bump();
+                    return (!is_malformed).then_some(tuple)
+                },
+
+                Token::ListDelimiter | Token::SectionClose => { self.bump(); },
+
+                _ => {
+                    let position = self.position;
+
+                    match self.parse_value(node, None) {
+                        Some(data_type) => tuple.push(data_type),
+                        None if self.position == position => return None,
+                        None => is_malformed = true
+                    }
+                }
+            }
+        }
+    }
+
+    /// Parses the values of an `@each`, whose `(` has been consumed, up to and including the `)`. Bare
+    /// words are taken as strings, so `@each $color in (red, blue)` loops over names.
+    fn parse_each_values(&mut self, node: NodeId<'a>) -> Option<Vec<DataType<'a>>> {
+        let mut values = vec![];
+
+        loop {
+            let position = self.position;
+
+            match self.peek(0)? {
+                Token::TupleClose => {
+                    self.bump();
+                    return Some(values)
+                },
+
+                Token::ListDelimiter => { self.bump(); },
+
+                token => match (self.parse_value(node, None), token) {
+                    (Some(value), _) => values.push(value),
+                    (None, Token::Text(TextType::NonSpecial(word))) if self.position == position => {
+                        self.bump();
+                        values.push(DataType::StringSingle(word));
+                    },
+                    (None, _) if self.position == position => return None,
+                    (None, _) => ()
+                }
+            }
+        }
+    }
 
-// Parse Text ----------------------------------------------------------------------------------------
-fn parse_text<'a>(token: &'a Token, mut parser: &mut Parser<'a>) -> Option<bool> {
-    if let Token::Text(text) = token {
-        let next_token = parser.advance()?;
-        if parse_assignment_equals(next_token, &mut parser, text)? { return Some(true) }
-        if parse_scope_name(next_token, &mut parser, 1)? { return Some(true) }
+    /// Evaluates the block at the next token into `node`, up to and including its `}`.
+    fn parse_block(&mut self, node: NodeId<'a>) {
+        self.bump();
+        self.parse_items(node, true);
     }
 
-    Some(false)  
+    /// Evaluates the block of an `@each` or `@if`, marking the rules declared in it as generated.
+    fn parse_control_block(&mut self, node: NodeId<'a>) {
+        self.control_depth += 1;
+        self.parse_block(node);
+        self.control_depth -= 1;
+    }
+
+    /// Skips over the block at the next token, up to and including its `}`.
+    fn skip_block(&mut self) {
+        let mut depth = 0;
+
+        while let Some(token) = self.bump() {
+            match token {
+                Token::ScopeOpen => depth += 1,
+                Token::ScopeClose if depth <= 1 => return,
+                Token::ScopeClose => depth -= 1,
+                _ => ()
+            }
+        }
+    }
+
+    /// Skips the rest of a directive that can't be evaluated, along with its block if it has one.
+    fn skip_control(&mut self) {
+        while let Some(token) = self.peek(0) {
+            match token {
+                Token::ScopeOpen => return self.skip_block(),
+                Token::ScopeClose | Token::SectionClose => return,
+                _ if is_control_token(token) => return,
+                _ => { self.bump(); }
+            }
+        }
+    }
+
+    /// Evaluates `@each $name in (values) { ... }`, whose `@each` has been consumed. The block is
+    /// evaluated into `node` once for each value, with `$name` standing for it.
+    fn parse_each(&mut self, node: NodeId<'a>) {
+        let (Some(Token::Text(TextType::Variable(name))), Some(Token::Text(TextType::NonSpecial("in"))), Some(Token::TupleOpen)) =
+            (self.peek(0), self.peek(1), self.peek(2)) else { return self.skip_control() };
+        self.position += 3;
+
+        let values = match self.parse_each_values(node) {
+            Some(values) if matches!(self.peek(0), Some(Token::ScopeOpen)) => values,
+            _ => return self.skip_control()
+        };
+
+        let block_start = self.position;
+
+        for value in values {
+            self.position = block_start;
+
+            self.bindings.push((name, value));
+            self.parse_control_block(node);
+            self.bindings.pop();
+        }
+
+        // The end of the block still needs finding when there's nothing to loop over.
+        self.position = block_start;
+        self.skip_block();
+    }
+
+    /// Evaluates `@sheet Name { ... }`, whose `@sheet` has been consumed, into a node of its own. Sheets
+    /// are only evaluated at the root, so their variables can see the stylesheet's and nothing else.
+    fn parse_sheet(&mut self, node: NodeId<'a>) {
+        let (Some(Token::Text(TextType::NonSpecial(name))), Some(Token::ScopeOpen)) = (self.peek(0), self.peek(1))
+            else { return self.skip_control() };
+        if node != TokenTreeNode::ROOT { return self.skip_control() }
+        let selector_token = self.token_indices[self.position];
+        self.bump();
+
+        let sheet = self.tree.push(TokenTreeNode::new(node));
+        self.node_mut(sheet).selector_token = selector_token;
+        self.node_mut(node).sheets.push((name, sheet));
+
+        self.parse_block(sheet);
+    }
+
+    /// Evaluates `@if condition { ... }`, whose `@if` has been consumed, along with the `@else if`
+    /// and `@else` blocks after it. Only the block of the first condition which holds is evaluated.
+    fn parse_if(&mut self, node: NodeId<'a>) {
+        let mut is_done = false;
+        let mut has_condition = true;
+
+        loop {
+            let holds = !has_condition || self.parse_condition(node);
+            if !matches!(self.peek(0), Some(Token::ScopeOpen)) { return self.skip_control() }
+
+            if holds && !is_done {
+                self.parse_control_block(node);
+                is_done = true;
+            } else {
+                self.skip_block();
+            }
+
+            if !has_condition || !matches!(self.peek(0), Some(Token::ElseDeclaration)) { return }
+            self.bump();
+
+            has_condition = matches!(self.peek(0), Some(Token::Text(TextType::NonSpecial("if"))));
+            if has_condition { self.bump(); }
+        }
+    }
+
+    fn bump_keyword(&mut self, keyword: &str) -> bool {
+        let is_keyword = matches!(self.peek(0), Some(Token::Text(TextType::NonSpecial(text))) if *text == keyword);
+        if is_keyword { self.bump(); }
+
+        is_keyword
+    }
+
+    /// Evaluates a condition made of comparisons joined with `and`, `or` and `not`, which `and`
+    /// binds tighter than `or`. Variables are looked up in `node` and the rules around it.
+    fn parse_condition(&mut self, node: NodeId<'a>) -> bool {
+        let mut holds = self.parse_and_condition(node);
+        while self.bump_keyword("or") { holds |= self.parse_and_condition(node); }
+
+        holds
+    }
+
+    fn parse_and_condition(&mut self, node: NodeId<'a>) -> bool {
+        let mut holds = self.parse_comparison(node);
+        while self.bump_keyword("and") { holds &= self.parse_comparison(node); }
+
+        holds
+    }
+
+    fn parse_comparison(&mut self, node: NodeId<'a>) -> bool {
+        if self.bump_keyword("not") { return !self.parse_comparison(node) }
+
+        let left = self.parse_operand(node);
+
+        let comparison = match self.peek(0) {
+            Some(Token::Comparison(comparison)) => *comparison,
+            Some(Token::ScopeToChildren) => Comparison::Greater,
+            _ => return is_truthy(&left)
+        };
+        self.bump();
+
+        compare(comparison, &left, &self.parse_operand(node))
+    }
+
+    /// A value in a condition. Variables which aren't defined give `None`.
+    fn parse_operand(&mut self, node: NodeId<'a>) -> Option<Variant> {
+        let value = data_type_to_variant(&self.parse_value(node, None)?);
+        self.resolve(node, value)
+    }
+
+    /// Parses `Enum.Name.Item`, or `.Item`, which is short for `Enum.<Property>.Item`.
+    fn parse_enum(&mut self, key: Option<&'a TextType<'a>>) -> DataType<'a> {
+        let mut parts = vec![];
+        if !matches!(self.peek(0), Some(Token::EnumKeyword)) { parts.push(String::from("Enum")) }
+
+        while let Some(token) = self.peek(0).filter(|token| is_enum_token(token)) {
+            parts.push(token_to_string_as_enum(token));
+            self.bump();
+        }
+
+        if let (2, Some(key)) = (parts.len(), key) {
+            parts.insert(1, token_to_string_as_enum(&Token::Text(key.clone())));
+        }
+
+        DataType::OwnedString(parts.join("."))
+    }
 }
 // ---------------------------------------------------------------------------------------------------
 
 
-pub fn parse_rsml<'a>(tokens: &'a Vec<Token>) -> Arena<TokenTreeNode<'a>> {
+/// Evaluates a stylesheet's tokens into a tree of rules. The root is the first node, and the rest
+/// are numbered in the order they appear in the source. Anything that can't be understood is
+/// skipped. Tokens are only looked at once, apart from the blocks of `@each` loops, which are
+/// evaluated once for every value, so this takes time linear in the size of the result.
+pub fn parse_rsml<'a>(tokens: &'a [Token]) -> Arena<TokenTreeNode<'a>> {
     let mut parser = Parser::new(tokens);
 
-    let root_node = TokenTreeNode::new(0);
-    parser.add_tree_node(root_node);
+    let root = parser.tree.push(TokenTreeNode::new(TokenTreeNode::ROOT));
+    parser.parse_items(root, false);
+
+    parser.tree
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lexer::lex_rsml;
 
-    let tokens_len = tokens.len();
+    fn rule<'t, 'a>(tree: &'t Arena<TokenTreeNode<'a>>, node: NodeId<'a>, selector: &str) -> (NodeId<'a>, &'t TokenTreeNode<'a>) {
+        let id = tree.get(node).unwrap().rules.0[selector][0];
+        (id, tree.get(id).unwrap())
+    }
+
+    #[test]
+    fn rules_in_source_order() {
+        let tokens = lex_rsml("Frame { Child { } } Frame > TextLabel, #Name:hover { } Text { }");
+        let tree = parse_rsml(&tokens);
+        let root = TokenTreeNode::ROOT;
+
+        let (frame, frame_node) = rule(&tree, root, "Frame");
+        let (child, child_node) = rule(&tree, frame, "Child");
+        let (tagged, _) = rule(&tree, root, "Frame > TextLabel , #Name :hover");
+        let (text, _) = rule(&tree, root, "Text");
+
+        assert_eq!([frame, child, tagged, text].map(Id::index), [1, 2, 3, 4]);
+        assert_eq!(child_node.parent_idx, frame);
+        assert_eq!(frame_node.parent_idx, root);
+    }
+
+    #[test]
+    fn values() {
+        let tokens = lex_rsml("
+            $Gap = 4px;
+            TextLabel {
+                @priority 2.6;
+                Size = udim2(100% - 10px, 50% + 2px);
+                Padding = 2 * udim(50%, 10px);
+                TextSize = (1 + 2) * 3 - -1;
+                TextXAlignment = .Left;
+                Font = Enum.Font.Gotham;
+                FontFace = font(\"rbxasset://fonts/families/GothamSSm.json\", \"Bold\");
+                BackgroundColor3 = rgb(255, 0, 0);
+                TextColor3 = $Text;
+                !Pseudo = 1;
+            }
+        ");
+        let tree = parse_rsml(&tokens);
+        let (_, label) = rule(&tree, TokenTreeNode::ROOT, "TextLabel");
+
+        assert_eq!(tree[TokenTreeNode::ROOT].variables["Gap"], Variant::UDim(UDim::new(0.0, 4)));
+        assert_eq!(label.priority, Some(3));
+        assert_eq!(label.properties["Size"], Variant::UDim2(UDim2::new(UDim::new(1.0, -10), UDim::new(0.5, 2))));
+        assert_eq!(label.properties["TextSize"], Variant::Float32(10.0));
+        assert_eq!(label.properties["TextXAlignment"], Variant::String(String::from("Enum.TextXAlignment.Left")));
+        assert_eq!(label.properties["Font"], Variant::String(String::from("Enum.Font.Gotham")));
+        assert_eq!(label.properties["BackgroundColor3"], Variant::Color3(Color3::new(1.0, 0.0, 0.0)));
+        assert_eq!(label.properties["TextColor3"], Variant::String(String::from("$Text")));
+        assert_eq!(label.psuedo_properties["Pseudo"], Variant::Float32(1.0));
+
+        let Variant::Font(font) = &label.properties["FontFace"] else { panic!() };
+        assert_eq!(font.weight, FontWeight::Bold);
+    }
 
-    if tokens_len == 0 { return parser.tree_node_arena }
+    #[test]
+    fn each() {
+        let tokens = lex_rsml("
+            @each $size in (4, 8) {
+                #p-$size { Padding = udim($size * 2px, 0); TextSize = $size; }
+                @each $inner in (1) { #Item$inner { LayoutOrder = $size + $inner; } }
+            }
+            @each $empty in () { Frame { } }
+        ");
+        let tree = parse_rsml(&tokens);
+        let root = TokenTreeNode::ROOT;
+
+        let (_, small) = rule(&tree, root, "#p-4");
+        assert_eq!(small.properties["Padding"], Variant::UDim(UDim::new(0.0, 8)));
+        assert_eq!(small.properties["TextSize"], Variant::Float32(4.0));
+        assert_eq!(rule(&tree, root, "#p-8").1.properties["TextSize"], Variant::Float32(8.0));
+
+        assert_eq!(tree[root].rules.0["#Item1"].len(), 2);
+        assert_eq!(tree[rule(&tree, root, "#Item1").0].properties["LayoutOrder"], Variant::Float32(5.0));
+        assert!(!tree[root].rules.0.contains_key("Frame"));
+    }
+
+    #[test]
+    fn each_colors() {
+        let tokens = lex_rsml("
+            @each $color in (red, blue) { .bg-$color { BackgroundColor3 = tw:$color:500; ImageColor3 = tw:$color:500/50; } }
+            @each $shade in (100, 900) { .red-$shade { BackgroundColor3 = tw:red:$shade; } }
+            @each $name in (rebeccapurple, notacolor) { .css-$name { BackgroundColor3 = css:$name; } }
+        ");
+        let tree = parse_rsml(&tokens);
+        let root = TokenTreeNode::ROOT;
+
+        let (_, blue) = rule(&tree, root, ".bg-blue");
+        assert_eq!(blue.properties["BackgroundColor3"], Variant::Color3(tailwind_color("tw:blue:500").unwrap()));
+        assert_eq!(blue.properties["ImageColor3"], Variant::Color3(tailwind_color("tw:blue:500").unwrap()));
+        assert_eq!(blue.properties["ImageTransparency"], Variant::Float32(0.5));
+        assert!(tree[root].rules.0.contains_key(".bg-red"));
+
+        let (_, shade) = rule(&tree, root, ".red-900");
+        assert_eq!(shade.properties["BackgroundColor3"], Variant::Color3(tailwind_color("tw:red:900").unwrap()));
+
+        let (_, purple) = rule(&tree, root, ".css-rebeccapurple");
+        assert_eq!(purple.properties["BackgroundColor3"], Variant::Color3(css_color("css:rebeccapurple").unwrap()));
+        assert!(rule(&tree, root, ".css-notacolor").1.properties.is_empty());
+    }
 
-    while parser.position != tokens_len - 1 {
+    #[test]
+    fn conditions() {
+        let tokens = lex_rsml("
+            $Compact = 1; $Scale = 2;
+            @if $Compact and $Scale > 1 { A { } } @else { B { } }
+            @if not $Compact { C { } } @else if $Scale == 2 { D { } } @else { E { } }
+            @each $size in (1, 2) { @if $size >= 2 { Large$size { } } }
+        ");
+        let tree = parse_rsml(&tokens);
+
+        let mut selectors = tree[TokenTreeNode::ROOT].rules.0.keys().collect::<Vec<_>>();
+        selectors.sort();
+        assert_eq!(selectors, [&"A", &"D", &"Large2"]);
+    }
 
-        if let Some(token) = parser.get_token_at(parser.position) { parse_text(token, &mut parser); }
-        if let Some(token) = parser.get_token_at(parser.position) { parse_scope_name(token, &mut parser, 0); }
-        if let Some(token) = parser.get_token_at(parser.position) { parse_priority(token, &mut parser); }
-        if let Some(token) = parser.get_token_at(parser.position) { parse_scope_close(token, &mut parser); }
-        
-        parser.advance();
+    #[test]
+    fn booleans() {
+        let tokens = lex_rsml("
+            $Dark = true; $Compact = false;
+            @if $Dark { A { Visible = $Compact; } }
+            @if $Compact or false { B { } }
+            @if $Dark == true { C { } }
+        ");
+        let tree = parse_rsml(&tokens);
+        let root = &tree[TokenTreeNode::ROOT];
+
+        assert_eq!(root.variables["Dark"], Variant::Bool(true));
+        assert_eq!(root.variables["Compact"], Variant::Bool(false));
+
+        let mut selectors = root.rules.0.keys().collect::<Vec<_>>();
+        selectors.sort();
+        assert_eq!(selectors, [&"A", &"C"]);
     }
 
-    parser.tree_node_arena
-}
\ No newline at end of file
+    #[test]
+    fn relative_units() {
+        let tokens = lex_rsml("
+            Frame { Padding = 2rem; }
+            $RootTextSize = $Base; $Base = 20;
+            TextLabel {
+                TextSize = 10;
+                Size = udim2(100% - 1rem, 1.5em);
+                ::UIPadding { PaddingTop = 2em; }
+            }
+            TextButton { Padding = -0.5em + 1px; }
+        ");
+        let tree = parse_rsml(&tokens);
+        let root = TokenTreeNode::ROOT;
+
+        // `$RootTextSize` only counts from where it's declared, so the first rule uses the default.
+        assert_eq!(rule(&tree, root, "Frame").1.properties["Padding"], Variant::UDim(UDim::new(0.0, 32)));
+
+        let (label, label_node) = rule(&tree, root, "TextLabel");
+        assert_eq!(label_node.properties["Size"], Variant::UDim2(UDim2::new(UDim::new(1.0, -20), UDim::new(0.0, 15))));
+        assert_eq!(rule(&tree, label, "::UIPadding").1.properties["PaddingTop"], Variant::UDim(UDim::new(0.0, 20)));
+
+        assert_eq!(rule(&tree, root, "TextButton").1.properties["Padding"], Variant::UDim(UDim::new(0.0, -9)));
+    }
+
+    #[test]
+    fn relative_text_sizes() {
+        let tokens = lex_rsml("
+            $RootTextSize = 10;
+            TextLabel { TextSize = 2rem; ::UIPadding { PaddingTop = 0.5em; } }
+            TextButton { TextSize = 1.5rem + 1px; LineHeight = 2px; Padding = 2rem; Size = 2rem; }
+        ");
+        let tree = parse_rsml(&tokens);
+        let root = TokenTreeNode::ROOT;
+
+        let (label, label_node) = rule(&tree, root, "TextLabel");
+        assert_eq!(label_node.properties["TextSize"], Variant::Float32(20.0));
+        assert_eq!(rule(&tree, label, "::UIPadding").1.properties["PaddingTop"], Variant::UDim(UDim::new(0.0, 10)));
+
+        let button = rule(&tree, root, "TextButton").1;
+        assert_eq!(button.properties["TextSize"], Variant::Float32(16.0));
+        assert_eq!(button.properties["LineHeight"], Variant::Float32(2.0));
+        assert_eq!(button.properties["Padding"], Variant::UDim(UDim::new(0.0, 20)));
+        assert_eq!(button.properties["Size"], Variant::UDim(UDim::new(0.0, 20)));
+    }
+
+    #[test]
+    fn alpha_colors() {
+        let tokens = lex_rsml("
+            Frame {
+                BackgroundColor3 = #ff000080;
+                TextColor3 = rgba(255, 255, 255, 25%);
+                ImageColor3 = tw:red:500/50;
+                BorderColor3 = #f008;
+                TextStrokeColor3 = rgb(0, 0, 0);
+            }
+        ");
+        let tree = parse_rsml(&tokens);
+        let (_, frame) = rule(&tree, TokenTreeNode::ROOT, "Frame");
+
+        assert_eq!(frame.properties["BackgroundColor3"], Variant::Color3(Color3::new(1.0, 0.0, 0.0)));
+        assert_eq!(frame.properties["BackgroundTransparency"], Variant::Float32(1.0 - 128.0 / 255.0));
+        assert_eq!(frame.properties["TextTransparency"], Variant::Float32(0.75));
+        assert_eq!(frame.properties["ImageColor3"], Variant::Color3(tailwind_color("tw:red:500").unwrap()));
+        assert_eq!(frame.properties["ImageTransparency"], Variant::Float32(0.5));
+
+        // Borders have no transparency of their own, and opaque colors leave transparency alone.
+        assert_eq!(frame.properties["BorderColor3"], Variant::Color3(Color3::new(1.0, 0.0, 0.0)));
+        assert!(!frame.properties.contains_key("BorderTransparency"));
+        assert!(!frame.properties.contains_key("TextStrokeTransparency"));
+    }
+
+    #[test]
+    fn alpha_variables() {
+        let tokens = lex_rsml("
+            $Overlay = #00000080; $Scrim = $Overlay;
+            Frame { BackgroundColor3 = $Overlay; ImageColor3 = $Scrim; }
+            Label { $Overlay = #000000; BackgroundColor3 = $Overlay; TextColor3 = $Scrim; }
+        ");
+        let tree = parse_rsml(&tokens);
+        let root = TokenTreeNode::ROOT;
+
+        let (_, frame) = rule(&tree, root, "Frame");
+        assert_eq!(frame.properties["BackgroundColor3"], Variant::String("$Overlay".into()));
+        assert_eq!(frame.properties["BackgroundTransparency"], Variant::Float32(1.0 - 128.0 / 255.0));
+        assert_eq!(frame.properties["ImageTransparency"], Variant::Float32(1.0 - 128.0 / 255.0));
+
+        // An opaque color declared closer in hides the translucent one.
+        let (_, label) = rule(&tree, root, "Label");
+        assert!(!label.properties.contains_key("BackgroundTransparency"));
+        assert_eq!(label.properties["TextTransparency"], Variant::Float32(1.0 - 128.0 / 255.0));
+    }
+
+    #[test]
+    fn broken_source() {
+        let tokens = lex_rsml("} Frame { --[[ Size = 1; ]] Stray Size = ; Offset = 5 Label { } Position = udim2(1, 0");
+        let tree = parse_rsml(&tokens);
+        let (frame, frame_node) = rule(&tree, TokenTreeNode::ROOT, "Frame");
+
+        assert_eq!(frame_node.properties.keys().collect::<Vec<_>>(), [&"Offset"]);
+        assert_eq!(rule(&tree, frame, "Label").1.parent_idx, frame);
+    }
+
+    #[test]
+    fn malformed_equations() {
+        let tokens = lex_rsml("
+            Frame { Size = 3 * $A + 2; Position = 2 *; Rotation = 1 + 2; }
+            TextLabel { Size = (1 +); Position = (); Rotation = (1 + 2) * 2; TextSize = udim(1 *) + 1; }
+        ");
+        let tree = parse_rsml(&tokens);
+        let (_, frame) = rule(&tree, TokenTreeNode::ROOT, "Frame");
+
+        assert!(!frame.properties.contains_key("Size"));
+        assert!(!frame.properties.contains_key("Position"));
+        assert_eq!(frame.properties["Rotation"], Variant::Float32(3.0));
+
+        let (_, label) = rule(&tree, TokenTreeNode::ROOT, "TextLabel");
+        assert_eq!(label.properties.keys().collect::<Vec<_>>(), [&"Rotation"]);
+        assert_eq!(label.properties["Rotation"], Variant::Float32(6.0));
+    }
+}
@@ -0,0 +1,54 @@
+//! Resolving the paths that stylesheets refer to other files by.
+
+// Modules -------------------------------------------------------------------------------------------
+use std::path::{Component, Path, PathBuf};
+// ---------------------------------------------------------------------------------------------------
+
+
+/// Resolves `..` and `.` without touching the file system, so derived paths compare equal to real ones.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => { normalized.pop(); },
+            other => normalized.push(other)
+        }
+    }
+
+    normalized
+}
+
+/// The file that `@derive "derived";` in the stylesheet at `path` refers to. Derived paths are
+/// relative to the stylesheet, and can leave off the `.rsml` extension.
+pub fn derived_file_path(path: &Path, derived: &str) -> PathBuf {
+    let mut derived_path = path.parent().unwrap_or(path).join(derived);
+    if derived_path.extension().is_none() { derived_path.set_extension("rsml"); }
+
+    normalize_path(&derived_path)
+}
+
+/// The file that `@tokens "tokens";` in the stylesheet at `path` refers to, relative to the stylesheet.
+pub fn design_token_file_path(path: &Path, tokens: &str) -> PathBuf {
+    normalize_path(&path.parent().unwrap_or(path).join(tokens))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalized_paths() {
+        assert_eq!(normalize_path(Path::new("src/Theme/../Theme/./Button.rsml")), Path::new("src/Theme/Button.rsml"));
+        assert_eq!(normalize_path(Path::new("/ui/../../theme.rsml")), Path::new("/theme.rsml"));
+    }
+
+    #[test]
+    fn referenced_files() {
+        assert_eq!(derived_file_path(Path::new("/ui/button.rsml"), "../theme"), Path::new("/theme.rsml"));
+        assert_eq!(derived_file_path(Path::new("/ui/button.rsml"), "./base.rsml"), Path::new("/ui/base.rsml"));
+        assert_eq!(design_token_file_path(Path::new("/ui/button.rsml"), "../tokens.json"), Path::new("/tokens.json"));
+    }
+}
@@ -0,0 +1,575 @@
+//! A lossless syntax tree for RSML.
+//!
+//! Unlike `parse_rsml`, which evaluates a stylesheet, this keeps every byte of the source around:
+//! whitespace and comments are stored as trivia in front of the token that follows them, so the
+//! tree always prints back out to exactly the text it was parsed from. Tools which need to
+//! rewrite source text, like the formatter, build on top of it.
+
+// Modules -------------------------------------------------------------------------------------------
+use crate::lexer::{DataType, TextType, Token};
+use crate::parser::token_to_string;
+
+use logos::Logos;
+
+use std::fmt;
+use std::ops::Range;
+// ---------------------------------------------------------------------------------------------------
+
+
+// Data ----------------------------------------------------------------------------------------------
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    LineComment,
+    BlockComment,
+    /// Text that the lexer could not make sense of.
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia<'a> {
+    pub kind: TriviaKind,
+    pub text: &'a str,
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxToken<'a> {
+    /// Whitespace and comments between the previous token and this one.
+    pub leading_trivia: Vec<Trivia<'a>>,
+    pub token: Token<'a>,
+    pub text: &'a str,
+    pub span: Range<usize>,
+}
+
+/// A `Key = value;` assignment of a property, `$variable` or `!psuedo-property`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeclarationSyntax<'a> {
+    pub key: SyntaxToken<'a>,
+    pub equals: SyntaxToken<'a>,
+    pub value: Vec<SyntaxToken<'a>>,
+    pub terminator: Option<SyntaxToken<'a>>,
+}
+
+impl<'a> DeclarationSyntax<'a> {
+    pub fn key_text_type(&self) -> &TextType<'a> {
+        match &self.key.token {
+            Token::Text(text_type) => text_type,
+            _ => unreachable!("declarations always start with a text token")
+        }
+    }
+
+    /// The span of the value, or an empty span after the `=` if there is no value.
+    pub fn value_span(&self) -> Range<usize> {
+        match (self.value.first(), self.value.last()) {
+            (Some(first), Some(last)) => first.span.start..last.span.end,
+            _ => self.equals.span.end..self.equals.span.end
+        }
+    }
+
+    pub fn span(&self) -> Range<usize> {
+        let end = match &self.terminator {
+            Some(terminator) => terminator.span.end,
+            None => self.value_span().end
+        };
+
+        self.key.span.start..end
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrioritySyntax<'a> {
+    pub keyword: SyntaxToken<'a>,
+    pub value: Option<SyntaxToken<'a>>,
+    pub terminator: Option<SyntaxToken<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleSyntax<'a> {
+    pub selector: Vec<SyntaxToken<'a>>,
+    pub open: SyntaxToken<'a>,
+    pub items: Vec<ItemSyntax<'a>>,
+    /// Missing if the source ended before the rule was closed.
+    pub close: Option<SyntaxToken<'a>>,
+}
+
+impl<'a> RuleSyntax<'a> {
+    /// The selector exactly as `parse_rsml` names the rule.
+    pub fn selector_text(&self) -> String {
+        self.selector.iter()
+            .filter(|token| !matches!(token.token, Token::MacroDeclaration))
+            .map(|token| token_to_string(&token.token))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    pub fn selector_span(&self) -> Range<usize> {
+        self.selector.first().unwrap().span.start..self.selector.last().unwrap().span.end
+    }
+
+    /// Everything between the braces of the rule.
+    pub fn body_span(&self, source_len: usize) -> Range<usize> {
+        let end = match &self.close {
+            Some(close) => close.span.start,
+            None => source_len
+        };
+
+        self.open.span.end..end
+    }
+}
+
+/// An `@each`, `@if`, `@else` or `@sheet` directive and the block it controls. The items in the
+/// block belong to the rule the directive is in, apart from those of an `@sheet`, which make up a
+/// StyleSheet of their own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlSyntax<'a> {
+    pub keyword: SyntaxToken<'a>,
+    /// Everything between the keyword and the `{`, like `$size in (4, 8)` or a condition.
+    pub header: Vec<SyntaxToken<'a>>,
+    pub open: SyntaxToken<'a>,
+    pub items: Vec<ItemSyntax<'a>>,
+    /// Missing if the source ended before the block was closed.
+    pub close: Option<SyntaxToken<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemSyntax<'a> {
+    Rule(RuleSyntax<'a>),
+    Declaration(DeclarationSyntax<'a>),
+    Priority(PrioritySyntax<'a>),
+    Control(ControlSyntax<'a>),
+    /// Tokens which don't form any construct we know about, kept as they are.
+    Other(Vec<SyntaxToken<'a>>),
+}
+
+impl<'a> ItemSyntax<'a> {
+    pub fn first_token(&self) -> &SyntaxToken<'a> {
+        match self {
+            ItemSyntax::Rule(rule) => &rule.selector[0],
+            ItemSyntax::Declaration(declaration) => &declaration.key,
+            ItemSyntax::Priority(priority) => &priority.keyword,
+            ItemSyntax::Control(control) => &control.keyword,
+            ItemSyntax::Other(tokens) => &tokens[0],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleSheetSyntax<'a> {
+    pub items: Vec<ItemSyntax<'a>>,
+    /// Whitespace and comments after the last token.
+    pub trailing_trivia: Vec<Trivia<'a>>,
+}
+
+impl<'a> StyleSheetSyntax<'a> {
+    /// Every token in the tree in source order, without trivia. This is what `lex_rsml` would have
+    /// produced, minus comments, so it can be fed straight to `parse_rsml`.
+    pub fn tokens(&self) -> Vec<&SyntaxToken<'a>> {
+        let mut tokens = vec![];
+        push_item_tokens(&self.items, &mut tokens);
+
+        tokens
+    }
+}
+
+fn push_item_tokens<'s, 'a>(items: &'s [ItemSyntax<'a>], tokens: &mut Vec<&'s SyntaxToken<'a>>) {
+    for item in items {
+        match item {
+            ItemSyntax::Rule(rule) => {
+                tokens.extend(&rule.selector);
+                tokens.push(&rule.open);
+                push_item_tokens(&rule.items, tokens);
+                tokens.extend(&rule.close);
+            },
+            ItemSyntax::Declaration(declaration) => {
+                tokens.extend([&declaration.key, &declaration.equals]);
+                tokens.extend(&declaration.value);
+                tokens.extend(&declaration.terminator);
+            },
+            ItemSyntax::Priority(priority) => {
+                tokens.push(&priority.keyword);
+                tokens.extend(&priority.value);
+                tokens.extend(&priority.terminator);
+            },
+            ItemSyntax::Control(control) => {
+                tokens.push(&control.keyword);
+                tokens.extend(&control.header);
+                tokens.push(&control.open);
+                push_item_tokens(&control.items, tokens);
+                tokens.extend(&control.close);
+            },
+            ItemSyntax::Other(other) => tokens.extend(other)
+        }
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Lex -----------------------------------------------------------------------------------------------
+fn push_gap_trivia<'a>(source: &'a str, span: Range<usize>, trivia: &mut Vec<Trivia<'a>>) {
+    if span.is_empty() { return }
+
+    let text = &source[span.clone()];
+    let kind = if text.trim().is_empty() { TriviaKind::Whitespace } else { TriviaKind::Unknown };
+
+    trivia.push(Trivia { kind, text, span });
+}
+
+/// Lexes the source into tokens which carry their leading trivia, along with the trivia at the end.
+fn lex_syntax_tokens(source: &str) -> (Vec<SyntaxToken<'_>>, Vec<Trivia<'_>>) {
+    let mut tokens = vec![];
+    let mut trivia = vec![];
+    let mut cursor = 0;
+
+    // The lexer tokenizes the insides of comments, so it gets restarted after each one.
+    'lex: loop {
+        let offset = cursor;
+
+        for (result, span) in Token::lexer(&source[offset..]).spanned() {
+            let span = span.start + offset..span.end + offset;
+            push_gap_trivia(source, cursor..span.start, &mut trivia);
+
+            let rest = &source[span.start..];
+            if rest.starts_with("--") {
+                let (kind, len) = if rest.starts_with("--[[") {
+                    (TriviaKind::BlockComment, rest.find("]]").map(|idx| idx + 2).unwrap_or(rest.len()))
+                } else {
+                    (TriviaKind::LineComment, rest.find(['\n', '\r', '\x0c']).unwrap_or(rest.len()))
+                };
+
+                let comment_span = span.start..span.start + len;
+                trivia.push(Trivia { kind, text: &source[comment_span.clone()], span: comment_span.clone() });
+
+                cursor = comment_span.end;
+                continue 'lex;
+            }
+
+            match result {
+                Ok(token) => tokens.push(SyntaxToken {
+                    leading_trivia: std::mem::take(&mut trivia),
+                    token,
+                    text: &source[span.clone()],
+                    span: span.clone(),
+                }),
+
+                Err(_) => push_gap_trivia(source, span.clone(), &mut trivia)
+            }
+
+            cursor = span.end;
+        }
+
+        break;
+    }
+
+    push_gap_trivia(source, cursor..source.len(), &mut trivia);
+
+    (tokens, trivia)
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Parse ---------------------------------------------------------------------------------------------
+pub(crate) fn is_selector_token(token: &Token) -> bool {
+    matches!(token, Token::Text(_) | Token::ScopeToChildren | Token::ScopeToDescendants | Token::ListDelimiter)
+}
+
+pub(crate) fn is_control_token(token: &Token) -> bool {
+    matches!(token, Token::EachDeclaration | Token::IfDeclaration | Token::ElseDeclaration | Token::SheetDeclaration)
+}
+
+struct SyntaxParser<'a> {
+    /// The remaining tokens, in reverse order so that the next one can be popped off the end.
+    tokens: Vec<SyntaxToken<'a>>,
+}
+
+impl<'a> SyntaxParser<'a> {
+    fn peek(&self, ahead: usize) -> Option<&Token<'a>> {
+        let len = self.tokens.len();
+        if ahead >= len { return None }
+
+        Some(&self.tokens[len - 1 - ahead].token)
+    }
+
+    fn bump(&mut self) -> SyntaxToken<'a> {
+        self.tokens.pop().expect("bumped past the end of the tokens")
+    }
+
+    fn bump_if(&mut self, predicate: fn(&Token) -> bool) -> Option<SyntaxToken<'a>> {
+        if self.peek(0).is_some_and(predicate) { Some(self.bump()) } else { None }
+    }
+
+    fn is_declaration_start(&self, ahead: usize) -> bool {
+        matches!(self.peek(ahead), Some(Token::Text(_))) && matches!(self.peek(ahead + 1), Some(Token::Equals))
+    }
+
+    /// Returns how many selector tokens there are in a row, starting `ahead` tokens away.
+    fn selector_len(&self, ahead: usize) -> usize {
+        let mut len = 0;
+        while self.peek(ahead + len).is_some_and(is_selector_token) && !self.is_declaration_start(ahead + len) {
+            len += 1;
+        }
+
+        len
+    }
+
+    fn is_rule_start(&self, ahead: usize) -> bool {
+        let selector_len = self.selector_len(ahead);
+        selector_len > 0 && matches!(self.peek(ahead + selector_len), Some(Token::ScopeOpen))
+    }
+
+    /// Whether the control directive at the next token has a block, ie. there's a `{` before the
+    /// section or rule it's in ends.
+    fn is_control_start(&self) -> bool {
+        let mut ahead = 1;
+
+        loop {
+            match self.peek(ahead) {
+                Some(Token::ScopeOpen) => return true,
+                Some(Token::ScopeClose | Token::SectionClose) | None => return false,
+                _ => ahead += 1
+            }
+        }
+    }
+
+    fn parse_items(&mut self, in_rule: bool) -> Vec<ItemSyntax<'a>> {
+        let mut items = vec![];
+
+        while let Some(token) = self.peek(0) {
+            let item = match token {
+                Token::ScopeClose if in_rule => break,
+
+                Token::Text(_) if self.is_declaration_start(0) => ItemSyntax::Declaration(self.parse_declaration()),
+
+                Token::PriorityDeclaration => ItemSyntax::Priority(self.parse_priority()),
+
+                token if is_control_token(token) && self.is_control_start() => ItemSyntax::Control(self.parse_control()),
+
+                // `@macro Name { ... }` is evaluated like any other rule.
+                Token::MacroDeclaration if self.is_rule_start(1) => ItemSyntax::Rule(self.parse_rule(self.selector_len(1) + 1)),
+
+                _ if self.is_rule_start(0) => ItemSyntax::Rule(self.parse_rule(self.selector_len(0))),
+
+                _ => ItemSyntax::Other(self.parse_other())
+            };
+
+            items.push(item);
+        }
+
+        items
+    }
+
+    fn parse_rule(&mut self, selector_len: usize) -> RuleSyntax<'a> {
+        let selector = (0..selector_len).map(|_| self.bump()).collect();
+        let open = self.bump();
+        let items = self.parse_items(true);
+        let close = self.bump_if(|token| matches!(token, Token::ScopeClose));
+
+        RuleSyntax { selector, open, items, close }
+    }
+
+    fn parse_control(&mut self) -> ControlSyntax<'a> {
+        let keyword = self.bump();
+
+        let mut header = vec![];
+        while !matches!(self.peek(0), Some(Token::ScopeOpen)) { header.push(self.bump()); }
+
+        let open = self.bump();
+        let items = self.parse_items(true);
+        let close = self.bump_if(|token| matches!(token, Token::ScopeClose));
+
+        ControlSyntax { keyword, header, open, items, close }
+    }
+
+    fn parse_declaration(&mut self) -> DeclarationSyntax<'a> {
+        let key = self.bump();
+        let equals = self.bump();
+
+        let mut value = vec![];
+        let mut depth: usize = 0;
+
+        while let Some(token) = self.peek(0) {
+            match token {
+                Token::TupleOpen => depth += 1,
+                Token::TupleClose => depth = depth.saturating_sub(1),
+
+                Token::SectionClose | Token::ScopeOpen | Token::ScopeClose
+                | Token::PriorityDeclaration | Token::MacroDeclaration | Token::DeriveDeclaration
+                | Token::TokensDeclaration | Token::UtilitiesDeclaration | Token::EachDeclaration
+                | Token::IfDeclaration | Token::ElseDeclaration | Token::SheetDeclaration if depth == 0 => break,
+
+                Token::Text(_) if depth == 0 && !value.is_empty()
+                    && (self.is_declaration_start(0) || self.is_rule_start(0)) => break,
+
+                _ => ()
+            }
+
+            value.push(self.bump());
+        }
+
+        let terminator = self.bump_if(|token| matches!(token, Token::SectionClose));
+
+        DeclarationSyntax { key, equals, value, terminator }
+    }
+
+    fn parse_priority(&mut self) -> PrioritySyntax<'a> {
+        let keyword = self.bump();
+        let value = self.bump_if(|token| matches!(token, Token::DataType(DataType::Number(_))));
+        let terminator = self.bump_if(|token| matches!(token, Token::SectionClose));
+
+        PrioritySyntax { keyword, value, terminator }
+    }
+
+    /// Collects tokens up to the end of the current section, stopping early at anything which
+    /// changes the structure of the tree.
+    fn parse_other(&mut self) -> Vec<SyntaxToken<'a>> {
+        let mut tokens = vec![self.bump()];
+        if matches!(tokens[0].token, Token::SectionClose | Token::ScopeOpen | Token::ScopeClose) { return tokens }
+
+        while let Some(token) = self.peek(0) {
+            if matches!(token, Token::ScopeOpen | Token::ScopeClose) || self.is_declaration_start(0) || self.is_rule_start(0) { break }
+
+            let token = self.bump();
+            let is_end = matches!(token.token, Token::SectionClose);
+            tokens.push(token);
+
+            if is_end { break }
+        }
+
+        tokens
+    }
+}
+
+/// Parses RSML source into a lossless syntax tree. This never fails: anything which isn't
+/// understood ends up in [`ItemSyntax::Other`] or in trivia.
+pub fn parse_syntax(source: &str) -> StyleSheetSyntax<'_> {
+    let (mut tokens, trailing_trivia) = lex_syntax_tokens(source);
+    tokens.reverse();
+
+    let mut parser = SyntaxParser { tokens };
+    let items = parser.parse_items(false);
+
+    StyleSheetSyntax { items, trailing_trivia }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Display -------------------------------------------------------------------------------------------
+fn fmt_token(token: &SyntaxToken, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for trivia in &token.leading_trivia {
+        formatter.write_str(trivia.text)?;
+    }
+
+    formatter.write_str(token.text)
+}
+
+fn fmt_items(items: &[ItemSyntax], formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for item in items {
+        match item {
+            ItemSyntax::Rule(rule) => {
+                for token in &rule.selector { fmt_token(token, formatter)?; }
+                fmt_token(&rule.open, formatter)?;
+                fmt_items(&rule.items, formatter)?;
+                if let Some(close) = &rule.close { fmt_token(close, formatter)?; }
+            },
+
+            ItemSyntax::Declaration(declaration) => {
+                fmt_token(&declaration.key, formatter)?;
+                fmt_token(&declaration.equals, formatter)?;
+                for token in &declaration.value { fmt_token(token, formatter)?; }
+                if let Some(terminator) = &declaration.terminator { fmt_token(terminator, formatter)?; }
+            },
+
+            ItemSyntax::Priority(priority) => {
+                fmt_token(&priority.keyword, formatter)?;
+                if let Some(value) = &priority.value { fmt_token(value, formatter)?; }
+                if let Some(terminator) = &priority.terminator { fmt_token(terminator, formatter)?; }
+            },
+
+            ItemSyntax::Control(control) => {
+                fmt_token(&control.keyword, formatter)?;
+                for token in &control.header { fmt_token(token, formatter)?; }
+                fmt_token(&control.open, formatter)?;
+                fmt_items(&control.items, formatter)?;
+                if let Some(close) = &control.close { fmt_token(close, formatter)?; }
+            },
+
+            ItemSyntax::Other(tokens) => {
+                for token in tokens { fmt_token(token, formatter)?; }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the tree back out as source text, which is always identical to the parsed source.
+impl fmt::Display for StyleSheetSyntax<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_items(&self.items, formatter)?;
+
+        for trivia in &self.trailing_trivia {
+            formatter.write_str(trivia.text)?;
+        }
+
+        Ok(())
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lossless() {
+        let sources = [
+            "",
+            "  -- only a comment",
+            "$Gap = 4px;\n\nFrame > TextLabel, TextButton:hover {\n\t@priority 2\n    Size = udim2(50% + 4px, 0.5); --[[ multi\n line ]]\n}\n",
+            "Frame { Font = Enum.Font.Arial; ? } }\n  TextLabel {",
+            "@each $size in (4, 8) { .p-$size { Size = $size; } }\n@if $Dark == 1 { } @else if not $Dark { } @else {",
+        ];
+
+        for source in sources {
+            assert_eq!(parse_syntax(source).to_string(), source);
+        }
+    }
+
+    #[test]
+    fn structure() {
+        let syntax = parse_syntax("Frame { -- note\n A = 1; B = 2 Child { @priority 3; } }");
+        assert_eq!(syntax.items.len(), 1);
+
+        let ItemSyntax::Rule(rule) = &syntax.items[0] else { panic!("expected a rule") };
+        assert_eq!(rule.selector_text(), "Frame");
+        assert_eq!(rule.items.len(), 3);
+
+        let ItemSyntax::Declaration(declaration) = &rule.items[0] else { panic!("expected a declaration") };
+        assert_eq!(declaration.key.leading_trivia[1].kind, TriviaKind::LineComment);
+
+        let ItemSyntax::Declaration(declaration) = &rule.items[1] else { panic!("expected a declaration") };
+        assert!(declaration.terminator.is_none());
+
+        let ItemSyntax::Rule(child) = &rule.items[2] else { panic!("expected a rule") };
+        assert!(matches!(child.items[0], ItemSyntax::Priority(_)));
+    }
+
+    #[test]
+    fn control_blocks() {
+        let syntax = parse_syntax("@each $size in (4, 8) { .p-$size { } }\n@if $Size >= 4 { A = 1; } @else { }\n@if;");
+        assert_eq!(syntax.items.len(), 4);
+
+        let ItemSyntax::Control(each) = &syntax.items[0] else { panic!("expected a control block") };
+        assert_eq!(each.keyword.token, Token::EachDeclaration);
+        assert_eq!(each.header.iter().map(|token| token.text).collect::<Vec<_>>(), ["$size", "in", "(", "4", ",", "8", ")"]);
+        assert!(matches!(each.items[0], ItemSyntax::Rule(_)));
+
+        let ItemSyntax::Control(condition) = &syntax.items[1] else { panic!("expected a control block") };
+        assert!(matches!(condition.items[0], ItemSyntax::Declaration(_)));
+        assert!(matches!(syntax.items[2], ItemSyntax::Control(_)));
+
+        // Without a block, the directive is left as it is.
+        assert!(matches!(syntax.items[3], ItemSyntax::Other(_)));
+    }
+}
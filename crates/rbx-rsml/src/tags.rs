@@ -0,0 +1,81 @@
+//! The tags and states that stylesheets style.
+//!
+//! Tag selectors like `.Primary` only match instances which game code has given that
+//! CollectionService tag, so a typo in either place quietly stops the rule from applying. Collecting
+//! the names lets tools generate constants for them, and check them against the tags a project
+//! applies.
+
+// Modules -------------------------------------------------------------------------------------------
+use crate::ast::{Rule, StyleSheet};
+
+use std::collections::BTreeSet;
+// ---------------------------------------------------------------------------------------------------
+
+
+// Data ----------------------------------------------------------------------------------------------
+/// Tag and state names from selectors, without their `.` or `:`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SelectorNames {
+    pub tags: BTreeSet<String>,
+    pub states: BTreeSet<String>,
+}
+
+impl SelectorNames {
+    /// Adds the names in a selector, as the AST writes it. `::` pseudo-instances aren't states, so
+    /// they're left out.
+    pub fn add_selector(&mut self, selector: &str) {
+        for part in selector.split(|char: char| char.is_whitespace() || char == ',') {
+            if part.starts_with("::") { continue }
+
+            if let Some(tag) = part.strip_prefix('.').filter(|tag| !tag.is_empty()) {
+                self.tags.insert(tag.to_owned());
+            } else if let Some(state) = part.strip_prefix(':').filter(|state| !state.is_empty()) {
+                self.states.insert(state.to_owned());
+            }
+        }
+    }
+
+    /// Adds the names in every selector of a stylesheet, including those in `@sheet` blocks.
+    pub fn add_stylesheet(&mut self, stylesheet: &StyleSheet) {
+        for rule in &stylesheet.rules { self.add_rule(rule); }
+
+        for sheet in &stylesheet.sheets {
+            for rule in &sheet.rules { self.add_rule(rule); }
+        }
+    }
+
+    fn add_rule(&mut self, rule: &Rule) {
+        self.add_selector(&rule.selector);
+        for child in &rule.rules { self.add_rule(child); }
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+/// The tag and state names that a stylesheet's selectors use.
+pub fn selector_names(stylesheet: &StyleSheet) -> SelectorNames {
+    let mut names = SelectorNames::default();
+    names.add_stylesheet(stylesheet);
+
+    names
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ast::parse_ast;
+
+    #[test]
+    fn names() {
+        let stylesheet = parse_ast("
+            .Primary :hover { ::UICorner { } }
+            Frame.Danger, TextButton :Press { .Primary { } }
+            @sheet Card { .Card > #Title { } }
+        ");
+
+        let names = selector_names(&stylesheet);
+        assert_eq!(names.tags.iter().collect::<Vec<_>>(), ["Card", "Danger", "Primary"]);
+        assert_eq!(names.states.iter().collect::<Vec<_>>(), ["Press", "hover"]);
+    }
+}
@@ -0,0 +1,187 @@
+//! Utility classes in the style of Tailwind, generated for the CollectionService tags a project uses.
+//!
+//! Only tags which are used get rules, so that the StyleSheet stays small. Each rule is a `.tag`
+//! selector: `bg-blue-500` sets `BackgroundColor3` and `text-gray-100` sets `TextColor3` from the
+//! bundled Tailwind palette, while `p-4`, `gap-2` and `rounded-md` style the `UIPadding`,
+//! `UIListLayout` and `UICorner` inside the tagged instance through `::` rules.
+
+// Modules -------------------------------------------------------------------------------------------
+use crate::ast::Rule;
+use crate::parser::tailwind_color;
+
+use rbx_types::{Color3, UDim, Variant};
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, BTreeSet};
+// ---------------------------------------------------------------------------------------------------
+
+
+// Data ----------------------------------------------------------------------------------------------
+/// The sizes that utility classes are measured in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UtilityScale {
+    /// Pixels per step of spacing, so `p-4` pads by four of these.
+    pub spacing: f32,
+    /// Corner radii in pixels, by the name used after `rounded-`. `DEFAULT` is plain `rounded`.
+    /// Giving this replaces the whole default scale.
+    pub radius: BTreeMap<String, i32>,
+}
+
+impl Default for UtilityScale {
+    fn default() -> Self {
+        let radius = [
+            ("none", 0), ("sm", 2), ("DEFAULT", 4), ("md", 6), ("lg", 8), ("xl", 12), ("2xl", 16), ("3xl", 24),
+            ("full", 9999),
+        ];
+
+        Self {
+            spacing: 4.0,
+            radius: radius.into_iter().map(|(name, pixels)| (String::from(name), pixels)).collect(),
+        }
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Helpers -------------------------------------------------------------------------------------------
+fn color(name: &str) -> Option<Color3> {
+    match name {
+        "white" => Some(Color3::new(1.0, 1.0, 1.0)),
+        "black" => Some(Color3::new(0.0, 0.0, 0.0)),
+        _ => {
+            let (palette, shade) = name.rsplit_once('-')?;
+            tailwind_color(&format!("tw:{}:{}", palette, shade))
+        }
+    }
+}
+
+/// A number of spacing steps, like the `4` in `p-4`. `px` is a single pixel.
+fn spacing(steps: &str, scale: &UtilityScale) -> Option<UDim> {
+    if steps == "px" { return Some(UDim::new(0.0, 1)) }
+
+    let steps = steps.parse::<f32>().ok().filter(|steps| steps.is_finite() && *steps >= 0.0)?;
+    Some(UDim::new(0.0, (steps * scale.spacing).round() as i32))
+}
+
+fn rule(selector: String, properties: Vec<(&str, Variant)>) -> Rule {
+    Rule {
+        selector,
+        priority: None,
+        variables: BTreeMap::new(),
+        properties: properties.into_iter().map(|(key, value)| (String::from(key), value)).collect(),
+        pseudo_properties: BTreeMap::new(),
+        rules: vec![],
+        generated: true,
+    }
+}
+
+/// A rule that styles the `class` instance inside whatever has the tag.
+fn child_rule(class: &str, properties: Vec<(&str, Variant)>) -> Vec<Rule> {
+    vec![rule(format!("::{}", class), properties)]
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+/// The words in some source which could be tags. Like Tailwind, this doesn't try to understand the
+/// source, so tags are found wherever they're written: in strings, in JSON, or in comments.
+pub fn candidate_tags(source: &str) -> impl Iterator<Item = &str> {
+    source.split(|char: char| !(char.is_ascii_alphanumeric() || char == '-' || char == '_' || char == '.'))
+        .filter(|word| !word.is_empty())
+}
+
+/// The rule for a utility class, or `None` when the tag isn't one.
+pub fn utility_rule(tag: &str, scale: &UtilityScale) -> Option<Rule> {
+    let (properties, rules) = match tag.split_once('-').unwrap_or((tag, "")) {
+        ("bg", "transparent") => (vec![("BackgroundTransparency", Variant::Float32(1.0))], vec![]),
+        ("bg", name) => (vec![("BackgroundColor3", Variant::Color3(color(name)?))], vec![]),
+        ("text", name) => (vec![("TextColor3", Variant::Color3(color(name)?))], vec![]),
+
+        ("rounded", size) => {
+            let radius = *scale.radius.get(if size.is_empty() { "DEFAULT" } else { size })?;
+            (vec![], child_rule("UICorner", vec![("CornerRadius", Variant::UDim(UDim::new(0.0, radius)))]))
+        },
+
+        ("gap", steps) => (vec![], child_rule("UIListLayout", vec![("Padding", Variant::UDim(spacing(steps, scale)?))])),
+
+        (side @ ("p" | "px" | "py" | "pt" | "pr" | "pb" | "pl"), steps) => {
+            let padding = Variant::UDim(spacing(steps, scale)?);
+            let sides: &[&str] = match side {
+                "p" => &["PaddingTop", "PaddingRight", "PaddingBottom", "PaddingLeft"],
+                "px" => &["PaddingLeft", "PaddingRight"],
+                "py" => &["PaddingTop", "PaddingBottom"],
+                "pt" => &["PaddingTop"],
+                "pr" => &["PaddingRight"],
+                "pb" => &["PaddingBottom"],
+                _ => &["PaddingLeft"]
+            };
+
+            (vec![], child_rule("UIPadding", sides.iter().map(|side| (*side, padding.clone())).collect()))
+        },
+
+        _ => return None
+    };
+
+    let mut rule = rule(format!(".{}", tag), properties);
+    rule.rules = rules;
+
+    Some(rule)
+}
+
+/// The rules for every utility class among `tags`, sorted by tag so that the output doesn't depend
+/// on the order the tags were found in.
+pub fn utility_rules<'a>(tags: impl IntoIterator<Item = &'a str>, scale: &UtilityScale) -> Vec<Rule> {
+    tags.into_iter().collect::<BTreeSet<_>>().into_iter()
+        .filter_map(|tag| utility_rule(tag, scale))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn properties(rule: &Rule) -> Vec<(&str, &Variant)> {
+        rule.properties.iter().map(|(key, value)| (key.as_str(), value)).collect()
+    }
+
+    #[test]
+    fn utilities() {
+        let scale = UtilityScale::default();
+        let source = r#"
+            CollectionService:AddTag(frame, "bg-blue-500")
+            label:AddTag("text-white") -- p-4
+            local classes = "rounded-md gap-0.5 px-px bg-blue-500 bg-nope-500 pq-4 bg-transparent"
+        "#;
+
+        let rules = utility_rules(candidate_tags(source), &scale);
+        let selectors = rules.iter().map(|rule| rule.selector.as_str()).collect::<Vec<_>>();
+
+        assert_eq!(selectors, [".bg-blue-500", ".bg-transparent", ".gap-0.5", ".p-4", ".px-px", ".rounded-md", ".text-white"]);
+
+        assert_eq!(properties(&rules[0]), [("BackgroundColor3", &Variant::Color3(tailwind_color("tw:blue:500").unwrap()))]);
+        assert_eq!(properties(&rules[1]), [("BackgroundTransparency", &Variant::Float32(1.0))]);
+        assert_eq!(properties(&rules[6]), [("TextColor3", &Variant::Color3(Color3::new(1.0, 1.0, 1.0)))]);
+
+        let gap = &rules[2].rules[0];
+        assert_eq!(gap.selector, "::UIListLayout");
+        assert_eq!(properties(gap), [("Padding", &Variant::UDim(UDim::new(0.0, 2)))]);
+
+        let padding = &rules[3].rules[0];
+        assert_eq!(padding.selector, "::UIPadding");
+        assert_eq!(padding.properties.len(), 4);
+        assert_eq!(padding.properties["PaddingTop"], Variant::UDim(UDim::new(0.0, 16)));
+
+        assert_eq!(properties(&rules[4].rules[0]).iter().map(|(key, _)| *key).collect::<Vec<_>>(), ["PaddingLeft", "PaddingRight"]);
+        assert_eq!(properties(&rules[5].rules[0]), [("CornerRadius", &Variant::UDim(UDim::new(0.0, 6)))]);
+    }
+
+    #[test]
+    fn custom_scale() {
+        let scale: UtilityScale = serde_json::from_str(r#"{ "spacing": 8, "radius": { "card": 10 } }"#).unwrap();
+
+        assert_eq!(utility_rule("p-2", &scale).unwrap().rules[0].properties["PaddingTop"], Variant::UDim(UDim::new(0.0, 16)));
+        assert_eq!(utility_rule("rounded-card", &scale).unwrap().rules[0].properties["CornerRadius"], Variant::UDim(UDim::new(0.0, 10)));
+        assert!(utility_rule("rounded-md", &scale).is_none());
+    }
+}
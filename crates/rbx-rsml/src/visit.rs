@@ -0,0 +1,220 @@
+//! Walking and reshaping the tree that `parse_rsml` builds.
+//!
+//! The tree is an [`Arena`] of [`TokenTreeNode`]s, with the stylesheet itself at
+//! [`TokenTreeNode::ROOT`]. Lints and code generators should get around it with these rather than
+//! by counting rules, so that they keep working when rules are removed.
+
+// Modules -------------------------------------------------------------------------------------------
+use crate::arena::Arena;
+use crate::parser::{NodeId, TokenTreeNode};
+
+use rbx_types::Variant;
+
+use std::collections::HashMap;
+// ---------------------------------------------------------------------------------------------------
+
+
+// Traversal -----------------------------------------------------------------------------------------
+/// The rules around a rule, from the innermost out to the root. See [`Arena::ancestors`].
+pub struct Ancestors<'t, 'a> {
+    tree: &'t Arena<TokenTreeNode<'a>>,
+    next: Option<NodeId<'a>>,
+}
+
+impl<'a> Iterator for Ancestors<'_, 'a> {
+    type Item = NodeId<'a>;
+
+    fn next(&mut self) -> Option<NodeId<'a>> {
+        let node = self.next?;
+        self.next = self.tree.get(node).and_then(|rule| rule.parent(node));
+
+        Some(node)
+    }
+}
+
+/// Every rule inside a rule, in the order they appear in the source. See [`Arena::descendants`].
+pub struct Descendants<'t, 'a> {
+    tree: &'t Arena<TokenTreeNode<'a>>,
+    /// The rules left to visit, with the next one at the end.
+    stack: Vec<NodeId<'a>>,
+}
+
+impl<'t, 'a> Descendants<'t, 'a> {
+    fn push_children(&mut self, node: NodeId<'a>) {
+        self.stack.extend(self.tree.children(node).into_iter().rev().map(|(_, child)| child));
+    }
+}
+
+impl<'a> Iterator for Descendants<'_, 'a> {
+    type Item = NodeId<'a>;
+
+    fn next(&mut self) -> Option<NodeId<'a>> {
+        let node = self.stack.pop()?;
+        self.push_children(node);
+
+        Some(node)
+    }
+}
+
+impl<'a> Arena<TokenTreeNode<'a>> {
+    /// The rules directly inside `node` with their selectors, in the order they appear in the source.
+    pub fn children(&self, node: NodeId<'a>) -> Vec<(&str, NodeId<'a>)> {
+        self.get(node).map(TokenTreeNode::children).unwrap_or_default()
+    }
+
+    /// The rules that `node` is inside of, starting with its parent and ending with the root.
+    pub fn ancestors(&self, node: NodeId<'a>) -> Ancestors<'_, 'a> {
+        Ancestors { tree: self, next: self.get(node).and_then(|rule| rule.parent(node)) }
+    }
+
+    /// Every rule nested inside `node`, however deep, in the order they appear in the source.
+    pub fn descendants(&self, node: NodeId<'a>) -> Descendants<'_, 'a> {
+        let mut descendants = Descendants { tree: self, stack: vec![] };
+        descendants.push_children(node);
+
+        descendants
+    }
+
+    /// Removes a rule along with every rule inside it. Returns whether there was a rule to remove;
+    /// the root can't be removed.
+    pub fn remove_rule(&mut self, node: NodeId<'a>) -> bool {
+        let Some(parent) = self.get(node).and_then(|rule| rule.parent(node)) else { return false };
+
+        for descendant in self.descendants(node).collect::<Vec<_>>() {
+            self.remove(descendant);
+        }
+        self.remove(node);
+
+        let rules = &mut self[parent].rules.0;
+        rules.values_mut().for_each(|nodes| nodes.retain(|child| *child != node));
+        rules.retain(|_, nodes| !nodes.is_empty());
+
+        true
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Visitor -------------------------------------------------------------------------------------------
+/// Visits what's in a parsed stylesheet, rule by rule. Everything does nothing by default apart
+/// from `visit_rule`, which walks into the rule, so implementors only write what they care about.
+/// An implementation of `visit_rule` that doesn't call [`walk_rule`] skips what's inside the rule.
+pub trait Visitor<'a> {
+    fn visit_rule(&mut self, tree: &Arena<TokenTreeNode<'a>>, _selector: &str, node: NodeId<'a>) {
+        walk_rule(self, tree, node);
+    }
+
+    fn visit_priority(&mut self, _node: NodeId<'a>, _priority: i32) {}
+
+    fn visit_variable(&mut self, _node: NodeId<'a>, _name: &'a str, _value: &Variant) {}
+
+    fn visit_property(&mut self, _node: NodeId<'a>, _name: &'a str, _value: &Variant) {}
+
+    fn visit_pseudo_property(&mut self, _node: NodeId<'a>, _name: &'a str, _value: &Variant) {}
+}
+
+fn sorted<'n, 'a>(values: &'n HashMap<&'a str, Variant>) -> Vec<(&'a str, &'n Variant)> {
+    let mut values = values.iter().map(|(name, value)| (*name, value)).collect::<Vec<_>>();
+    values.sort_by_key(|(name, _)| *name);
+    values
+}
+
+/// Visits everything in a stylesheet, starting with the variables at its root.
+pub fn walk_stylesheet<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, tree: &Arena<TokenTreeNode<'a>>) {
+    walk_rule(visitor, tree, TokenTreeNode::ROOT);
+}
+
+/// Visits what's in a rule: its priority, then its variables, properties and pseudo properties,
+/// each sorted by name, then the rules inside it in the order they appear in the source.
+pub fn walk_rule<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, tree: &Arena<TokenTreeNode<'a>>, node: NodeId<'a>) {
+    let Some(rule) = tree.get(node) else { return };
+
+    if let Some(priority) = rule.priority { visitor.visit_priority(node, priority) }
+
+    for (name, value) in sorted(&rule.variables) { visitor.visit_variable(node, name, value) }
+    for (name, value) in sorted(&rule.properties) { visitor.visit_property(node, name, value) }
+    for (name, value) in sorted(&rule.psuedo_properties) { visitor.visit_pseudo_property(node, name, value) }
+
+    for (selector, child) in rule.children() {
+        visitor.visit_rule(tree, selector, child);
+    }
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lexer::lex_rsml;
+    use crate::parser::parse_rsml;
+
+    const SOURCE: &str = "$Gap = 4px;\nFrame { @priority 2; Size = 1; Child { Debug { } } }\nDebug { Color = #fff; }\nText { }";
+
+    #[derive(Default)]
+    struct Outline(Vec<String>);
+
+    impl<'a> Visitor<'a> for Outline {
+        fn visit_rule(&mut self, tree: &Arena<TokenTreeNode<'a>>, selector: &str, node: NodeId<'a>) {
+            self.0.push(format!("{} {{", selector));
+            walk_rule(self, tree, node);
+            self.0.push(String::from("}"));
+        }
+
+        fn visit_priority(&mut self, _node: NodeId<'a>, priority: i32) {
+            self.0.push(format!("@priority {}", priority));
+        }
+
+        fn visit_variable(&mut self, _node: NodeId<'a>, name: &'a str, _value: &Variant) {
+            self.0.push(format!("${}", name));
+        }
+
+        fn visit_property(&mut self, _node: NodeId<'a>, name: &'a str, _value: &Variant) {
+            self.0.push(name.to_owned());
+        }
+    }
+
+    #[test]
+    fn visitor() {
+        let tokens = lex_rsml(SOURCE);
+        let tree = parse_rsml(&tokens);
+
+        let mut outline = Outline::default();
+        walk_stylesheet(&mut outline, &tree);
+
+        assert_eq!(outline.0.join(" "), "$Gap Frame { @priority 2 Size Child { Debug { } } } Debug { Color } Text { }");
+    }
+
+    #[test]
+    fn traversal() {
+        let tokens = lex_rsml(SOURCE);
+        let tree = parse_rsml(&tokens);
+
+        let selector = |node: NodeId| {
+            let parent = tree.ancestors(node).next().unwrap();
+            tree.children(parent).into_iter().find(|(_, child)| *child == node).unwrap().0.to_owned()
+        };
+
+        let descendants = tree.descendants(TokenTreeNode::ROOT).map(selector).collect::<Vec<_>>();
+        assert_eq!(descendants, ["Frame", "Child", "Debug", "Debug", "Text"]);
+
+        let debug = tree.descendants(TokenTreeNode::ROOT).nth(2).unwrap();
+        let ancestors = tree.ancestors(debug).map(|node| node.index()).collect::<Vec<_>>();
+        assert_eq!(ancestors, [2, 1, 0]);
+    }
+
+    #[test]
+    fn removing_rules() {
+        let tokens = lex_rsml(SOURCE);
+        let mut tree = parse_rsml(&tokens);
+
+        assert!(!tree.remove_rule(TokenTreeNode::ROOT));
+
+        let frame = tree.children(TokenTreeNode::ROOT)[0].1;
+        assert!(tree.remove_rule(frame));
+
+        let selectors = tree.children(TokenTreeNode::ROOT).into_iter().map(|(selector, _)| selector).collect::<Vec<_>>();
+        assert_eq!(selectors, ["Debug", "Text"]);
+        assert_eq!(tree.len(), 3);
+        assert!(!tree.contains(frame));
+    }
+}
@@ -1,17 +1,21 @@
 use std::{
+    collections::HashMap,
     fs,
+    path::PathBuf,
     sync::{Arc, Mutex},
 };
 
 use crossbeam_channel::{select, Receiver, RecvError, Sender};
 use jod_thread::JoinHandle;
 use memofs::{IoResultExt, Vfs, VfsEvent};
-use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::types::{Attributes, Ref, Variant};
+use rbx_rsml::{edit_rsml, sheet_segment, variant_to_rsml, RuleEdit};
 
 use crate::{
     message_queue::MessageQueue,
     snapshot::{
-        apply_patch_set, compute_patch_set, AppliedPatchSet, InstigatingSource, PatchSet, RojoTree,
+        apply_patch_set, compute_patch_set, AppliedPatchSet, InstanceWithMeta, InstigatingSource,
+        PatchSet, RojoTree,
     },
     snapshot_middleware::{snapshot_from_vfs, snapshot_project_node},
 };
@@ -218,6 +222,11 @@ impl JobThreadContext {
                         log::warn!("Cannot change metadata yet.");
                     }
 
+                    if matches!(instance.class_name(), "StyleSheet" | "StyleRule") {
+                        write_rsml_changes(&tree, id, &update.changed_properties);
+                        continue;
+                    }
+
                     for (key, changed_value) in &update.changed_properties {
                         if key == "Source" {
                             if let Some(instigating_source) =
@@ -225,7 +234,17 @@ impl JobThreadContext {
                             {
                                 match instigating_source {
                                     InstigatingSource::Path(path) => {
-                                        if let Some(Variant::String(value)) = changed_value {
+                                        // ModuleScripts compiled from a .rsml
+                                        // file can't be written back to it.
+                                        if path.extension().and_then(|ext| ext.to_str())
+                                            == Some("rsml")
+                                        {
+                                            log::warn!(
+                                                "Cannot update instance {:?}, its Source is generated from {}.",
+                                                id,
+                                                path.display()
+                                            );
+                                        } else if let Some(Variant::String(value)) = changed_value {
                                             fs::write(path, value).unwrap();
                                         } else {
                                             log::warn!("Cannot change Source to non-string value.");
@@ -346,3 +365,326 @@ fn compute_and_apply_changes(tree: &mut RojoTree, vfs: &Vfs, id: Ref) -> Option<
 
     Some(applied_patch_set)
 }
+
+/// Finds the `.rsml` file that the given StyleSheet or StyleRule was generated
+/// from, along with the path of selectors that leads from the root of that file
+/// to the rule.
+///
+/// Each step of the path contains the rule's selector and its index among the
+/// sibling rules that share that selector. StyleSheets made from `@sheet`
+/// blocks add a step into their block.
+fn rsml_rule_location(tree: &RojoTree, id: Ref) -> Option<(PathBuf, Vec<(String, usize)>)> {
+    let mut rule_path = Vec::new();
+    let mut instance = tree.get_instance(id)?;
+
+    loop {
+        match instance.class_name() {
+            "StyleSheet" => {
+                // StyleSheets made from `@sheet` blocks don't have a source of
+                // their own, since removing one shouldn't remove the whole
+                // file. The Folder they're in does.
+                if instance.metadata().instigating_source.is_none() {
+                    let parent = tree.get_instance(instance.parent())?;
+
+                    if parent.class_name() == "Folder" {
+                        rule_path.push((sheet_segment(instance.name()), 0));
+                        instance = parent;
+                    }
+                }
+
+                let metadata = instance.metadata();
+
+                if let Some(InstigatingSource::Path(path)) = &metadata.instigating_source {
+                    // StyleSheets made from a directory come from the
+                    // init.rsml file inside of it.
+                    let init_path = path.join("init.rsml");
+
+                    let path = if path.extension().and_then(|ext| ext.to_str()) == Some("rsml") {
+                        path.clone()
+                    } else if metadata.relevant_paths.contains(&init_path) {
+                        init_path
+                    } else {
+                        return None;
+                    };
+
+                    rule_path.reverse();
+                    return Some((path, rule_path));
+                }
+
+                return None;
+            }
+            "StyleRule" => {
+                // Rules from `@each` and `@if` blocks aren't written out in
+                // the file, so there's nowhere for their changes to go.
+                if instance.metadata().generated {
+                    return None;
+                }
+
+                let selector = style_rule_selector(&instance);
+                let parent = tree.get_instance(instance.parent())?;
+
+                // Rules added while live syncing end up after their siblings
+                // in the tree, so the order that they were declared in is
+                // taken from their metadata when it's there.
+                let index = match instance.metadata().declaration_index {
+                    Some(index) => index,
+                    None => parent
+                        .children()
+                        .iter()
+                        .filter_map(|&child_id| tree.get_instance(child_id))
+                        .filter(|child| {
+                            child.class_name() == "StyleRule"
+                                && style_rule_selector(child) == selector
+                        })
+                        .position(|child| child.id() == instance.id())?,
+                };
+
+                rule_path.push((selector.to_owned(), index));
+                instance = parent;
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn style_rule_selector<'a>(instance: &InstanceWithMeta<'a>) -> &'a str {
+    match instance.properties().get("Selector") {
+        Some(Variant::String(selector)) => selector,
+        _ => instance.name(),
+    }
+}
+
+/// Turns the difference between two sets of attributes into RSML edits, using
+/// `to_edit` to pick between properties and variables.
+fn attribute_edits(
+    old: Option<&Variant>,
+    new: &Option<Variant>,
+    to_edit: fn(String, Option<String>) -> RuleEdit,
+) -> Vec<RuleEdit> {
+    let empty = Attributes::new();
+
+    let old = match old {
+        Some(Variant::Attributes(attributes)) => attributes,
+        _ => &empty,
+    };
+
+    let new = match new {
+        Some(Variant::Attributes(attributes)) => attributes,
+        None => &empty,
+        Some(other) => {
+            log::warn!("Cannot change attributes to a {:?} value.", other.ty());
+            return Vec::new();
+        }
+    };
+
+    let mut edits = Vec::new();
+
+    for (key, value) in new.iter() {
+        if old.get(key.as_str()) == Some(value) {
+            continue;
+        }
+
+        match variant_to_rsml(value) {
+            Some(source) => edits.push(to_edit(key.to_owned(), Some(source))),
+            None => log::warn!(
+                "Cannot write {} to a .rsml file, {:?} values are not supported.",
+                key,
+                value.ty()
+            ),
+        }
+    }
+
+    for (key, _) in old.iter() {
+        if new.get(key.as_str()).is_none() {
+            edits.push(to_edit(key.to_owned(), None));
+        }
+    }
+
+    edits
+}
+
+/// Writes property changes made to a StyleSheet or StyleRule back into the
+/// `.rsml` file that it came from.
+fn write_rsml_changes(
+    tree: &RojoTree,
+    id: Ref,
+    changed_properties: &HashMap<String, Option<Variant>>,
+) {
+    let instance = match tree.get_instance(id) {
+        Some(instance) => instance,
+        None => return,
+    };
+
+    if instance.metadata().generated {
+        log::warn!(
+            "Cannot update instance {:?}, it is generated by a control block or utility class in a .rsml file.",
+            id
+        );
+        return;
+    }
+
+    let (path, rule_path) = match rsml_rule_location(tree, id) {
+        Some(location) => location,
+        None => {
+            log::warn!(
+                "Cannot update instance {:?}, it does not come from a .rsml file.",
+                id
+            );
+            return;
+        }
+    };
+
+    let is_rule = instance.class_name() == "StyleRule";
+    let mut edits = Vec::new();
+
+    for (key, changed_value) in changed_properties {
+        match (key.as_str(), changed_value) {
+            ("Selector", Some(Variant::String(selector))) if is_rule => {
+                edits.push(RuleEdit::Selector(selector.to_owned()));
+            }
+            ("Priority", Some(Variant::Int32(priority))) if is_rule => {
+                edits.push(RuleEdit::Priority(*priority));
+            }
+            ("StyledProperties", _) if is_rule => {
+                edits.extend(attribute_edits(
+                    instance.properties().get(key),
+                    changed_value,
+                    RuleEdit::Property,
+                ));
+            }
+            ("Attributes", _) => {
+                edits.extend(attribute_edits(
+                    instance.properties().get(key),
+                    changed_value,
+                    RuleEdit::Variable,
+                ));
+            }
+            _ => log::warn!(
+                "Cannot change property {} of {} in a .rsml file.",
+                key,
+                instance.class_name()
+            ),
+        }
+    }
+
+    if edits.is_empty() {
+        return;
+    }
+
+    let source = match fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(err) => {
+            log::error!("Could not read {}: {}", path.display(), err);
+            return;
+        }
+    };
+
+    let rule_path: Vec<(&str, usize)> = rule_path
+        .iter()
+        .map(|(selector, index)| (selector.as_str(), *index))
+        .collect();
+
+    match edit_rsml(&source, &rule_path, &edits) {
+        Some(edited) => {
+            if let Err(err) = fs::write(&path, edited) {
+                log::error!("Could not write {}: {}", path.display(), err);
+            }
+        }
+        None => log::warn!(
+            "Cannot update instance {:?}, its rule could not be found in {}.",
+            id,
+            path.display()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::Path;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    use crate::snapshot::InstanceContext;
+
+    #[test]
+    fn rsml_sheet_rule_location() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/Components.rsml",
+            VfsSnapshot::file(
+                "@sheet Button { TextButton { } }\n@sheet Card { Frame { } Frame { } }",
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+        let snapshot = snapshot_from_vfs(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/Components.rsml"),
+        )
+        .unwrap()
+        .unwrap();
+        let tree = RojoTree::new(snapshot);
+
+        let folder = tree.get_instance(tree.get_root_id()).unwrap();
+        let card = tree.get_instance(folder.children()[1]).unwrap();
+        let frame = card.children()[1];
+
+        assert_eq!(
+            rsml_rule_location(&tree, frame),
+            Some((
+                PathBuf::from("/Components.rsml"),
+                vec![("@sheet Card".to_owned(), 0), ("Frame".to_owned(), 1)]
+            ))
+        );
+        assert_eq!(
+            rsml_rule_location(&tree, card.id()),
+            Some((
+                PathBuf::from("/Components.rsml"),
+                vec![("@sheet Card".to_owned(), 0)]
+            ))
+        );
+    }
+
+    #[test]
+    fn rsml_generated_rule_location() {
+        let source =
+            "$Dark = true;\nFrame { A = 1; }\n@if $Dark { Frame { B = 2; } }\nFrame { C = 3; }";
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/Theme.rsml", VfsSnapshot::file(source))
+            .unwrap();
+
+        let vfs = Vfs::new(imfs);
+        let snapshot =
+            snapshot_from_vfs(&InstanceContext::default(), &vfs, Path::new("/Theme.rsml"))
+                .unwrap()
+                .unwrap();
+        let tree = RojoTree::new(snapshot);
+
+        let sheet = tree.get_instance(tree.get_root_id()).unwrap();
+        let generated = tree.get_instance(sheet.children()[1]).unwrap();
+        let literal = sheet.children()[2];
+
+        assert!(generated.metadata().generated);
+        assert_eq!(rsml_rule_location(&tree, generated.id()), None);
+
+        let (path, rule_path) = rsml_rule_location(&tree, literal).unwrap();
+        assert_eq!(path, PathBuf::from("/Theme.rsml"));
+        assert_eq!(rule_path, vec![("Frame".to_owned(), 1)]);
+
+        let rule_path: Vec<(&str, usize)> = rule_path
+            .iter()
+            .map(|(selector, index)| (selector.as_str(), *index))
+            .collect();
+        let edits = [RuleEdit::Property("C".to_owned(), Some("5".to_owned()))];
+
+        assert_eq!(
+            edit_rsml(source, &rule_path, &edits).unwrap(),
+            "$Dark = true;\nFrame { A = 1; }\n@if $Dark { Frame { B = 2; } }\nFrame { C = 5; }"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use rbx_rsml::{check_syntax, format_rsml, line_column, parse_syntax, FormatOptions, Severity};
+
+use super::resolve_path;
+
+/// Reformat RSML files using the standard formatting rules.
+#[derive(Debug, Parser)]
+pub struct FmtRsmlCommand {
+    /// Files or directories to format. Directories are searched for .rsml files
+    /// recursively. Defaults to the current directory.
+    #[clap(default_value = "")]
+    pub paths: Vec<PathBuf>,
+
+    /// Don't write any files, and fail if any of them would have been changed.
+    #[clap(long)]
+    pub check: bool,
+
+    /// Sort the property declarations of each rule alphabetically.
+    #[clap(long)]
+    pub sort_properties: bool,
+}
+
+impl FmtRsmlCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        let options = FormatOptions {
+            sort_properties: self.sort_properties,
+            ..Default::default()
+        };
+
+        let mut files = Vec::new();
+        for path in &self.paths {
+            collect_rsml_files(&resolve_path(path), &mut files)?;
+        }
+
+        let mut unformatted = Vec::new();
+        let mut failed = 0;
+
+        for file in files {
+            let source = fs_err::read_to_string(&file)?;
+
+            // Formatting source with errors in it would lose whatever the
+            // formatter doesn't understand, so those files are left alone.
+            let errors: Vec<_> = check_syntax(&parse_syntax(&source))
+                .into_iter()
+                .filter(|diagnostic| diagnostic.severity == Severity::Error)
+                .collect();
+
+            if !errors.is_empty() {
+                for diagnostic in &errors {
+                    let (line, column) = line_column(&source, diagnostic.span.start);
+                    log::error!(
+                        "{}:{}:{}: {}",
+                        file.display(),
+                        line,
+                        column,
+                        diagnostic.message
+                    );
+                }
+
+                failed += 1;
+                continue;
+            }
+
+            let formatted = format_rsml(&source, &options);
+
+            if formatted == source {
+                continue;
+            }
+
+            if self.check {
+                println!("{}", file.display());
+                unformatted.push(file);
+            } else {
+                fs_err::write(&file, formatted)
+                    .with_context(|| format!("could not write {}", file.display()))?;
+            }
+        }
+
+        if failed > 0 {
+            bail!(
+                "{} RSML file(s) have syntax errors and were not formatted",
+                failed
+            );
+        }
+
+        if !unformatted.is_empty() {
+            bail!("{} RSML file(s) are not formatted", unformatted.len());
+        }
+
+        Ok(())
+    }
+}
+
+/// Finds every .rsml file in a directory and its subdirectories, skipping
+/// hidden directories. If given a file, that file is the only result.
+pub(super) fn collect_rsml_files(path: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = fs_err::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for entry in entries {
+        let is_hidden = entry
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with('.'));
+
+        if entry.is_dir() && !is_hidden {
+            collect_rsml_files(&entry, files)?;
+        } else if entry.extension().and_then(|ext| ext.to_str()) == Some("rsml") {
+            files.push(entry);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn files_with_syntax_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let broken = dir.path().join("broken.rsml");
+        let messy = dir.path().join("messy.rsml");
+
+        fs_err::write(&broken, "Frame {  Size = 1;\n").unwrap();
+        fs_err::write(&messy, "Frame {Size=1;}\n").unwrap();
+
+        let command = FmtRsmlCommand {
+            paths: vec![dir.path().to_path_buf()],
+            check: false,
+            sort_properties: false,
+        };
+
+        assert!(command.run().is_err());
+        assert_eq!(
+            fs_err::read_to_string(&broken).unwrap(),
+            "Frame {  Size = 1;\n"
+        );
+        assert_eq!(
+            fs_err::read_to_string(&messy).unwrap(),
+            "Frame {\n    Size = 1;\n}\n"
+        );
+    }
+}
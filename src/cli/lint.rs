@@ -0,0 +1,362 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{bail, format_err, Context};
+use clap::Parser;
+use memofs::Vfs;
+use rbx_rsml::{line_column, lint_stylesheets, ContrastLevel, Diagnostic, LintRule, Severity};
+use serde::Serialize;
+
+use crate::project::{ContrastTarget, LintConfig, LintLevel, Project};
+
+use super::{fmt_rsml::collect_rsml_files, resolve_path};
+
+/// Check the RSML stylesheets in a project for likely mistakes.
+#[derive(Debug, Parser)]
+pub struct LintCommand {
+    /// Path to the project to lint. Defaults to the current directory.
+    #[clap(default_value = "")]
+    pub project: PathBuf,
+
+    /// How to print the results, 'human' or 'json'. Defaults to human.
+    #[clap(long, default_value = "human")]
+    pub format: LintFormat,
+}
+
+impl LintCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        let vfs = Vfs::new_default();
+        vfs.set_watch_enabled(false);
+
+        let project = Project::load_fuzzy(&vfs, &resolve_path(&self.project))?
+            .context("A project file is required to run 'rojo lint'")?;
+        let levels = rule_levels(&project.lint)?;
+        let contrast = contrast_level(&project.lint);
+
+        let root = project.folder_location();
+        let mut paths = Vec::new();
+        collect_rsml_files(root, &mut paths)?;
+
+        paths.retain(|path| {
+            let relative = path.strip_prefix(root).unwrap_or(path);
+            !project
+                .glob_ignore_paths
+                .iter()
+                .any(|glob| glob.is_match(relative))
+        });
+
+        let files = paths
+            .into_iter()
+            .map(|path| {
+                let source = fs_err::read_to_string(&path)?;
+                Ok(StyleSheetFile { path, source })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let findings = lint_files(&files, &levels, contrast);
+
+        match self.format {
+            LintFormat::Human => print_human(root, &files, &findings),
+            LintFormat::Json => print_json(root, &files, &findings)?,
+        }
+
+        let error_count = findings
+            .iter()
+            .filter(|(_, diagnostic)| diagnostic.severity == Severity::Error)
+            .count();
+
+        if error_count > 0 {
+            bail!("Found {} error(s) in stylesheets", error_count);
+        }
+
+        Ok(())
+    }
+}
+
+/// The output formats that `rojo lint` supports.
+#[derive(Debug, Clone, Copy)]
+pub enum LintFormat {
+    /// Messages meant to be read in a terminal.
+    Human,
+
+    /// A JSON array of findings, meant to be read by other tools.
+    Json,
+}
+
+impl FromStr for LintFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "human" => Ok(LintFormat::Human),
+            "json" => Ok(LintFormat::Json),
+            _ => Err(format_err!(
+                "Invalid lint format '{}'. Valid formats are: human, json",
+                source
+            )),
+        }
+    }
+}
+
+struct StyleSheetFile {
+    path: PathBuf,
+    source: String,
+}
+
+fn rule_levels(config: &LintConfig) -> anyhow::Result<HashMap<LintRule, LintLevel>> {
+    config
+        .rules
+        .iter()
+        .map(|(name, level)| {
+            let rule = LintRule::from_name(name).ok_or_else(|| {
+                let valid = LintRule::ALL.map(LintRule::name).join(", ");
+                format_err!("Unknown lint rule '{}'. Valid rules are: {}", name, valid)
+            })?;
+
+            Ok((rule, *level))
+        })
+        .collect()
+}
+
+fn contrast_level(config: &LintConfig) -> ContrastLevel {
+    match config.contrast {
+        Some(ContrastTarget::Aaa) => ContrastLevel::AAA,
+        Some(ContrastTarget::Aa) | None => ContrastLevel::AA,
+    }
+}
+
+/// Applies the project's configured level to a diagnostic, or drops it if its
+/// rule is turned off. Syntax errors can't be configured.
+fn apply_level(
+    mut diagnostic: Diagnostic,
+    levels: &HashMap<LintRule, LintLevel>,
+) -> Option<Diagnostic> {
+    let level = diagnostic.rule.and_then(|rule| levels.get(&rule));
+
+    match level {
+        None => {}
+        Some(LintLevel::Off) => return None,
+        Some(LintLevel::Warn) => diagnostic.severity = Severity::Warning,
+        Some(LintLevel::Error) => diagnostic.severity = Severity::Error,
+    }
+
+    Some(diagnostic)
+}
+
+/// Lints the project's stylesheets together, then applies the levels that the
+/// project configures for each rule.
+fn lint_files(
+    files: &[StyleSheetFile],
+    levels: &HashMap<LintRule, LintLevel>,
+    contrast: ContrastLevel,
+) -> Vec<(usize, Diagnostic)> {
+    let stylesheets = files
+        .iter()
+        .map(|file| (file.path.as_path(), file.source.as_str()))
+        .collect::<Vec<_>>();
+
+    lint_stylesheets(&stylesheets, contrast)
+        .into_iter()
+        .enumerate()
+        .flat_map(|(index, diagnostics)| {
+            diagnostics
+                .into_iter()
+                .filter_map(|diagnostic| apply_level(diagnostic, levels))
+                .map(move |diagnostic| (index, diagnostic))
+        })
+        .collect()
+}
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    }
+}
+
+fn print_human(root: &Path, files: &[StyleSheetFile], findings: &[(usize, Diagnostic)]) {
+    for (index, diagnostic) in findings {
+        let file = &files[*index];
+        let (line, column) = line_column(&file.source, diagnostic.span.start);
+
+        let label = match diagnostic.rule {
+            Some(rule) => format!("{}[{}]", severity_name(diagnostic.severity), rule.name()),
+            None => severity_name(diagnostic.severity).to_owned(),
+        };
+
+        println!("{}: {}", label, diagnostic.message);
+        println!(
+            "  --> {}:{}:{}",
+            file.path.strip_prefix(root).unwrap_or(&file.path).display(),
+            line,
+            column
+        );
+        println!();
+    }
+
+    let count = |severity| {
+        findings
+            .iter()
+            .filter(|(_, diagnostic)| diagnostic.severity == severity)
+            .count()
+    };
+
+    println!(
+        "Linted {} stylesheet(s): {} error(s), {} warning(s)",
+        files.len(),
+        count(Severity::Error),
+        count(Severity::Warning)
+    );
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonFinding<'a> {
+    path: &'a Path,
+    line: usize,
+    column: usize,
+    end_line: usize,
+    end_column: usize,
+    severity: &'static str,
+    rule: Option<&'static str>,
+    message: &'a str,
+}
+
+fn print_json(
+    root: &Path,
+    files: &[StyleSheetFile],
+    findings: &[(usize, Diagnostic)],
+) -> anyhow::Result<()> {
+    let json_findings = findings
+        .iter()
+        .map(|(index, diagnostic)| {
+            let file = &files[*index];
+            let (line, column) = line_column(&file.source, diagnostic.span.start);
+            let (end_line, end_column) = line_column(&file.source, diagnostic.span.end);
+
+            JsonFinding {
+                path: file.path.strip_prefix(root).unwrap_or(&file.path),
+                line,
+                column,
+                end_line,
+                end_column,
+                severity: severity_name(diagnostic.severity),
+                rule: diagnostic.rule.map(LintRule::name),
+                message: &diagnostic.message,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    println!("{}", serde_json::to_string_pretty(&json_findings)?);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn file(path: &str, source: &str) -> StyleSheetFile {
+        StyleSheetFile {
+            path: PathBuf::from(path),
+            source: source.to_owned(),
+        }
+    }
+
+    fn summarize(files: &[StyleSheetFile], findings: &[(usize, Diagnostic)]) -> Vec<String> {
+        findings
+            .iter()
+            .map(|(index, diagnostic)| {
+                let file = &files[*index];
+                let (line, column) = line_column(&file.source, diagnostic.span.start);
+
+                format!(
+                    "{}:{}:{} {} {}",
+                    file.path.display(),
+                    line,
+                    column,
+                    severity_name(diagnostic.severity),
+                    diagnostic.rule.map_or("syntax", LintRule::name)
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn variables_shared_through_derive() {
+        let files = [
+            file("/project/theme.rsml", "$Accent = #ff0000;\n$Unused = 1;\n"),
+            file(
+                "/project/ui/button.rsml",
+                "@derive \"../theme\";\nTextButton {\n    TextColor3 = $Accent;\n    Size = $Missing;\n}\n",
+            ),
+        ];
+
+        let findings = lint_files(&files, &HashMap::new(), ContrastLevel::AA);
+
+        assert_eq!(
+            summarize(&files, &findings),
+            [
+                "/project/theme.rsml:2:1 warning unused-variable",
+                "/project/ui/button.rsml:4:12 error undefined-variable",
+            ]
+        );
+    }
+
+    #[test]
+    fn configured_levels() {
+        let config: LintConfig = serde_json::from_str(
+            r#"{ "rules": { "unused-variable": "off", "duplicate-property": "error" } }"#,
+        )
+        .unwrap();
+        let levels = rule_levels(&config).unwrap();
+
+        let files = [file(
+            "/project/styles.rsml",
+            "$Unused = 1;\nFrame { Size = 1; Size = 2; }\n",
+        )];
+
+        let findings = lint_files(&files, &levels, ContrastLevel::AA);
+
+        assert_eq!(
+            summarize(&files, &findings),
+            ["/project/styles.rsml:2:19 error duplicate-property"]
+        );
+    }
+
+    #[test]
+    fn contrast_with_derived_colors() {
+        let config: LintConfig = serde_json::from_str(r#"{ "contrast": "AAA" }"#).unwrap();
+
+        let files = [
+            file(
+                "/project/theme.rsml",
+                "$Text = #6b6b6b;\n$Surface = css:white;\n",
+            ),
+            file(
+                "/project/label.rsml",
+                "@derive \"theme\";\nTextLabel {\n    TextColor3 = $Text;\n    BackgroundColor3 = $Surface;\n}\n",
+            ),
+        ];
+
+        let findings = lint_files(&files, &HashMap::new(), ContrastLevel::AA);
+        assert!(findings.is_empty());
+
+        let findings = lint_files(&files, &HashMap::new(), contrast_level(&config));
+        assert_eq!(
+            summarize(&files, &findings),
+            ["/project/label.rsml:3:5 warning low-contrast"]
+        );
+    }
+
+    #[test]
+    fn unknown_rule() {
+        let config: LintConfig =
+            serde_json::from_str(r#"{ "rules": { "no-such-rule": "warn" } }"#).unwrap();
+
+        assert!(rule_levels(&config).is_err());
+    }
+}
@@ -3,8 +3,11 @@
 mod build;
 mod doc;
 mod fmt_project;
+mod fmt_rsml;
 mod init;
+mod lint;
 mod plugin;
+mod rsml_lsp;
 mod serve;
 mod sourcemap;
 mod upload;
@@ -17,8 +20,11 @@ use thiserror::Error;
 pub use self::build::BuildCommand;
 pub use self::doc::DocCommand;
 pub use self::fmt_project::FmtProjectCommand;
+pub use self::fmt_rsml::FmtRsmlCommand;
 pub use self::init::{InitCommand, InitKind};
+pub use self::lint::{LintCommand, LintFormat};
 pub use self::plugin::{PluginCommand, PluginSubcommand};
+pub use self::rsml_lsp::RsmlLspCommand;
 pub use self::serve::ServeCommand;
 pub use self::sourcemap::SourcemapCommand;
 pub use self::upload::UploadCommand;
@@ -44,6 +50,9 @@ impl Options {
             Subcommand::Upload(subcommand) => subcommand.run(),
             Subcommand::Sourcemap(subcommand) => subcommand.run(),
             Subcommand::FmtProject(subcommand) => subcommand.run(),
+            Subcommand::FmtRsml(subcommand) => subcommand.run(),
+            Subcommand::RsmlLsp(subcommand) => subcommand.run(),
+            Subcommand::Lint(subcommand) => subcommand.run(),
             Subcommand::Doc(subcommand) => subcommand.run(),
             Subcommand::Plugin(subcommand) => subcommand.run(),
         }
@@ -117,6 +126,9 @@ pub enum Subcommand {
     Upload(UploadCommand),
     Sourcemap(SourcemapCommand),
     FmtProject(FmtProjectCommand),
+    FmtRsml(FmtRsmlCommand),
+    RsmlLsp(RsmlLspCommand),
+    Lint(LintCommand),
     Doc(DocCommand),
     Plugin(PluginCommand),
 }
@@ -0,0 +1,11 @@
+use clap::Parser;
+
+/// Run a language server for RSML files, communicating over stdio.
+#[derive(Debug, Parser)]
+pub struct RsmlLspCommand {}
+
+impl RsmlLspCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        crate::rsml_lsp::run_stdio()
+    }
+}
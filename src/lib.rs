@@ -17,6 +17,7 @@ mod path_serializer;
 mod project;
 mod resolution;
 mod rojo_ref;
+mod rsml_lsp;
 mod serve_session;
 mod session_id;
 mod snapshot;
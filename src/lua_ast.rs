@@ -1,5 +1,6 @@
 //! Defines module for defining a small Lua AST for simple codegen. Rojo uses
-//! this module to convert JSON into generated Lua code.
+//! this module to convert JSON into generated Lua code, and to generate typed
+//! Luau modules from RSML variables.
 
 use std::{
     fmt::{self, Write},
@@ -36,6 +37,36 @@ where
 
 pub(crate) enum Statement {
     Return(Expression),
+
+    /// `local name: Type = value`, where the type annotation is optional.
+    Local {
+        name: String,
+        type_annotation: Option<String>,
+        value: Expression,
+    },
+
+    /// `export type Name = { field: Type }`, a Luau table type.
+    ExportType {
+        name: String,
+        fields: Vec<(String, String)>,
+    },
+
+    /// `export type Name = "a" | "b"`, a union of string literal types. A
+    /// union without any members is `never`.
+    ExportUnion {
+        name: String,
+        members: Vec<String>,
+    },
+
+    /// `target = value`, where the target is a name or a path like
+    /// `rule.Selector`.
+    Assign {
+        target: String,
+        value: Expression,
+    },
+
+    /// An expression on its own, like a method call.
+    Expression(Expression),
 }
 
 impl FmtLua for Statement {
@@ -45,6 +76,54 @@ impl FmtLua for Statement {
                 write!(output, "return ")?;
                 literal.fmt_lua(output)
             }
+            Self::Local {
+                name,
+                type_annotation,
+                value,
+            } => {
+                write!(output, "local {}", name)?;
+
+                if let Some(type_annotation) = type_annotation {
+                    write!(output, ": {}", type_annotation)?;
+                }
+
+                write!(output, " = ")?;
+                value.fmt_lua(output)
+            }
+            Self::ExportType { name, fields } => {
+                writeln!(output, "export type {} = {{", name)?;
+                output.indent();
+
+                for (field, field_type) in fields {
+                    field.fmt_table_key(output)?;
+                    writeln!(output, ": {},", field_type)?;
+                }
+
+                output.unindent();
+                write!(output, "}}")
+            }
+            Self::ExportUnion { name, members } => {
+                write!(output, "export type {} = ", name)?;
+
+                if members.is_empty() {
+                    return write!(output, "never");
+                }
+
+                for (index, member) in members.iter().enumerate() {
+                    if index > 0 {
+                        write!(output, " | ")?;
+                    }
+
+                    member.fmt_lua(output)?;
+                }
+
+                Ok(())
+            }
+            Self::Assign { target, value } => {
+                write!(output, "{} = ", target)?;
+                value.fmt_lua(output)
+            }
+            Self::Expression(expression) => expression.fmt_lua(output),
         }
     }
 }
@@ -56,6 +135,29 @@ impl fmt::Display for Statement {
     }
 }
 
+/// A sequence of blocks of statements. Statements go on lines of their own,
+/// and blocks are separated from each other by an empty line.
+pub(crate) struct Chunk(pub Vec<Vec<Statement>>);
+
+impl fmt::Display for Chunk {
+    fn fmt(&self, output: &mut fmt::Formatter) -> fmt::Result {
+        let mut stream = LuaStream::new(output);
+
+        for (index, block) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(stream)?;
+            }
+
+            for statement in block {
+                statement.fmt_lua(&mut stream)?;
+                writeln!(stream)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 pub(crate) enum Expression {
     Nil,
     Bool(bool),
@@ -66,6 +168,13 @@ pub(crate) enum Expression {
     /// Arrays are not technically distinct from other tables in Lua, but this
     /// representation is more convenient.
     Array(Vec<Expression>),
+
+    /// A name or a path of names, like `Enum.Font.Gotham`.
+    Name(String),
+
+    /// A call to the function or method at the given path, like
+    /// `Color3.fromRGB(...)` or `rule:SetProperties(...)`.
+    Call(String, Vec<Expression>),
 }
 
 impl Expression {
@@ -83,6 +192,20 @@ impl FmtLua for Expression {
             Self::String(inner) => inner.fmt_lua(output),
             Self::Table(inner) => inner.fmt_lua(output),
             Self::Array(inner) => inner.fmt_lua(output),
+            Self::Name(inner) => write!(output, "{}", inner),
+            Self::Call(function, arguments) => {
+                write!(output, "{}(", function)?;
+
+                for (index, argument) in arguments.iter().enumerate() {
+                    if index > 0 {
+                        write!(output, ", ")?;
+                    }
+
+                    argument.fmt_lua(output)?;
+                }
+
+                write!(output, ")")
+            }
         }
     }
 
@@ -94,6 +217,11 @@ impl FmtLua for Expression {
             Self::String(inner) => inner.fmt_table_key(output),
             Self::Table(inner) => inner.fmt_table_key(output),
             Self::Array(inner) => inner.fmt_table_key(output),
+            Self::Name(_) | Self::Call(..) => {
+                write!(output, "[")?;
+                self.fmt_lua(output)?;
+                write!(output, "]")
+            }
         }
     }
 }
@@ -342,4 +470,70 @@ mod test {
 
         assert_eq!(displayed, "\"\\\"\\r\\n\\t\\\\\"");
     }
+
+    #[test]
+    fn typed_module() {
+        let chunk = Chunk(vec![
+            vec![Statement::ExportType {
+                name: "Theme".to_owned(),
+                fields: vec![
+                    ("Accent".to_owned(), "Color3".to_owned()),
+                    ("space-sm".to_owned(), "number".to_owned()),
+                ],
+            }],
+            vec![Statement::Local {
+                name: "theme".to_owned(),
+                type_annotation: Some("Theme".to_owned()),
+                value: Expression::Call(
+                    "table.freeze".to_owned(),
+                    vec![Expression::table(vec![
+                        (
+                            "Accent".into(),
+                            Expression::Call(
+                                "Color3.fromRGB".to_owned(),
+                                vec![
+                                    Expression::Number(255.0),
+                                    Expression::Number(0.0),
+                                    Expression::Number(0.0),
+                                ],
+                            ),
+                        ),
+                        ("space-sm".into(), Expression::Number(8.0)),
+                    ])],
+                ),
+            }],
+            vec![
+                Statement::Expression(Expression::Call(
+                    "print".to_owned(),
+                    vec![Expression::Name("theme".to_owned())],
+                )),
+                Statement::Return(Expression::Name("theme".to_owned())),
+            ],
+        ]);
+
+        assert_eq!(
+            chunk.to_string(),
+            "export type Theme = {\n\tAccent: Color3,\n\t[\"space-sm\"]: number,\n}\n\n\
+             local theme: Theme = table.freeze({\n\tAccent = Color3.fromRGB(255, 0, 0),\n\t[\"space-sm\"] = 8,\n})\n\n\
+             print(theme)\nreturn theme\n"
+        );
+    }
+
+    #[test]
+    fn string_unions() {
+        let union = Statement::ExportUnion {
+            name: "Tag".to_owned(),
+            members: vec!["Primary".to_owned(), "bg-\"x\"".to_owned()],
+        };
+        let empty = Statement::ExportUnion {
+            name: "State".to_owned(),
+            members: vec![],
+        };
+
+        assert_eq!(
+            union.to_string(),
+            "export type Tag = \"Primary\" | \"bg-\\\"x\\\"\""
+        );
+        assert_eq!(empty.to_string(), "export type State = never");
+    }
 }
@@ -20,6 +20,17 @@ where
     serializer.serialize_str(&replaced)
 }
 
+pub fn serialize_option_absolute<S, T>(path: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: AsRef<Path>,
+{
+    match path {
+        Some(path) => serializer.serialize_some(&WithAbsolute(path.as_ref())),
+        None => serializer.serialize_none(),
+    }
+}
+
 #[derive(Serialize)]
 struct WithAbsolute<'a>(#[serde(serialize_with = "serialize_absolute")] &'a Path);
 
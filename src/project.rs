@@ -7,6 +7,7 @@ use std::{
 };
 
 use memofs::Vfs;
+use rbx_rsml::UtilityScale;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -49,6 +50,44 @@ enum Error {
     },
 }
 
+/// Configuration for `rojo lint`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct LintConfig {
+    /// Changes the level of individual lint rules, keyed by the name of the
+    /// rule, like `unused-variable`. Rules that aren't listed keep their
+    /// default level.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rules: BTreeMap<String, LintLevel>,
+
+    /// The WCAG level that the `low-contrast` rule checks text against.
+    /// Defaults to AA.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contrast: Option<ContrastTarget>,
+}
+
+impl LintConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    Off,
+    Warn,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContrastTarget {
+    #[serde(rename = "AA")]
+    Aa,
+    #[serde(rename = "AAA")]
+    Aaa,
+}
+
 /// Contains all of the configuration for a Rojo-managed project.
 ///
 /// Project files are stored in `.project.json` files.
@@ -99,6 +138,25 @@ pub struct Project {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub emit_legacy_scripts: Option<bool>,
 
+    /// Determines if Rojo should give every StyleSheet made from a `.rsml`
+    /// file a `Variables` ModuleScript, which returns the stylesheet's root
+    /// variables as a typed, frozen table.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emit_rsml_variables: Option<bool>,
+
+    /// Turns on utility classes for stylesheets with `@utilities;` in them.
+    /// The project's Luau sources and model files are scanned for tags like
+    /// `bg-blue-500` or `p-4`, and only the tags found get StyleRules. The
+    /// spacing and radius scales can be changed here.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rsml_utilities: Option<UtilityScale>,
+
+    /// An asset manifest, relative to the project file, that `asset("name")`
+    /// in stylesheets looks names up in. It's a JSON object from names to
+    /// asset ids or content URLs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rsml_assets: Option<PathBuf>,
+
     /// A list of globs, relative to the folder the project file is in, that
     /// match files that should be excluded if Rojo encounters them.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
@@ -110,6 +168,10 @@ pub struct Project {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub sync_rules: Vec<SyncRule>,
 
+    /// Configures the rules that `rojo lint` checks stylesheets with.
+    #[serde(default, skip_serializing_if = "LintConfig::is_default")]
+    pub lint: LintConfig,
+
     /// The path to the file that this project came from. Relative paths in the
     /// project should be considered relative to the parent of this field, also
     /// given by `Project::folder_location`.
@@ -364,6 +426,20 @@ pub struct ProjectNode {
     /// spreadsheets (`.csv`).
     #[serde(rename = "$path", skip_serializing_if = "Option::is_none")]
     pub path: Option<PathNode>,
+
+    /// If set, a StyleLink is added to the described instance to link it to a
+    /// StyleSheet. This can either be the path of a `.rsml` file, relative to
+    /// the project file, or the ID of a StyleSheet.
+    #[serde(rename = "$styleSheet", skip_serializing_if = "Option::is_none")]
+    pub style_sheet: Option<String>,
+
+    /// If set to `true`, a `Tags` ModuleScript is added to the described
+    /// instance. It has a string constant for every tag and state that the
+    /// selectors in the project's `.rsml` files use, along with `Tag` and
+    /// `State` union types of them. Tags that are styled but never applied
+    /// anywhere in the project are warned about.
+    #[serde(rename = "$rsmlTags", skip_serializing_if = "Option::is_none")]
+    pub rsml_tags: Option<bool>,
 }
 
 impl ProjectNode {
@@ -0,0 +1,420 @@
+//! Answers the questions the language server asks about an RSML document:
+//! which rules enclose a position, where things are defined, and what values
+//! end up resolving to.
+
+use std::ops::Range;
+
+use lsp_types::{Color, ColorInformation, DocumentSymbol, SymbolKind};
+use rbx_dom_weak::types::Variant;
+use rbx_reflection::{ClassDescriptor, DataType, PropertyDescriptor};
+use rbx_rsml::{
+    color_literal, lex_rsml, parse_rsml,
+    syntax::{DeclarationSyntax, ItemSyntax, RuleSyntax, StyleSheetSyntax, SyntaxToken},
+    variant_to_rsml, Arena, Id, TextType, Token, TokenTreeNode,
+};
+
+use super::document::Document;
+
+/// How many variables deep a value is followed before giving up, which keeps
+/// variables that refer to each other from looping forever.
+const MAX_VARIABLE_DEPTH: usize = 8;
+
+/// One level of rule nesting around a position.
+pub struct Scope<'s, 'a> {
+    pub rule: Option<&'s RuleSyntax<'a>>,
+    pub items: &'s [ItemSyntax<'a>],
+    /// The index of the rule's node in the tree built by `parse_rsml`, which
+    /// numbers rules in the order that they appear in the source.
+    pub node_index: usize,
+}
+
+fn count_rules(rule: &RuleSyntax) -> usize {
+    let nested = rule
+        .items
+        .iter()
+        .map(|item| match item {
+            ItemSyntax::Rule(rule) => count_rules(rule),
+            _ => 0,
+        })
+        .sum::<usize>();
+
+    1 + nested
+}
+
+/// Returns the scopes enclosing `offset`, starting with the root of the file.
+pub fn scope_chain<'s, 'a>(
+    syntax: &'s StyleSheetSyntax<'a>,
+    source_len: usize,
+    offset: usize,
+) -> Vec<Scope<'s, 'a>> {
+    let mut chain = vec![Scope {
+        rule: None,
+        items: &syntax.items,
+        node_index: 0,
+    }];
+    let mut next_index = 1;
+
+    'descend: loop {
+        let items = chain.last().unwrap().items;
+
+        for item in items {
+            let ItemSyntax::Rule(rule) = item else {
+                continue;
+            };
+
+            let body = rule.body_span(source_len);
+            if body.start <= offset && offset <= body.end {
+                chain.push(Scope {
+                    rule: Some(rule),
+                    items: &rule.items,
+                    node_index: next_index,
+                });
+                next_index += 1;
+
+                continue 'descend;
+            }
+
+            next_index += count_rules(rule);
+        }
+
+        return chain;
+    }
+}
+
+fn item_tokens<'s, 'a>(item: &'s ItemSyntax<'a>) -> Vec<&'s SyntaxToken<'a>> {
+    match item {
+        ItemSyntax::Rule(rule) => rule.selector.iter().chain([&rule.open]).collect(),
+        ItemSyntax::Declaration(declaration) => [&declaration.key, &declaration.equals]
+            .into_iter()
+            .chain(&declaration.value)
+            .chain(&declaration.terminator)
+            .collect(),
+        ItemSyntax::Priority(priority) => [&priority.keyword]
+            .into_iter()
+            .chain(&priority.value)
+            .chain(&priority.terminator)
+            .collect(),
+        ItemSyntax::Control(control) => [&control.keyword]
+            .into_iter()
+            .chain(&control.header)
+            .chain([&control.open])
+            .collect(),
+        ItemSyntax::Other(tokens) => tokens.iter().collect(),
+    }
+}
+
+fn for_each_token<'s, 'a>(
+    items: &'s [ItemSyntax<'a>],
+    callback: &mut impl FnMut(&'s SyntaxToken<'a>),
+) {
+    for item in items {
+        item_tokens(item).into_iter().for_each(&mut *callback);
+
+        match item {
+            ItemSyntax::Rule(rule) => {
+                for_each_token(&rule.items, callback);
+                rule.close.iter().for_each(&mut *callback);
+            }
+            ItemSyntax::Control(control) => {
+                for_each_token(&control.items, callback);
+                control.close.iter().for_each(&mut *callback);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Finds the token under `offset`, along with the item it belongs to.
+fn token_at<'s, 'a>(
+    items: &'s [ItemSyntax<'a>],
+    offset: usize,
+) -> Option<(&'s ItemSyntax<'a>, &'s SyntaxToken<'a>)> {
+    items.iter().find_map(|item| {
+        item_tokens(item)
+            .into_iter()
+            .find(|token| token.span.start <= offset && offset <= token.span.end)
+            .map(|token| (item, token))
+    })
+}
+
+fn variable_name<'a>(token: &SyntaxToken<'a>) -> Option<&'a str> {
+    match token.token {
+        Token::Text(TextType::Variable(name)) => Some(name),
+        _ => None,
+    }
+}
+
+/// Finds the declaration that a variable refers to, searching outwards from
+/// the innermost scope. Returns the node index of the declaring scope too.
+pub fn find_variable<'s, 'a>(
+    chain: &[Scope<'s, 'a>],
+    name: &str,
+) -> Option<(&'s DeclarationSyntax<'a>, usize)> {
+    chain.iter().rev().find_map(|scope| {
+        scope.items.iter().rev().find_map(|item| match item {
+            ItemSyntax::Declaration(declaration)
+                if declaration.key_text_type() == &TextType::Variable(name) =>
+            {
+                Some((declaration, scope.node_index))
+            }
+            _ => None,
+        })
+    })
+}
+
+/// Returns every variable visible from the innermost scope, with inner
+/// declarations hiding outer ones of the same name.
+pub fn visible_variables<'s, 'a>(chain: &[Scope<'s, 'a>]) -> Vec<&'s DeclarationSyntax<'a>> {
+    let mut variables: Vec<&DeclarationSyntax> = Vec::new();
+
+    for scope in chain.iter().rev() {
+        for item in scope.items.iter().rev() {
+            let ItemSyntax::Declaration(declaration) = item else {
+                continue;
+            };
+
+            if matches!(declaration.key_text_type(), TextType::Variable(_))
+                && !variables
+                    .iter()
+                    .any(|known| known.key.text == declaration.key.text)
+            {
+                variables.push(declaration);
+            }
+        }
+    }
+
+    variables
+}
+
+fn find_macro<'s, 'a>(items: &'s [ItemSyntax<'a>], name: &str) -> Option<&'s SyntaxToken<'a>> {
+    items.iter().find_map(|item| {
+        let ItemSyntax::Rule(rule) = item else {
+            return None;
+        };
+
+        match (&rule.selector[0].token, rule.selector.get(1)) {
+            (Token::MacroDeclaration, Some(macro_name)) if macro_name.text == name => {
+                Some(macro_name)
+            }
+            _ => find_macro(&rule.items, name),
+        }
+    })
+}
+
+/// Picks the class a scope styles from the selectors around it, so that
+/// `Frame { :hover { ... } }` styles a Frame too.
+pub fn scope_class(chain: &[Scope]) -> Option<&'static ClassDescriptor<'static>> {
+    let database = rbx_reflection_database::get();
+
+    chain
+        .iter()
+        .rev()
+        .filter_map(|scope| scope.rule)
+        .find_map(|rule| {
+            rule.selector
+                .iter()
+                .rev()
+                .find_map(|token| match token.token {
+                    Token::Text(TextType::NonSpecial(name)) => database.classes.get(name),
+                    _ => None,
+                })
+        })
+}
+
+pub fn find_property(
+    class: &'static ClassDescriptor<'static>,
+    name: &str,
+) -> Option<(
+    &'static ClassDescriptor<'static>,
+    &'static PropertyDescriptor<'static>,
+)> {
+    let database = rbx_reflection_database::get();
+
+    database
+        .superclasses(class)?
+        .into_iter()
+        .find_map(|class| Some((class, class.properties.get(name)?)))
+}
+
+pub fn type_name(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Value(variant_type) => format!("{:?}", variant_type),
+        DataType::Enum(enum_name) => format!("Enum.{}", enum_name),
+        _ => String::from("unknown"),
+    }
+}
+
+/// Follows variable references until reaching an actual value.
+fn resolve<'t>(
+    chain: &[Scope],
+    tree: &'t Arena<TokenTreeNode>,
+    mut variant: Option<&'t Variant>,
+) -> Option<&'t Variant> {
+    for _ in 0..MAX_VARIABLE_DEPTH {
+        let Some(Variant::String(text)) = variant else {
+            break;
+        };
+        let Some(name) = text.strip_prefix('$') else {
+            break;
+        };
+
+        let (_, node_index) = find_variable(chain, name)?;
+        variant = tree.get(Id::new(node_index))?.variables.get(name);
+    }
+
+    variant
+}
+
+fn value_text(variant: Option<&Variant>, declaration: &DeclarationSyntax, source: &str) -> String {
+    variant
+        .and_then(variant_to_rsml)
+        .unwrap_or_else(|| source[declaration.value_span()].to_string())
+}
+
+/// Where the variable or macro under `offset` is defined.
+pub fn definition(document: &Document, offset: usize) -> Option<Range<usize>> {
+    let syntax = rbx_rsml::parse_syntax(&document.text);
+    let chain = scope_chain(&syntax, document.text.len(), offset);
+    let (_, token) = token_at(chain.last().unwrap().items, offset)?;
+
+    if let Some(name) = variable_name(token) {
+        let (declaration, _) = find_variable(&chain, name)?;
+        return Some(declaration.key.span.clone());
+    }
+
+    match token.token {
+        Token::Text(TextType::NonSpecial(name)) => {
+            find_macro(&syntax.items, name).map(|token| token.span.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Markdown describing the variable or declaration under `offset`, with its
+/// value as the compiler resolves it.
+pub fn hover(document: &Document, offset: usize) -> Option<String> {
+    let source = &document.text;
+    let syntax = rbx_rsml::parse_syntax(source);
+    let chain = scope_chain(&syntax, source.len(), offset);
+    let (item, token) = token_at(chain.last().unwrap().items, offset)?;
+
+    let tokens = lex_rsml(source);
+    let tree = parse_rsml(&tokens);
+
+    if let Some(name) = variable_name(token) {
+        let (declaration, node_index) = find_variable(&chain, name)?;
+        let variant = tree
+            .get(Id::new(node_index))
+            .and_then(|node| node.variables.get(name));
+        let value = value_text(resolve(&chain, &tree, variant), declaration, source);
+
+        return Some(format!("```rsml\n${} = {}\n```", name, value));
+    }
+
+    let ItemSyntax::Declaration(declaration) = item else {
+        return None;
+    };
+    let TextType::NonSpecial(property) = declaration.key_text_type() else {
+        return None;
+    };
+
+    let scope = chain.last().unwrap();
+    let variant = tree
+        .get(Id::new(scope.node_index))
+        .and_then(|node| node.properties.get(property));
+    let value = value_text(resolve(&chain, &tree, variant), declaration, source);
+
+    let mut contents = format!("```rsml\n{} = {}\n```", property, value);
+
+    if let Some((class, descriptor)) =
+        scope_class(&chain).and_then(|class| find_property(class, property))
+    {
+        contents.push_str(&format!(
+            "\n\n`{}.{}`: `{}`",
+            class.name,
+            property,
+            type_name(&descriptor.data_type)
+        ));
+    }
+
+    Some(contents)
+}
+
+/// Every color literal in the document, so editors can show swatches.
+pub fn colors(document: &Document) -> Vec<ColorInformation> {
+    let syntax = rbx_rsml::parse_syntax(&document.text);
+    let mut colors = Vec::new();
+
+    for_each_token(&syntax.items, &mut |token| {
+        if let Some(color) = color_literal(&token.token) {
+            colors.push(ColorInformation {
+                range: document.range(token.span.clone()),
+                color: Color {
+                    red: color.r,
+                    green: color.g,
+                    blue: color.b,
+                    alpha: 1.0,
+                },
+            });
+        }
+    });
+
+    colors
+}
+
+#[allow(deprecated)]
+fn item_symbols(document: &Document, items: &[ItemSyntax]) -> Vec<DocumentSymbol> {
+    items
+        .iter()
+        .filter_map(|item| match item {
+            ItemSyntax::Rule(rule) => {
+                let selector_span = rule.selector_span();
+                let end = match &rule.close {
+                    Some(close) => close.span.end,
+                    None => document.text.len(),
+                };
+                let kind = match rule.selector[0].token {
+                    Token::MacroDeclaration => SymbolKind::FUNCTION,
+                    _ => SymbolKind::CLASS,
+                };
+
+                Some(DocumentSymbol {
+                    name: document.text[selector_span.clone()]
+                        .split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    detail: None,
+                    kind,
+                    tags: None,
+                    deprecated: None,
+                    range: document.range(selector_span.start..end),
+                    selection_range: document.range(selector_span),
+                    children: Some(item_symbols(document, &rule.items)),
+                })
+            }
+
+            ItemSyntax::Declaration(declaration) => match declaration.key_text_type() {
+                TextType::Variable(name) => Some(DocumentSymbol {
+                    name: format!("${}", name),
+                    detail: Some(document.text[declaration.value_span()].to_string()),
+                    kind: SymbolKind::VARIABLE,
+                    tags: None,
+                    deprecated: None,
+                    range: document.range(declaration.span()),
+                    selection_range: document.range(declaration.key.span.clone()),
+                    children: None,
+                }),
+                _ => None,
+            },
+
+            _ => None,
+        })
+        .collect()
+}
+
+/// The rules, macros and variables of a document, nested the way they are in
+/// the source.
+pub fn document_symbols(document: &Document) -> Vec<DocumentSymbol> {
+    let syntax = rbx_rsml::parse_syntax(&document.text);
+    item_symbols(document, &syntax.items)
+}
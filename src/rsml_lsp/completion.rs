@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+
+use lsp_types::{CompletionItem, CompletionItemKind, CompletionTextEdit, Range, TextEdit};
+use rbx_reflection::{ClassTag, DataType, PropertyDescriptor, PropertyTag, Scriptability};
+
+use super::{
+    analysis::{find_property, scope_chain, scope_class, type_name, visible_variables},
+    document::Document,
+};
+
+/// Properties are offered for this class when a rule's selector doesn't name
+/// one, since almost everything that gets styled is a GuiObject.
+const FALLBACK_CLASS: &str = "GuiObject";
+
+fn is_word_char(char: char) -> bool {
+    char.is_ascii_alphanumeric() || matches!(char, '_' | '-' | '.' | ':' | '$' | '!')
+}
+
+/// Whether a property can be set from a StyleRule.
+fn is_styleable(descriptor: &PropertyDescriptor) -> bool {
+    matches!(descriptor.scriptability, Scriptability::ReadWrite)
+        && !descriptor.tags.iter().any(|tag| {
+            matches!(
+                tag,
+                PropertyTag::Deprecated
+                    | PropertyTag::Hidden
+                    | PropertyTag::NotScriptable
+                    | PropertyTag::ReadOnly
+            )
+        })
+}
+
+/// Whether instances of a class can be the target of a selector.
+fn is_styleable_class(name: &str) -> bool {
+    let database = rbx_reflection_database::get();
+    let Some(class) = database.classes.get(name) else {
+        return false;
+    };
+
+    !class.tags.contains(&ClassTag::Deprecated)
+        && database.superclasses(class).is_some_and(|superclasses| {
+            superclasses
+                .iter()
+                .any(|class| matches!(&*class.name, "GuiBase2d" | "UIBase"))
+        })
+}
+
+fn completion(
+    label: String,
+    kind: CompletionItemKind,
+    detail: Option<String>,
+    sort_group: u8,
+    range: Range,
+) -> CompletionItem {
+    CompletionItem {
+        kind: Some(kind),
+        detail,
+        sort_text: Some(format!("{}{}", sort_group, label)),
+        filter_text: Some(label.clone()),
+        text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+            range,
+            label.clone(),
+        ))),
+        label,
+        ..Default::default()
+    }
+}
+
+/// Suggestions for the word being typed at `offset`: property and class names
+/// where a declaration or rule would start, and variables and enum items where
+/// a value goes.
+pub fn completions(document: &Document, offset: usize) -> Vec<CompletionItem> {
+    let source = &document.text;
+    let syntax = rbx_rsml::parse_syntax(source);
+    let chain = scope_chain(&syntax, source.len(), offset);
+    let database = rbx_reflection_database::get();
+
+    let before = &source[..offset];
+    let word_start = before.trim_end_matches(is_word_char).len();
+    let range = document.range(word_start..offset);
+
+    let statement_start = before
+        .rfind(['\n', ';', '{', '}'])
+        .map_or(0, |index| index + 1);
+    let statement = &source[statement_start..word_start];
+
+    let class = scope_class(&chain).or_else(|| database.classes.get(FALLBACK_CLASS));
+    let mut items = Vec::new();
+
+    match statement.split_once('=') {
+        Some((key, _)) => {
+            for declaration in visible_variables(&chain) {
+                items.push(completion(
+                    declaration.key.text.to_string(),
+                    CompletionItemKind::VARIABLE,
+                    Some(source[declaration.value_span()].to_string()),
+                    0,
+                    range,
+                ));
+            }
+
+            let enum_name = class
+                .and_then(|class| find_property(class, key.trim()))
+                .and_then(|(_, descriptor)| match &descriptor.data_type {
+                    DataType::Enum(enum_name) => Some(enum_name),
+                    _ => None,
+                });
+
+            if let Some(descriptor) = enum_name.and_then(|name| database.enums.get(name)) {
+                let mut enum_items = descriptor.items.iter().collect::<Vec<_>>();
+                enum_items.sort_by_key(|(_, value)| **value);
+
+                for (item_name, _) in enum_items {
+                    items.push(completion(
+                        format!("Enum.{}.{}", descriptor.name, item_name),
+                        CompletionItemKind::ENUM_MEMBER,
+                        None,
+                        1,
+                        range,
+                    ));
+                }
+            }
+        }
+
+        None => {
+            let in_rule = chain.len() > 1;
+
+            if let (true, Some(class)) = (in_rule, class) {
+                let mut properties = BTreeMap::new();
+
+                for class in database.superclasses(class).unwrap_or_default() {
+                    for (name, descriptor) in &class.properties {
+                        if is_styleable(descriptor) {
+                            properties.entry(name).or_insert(descriptor);
+                        }
+                    }
+                }
+
+                for (name, descriptor) in properties {
+                    items.push(completion(
+                        name.to_string(),
+                        CompletionItemKind::PROPERTY,
+                        Some(type_name(&descriptor.data_type)),
+                        0,
+                        range,
+                    ));
+                }
+            }
+
+            let mut classes = database
+                .classes
+                .keys()
+                .filter(|name| is_styleable_class(name))
+                .collect::<Vec<_>>();
+            classes.sort();
+
+            for name in classes {
+                items.push(completion(
+                    name.to_string(),
+                    CompletionItemKind::CLASS,
+                    None,
+                    1,
+                    range,
+                ));
+            }
+        }
+    }
+
+    items
+}
@@ -0,0 +1,56 @@
+use std::ops::Range;
+
+use lsp_types::Position;
+
+/// The text of an open document, along with where each of its lines start so
+/// that byte offsets can be converted to and from LSP positions.
+pub struct Document {
+    pub text: String,
+    line_starts: Vec<usize>,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+
+        Self { text, line_starts }
+    }
+
+    /// Converts a byte offset into a position. LSP positions count UTF-16
+    /// code units rather than bytes.
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let line_start = self.line_starts[line];
+
+        let character = self.text[line_start..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>();
+
+        Position::new(line as u32, character as u32)
+    }
+
+    pub fn offset(&self, position: Position) -> usize {
+        let Some(&line_start) = self.line_starts.get(position.line as usize) else {
+            return self.text.len();
+        };
+
+        let mut units = 0;
+        for (index, char) in self.text[line_start..].char_indices() {
+            if units >= position.character as usize || char == '\n' {
+                return line_start + index;
+            }
+
+            units += char.len_utf16();
+        }
+
+        self.text.len()
+    }
+
+    pub fn range(&self, span: Range<usize>) -> lsp_types::Range {
+        lsp_types::Range::new(self.position(span.start), self.position(span.end))
+    }
+}
@@ -0,0 +1,571 @@
+//! A language server for RSML files, spoken over stdio. It answers requests
+//! synchronously and re-parses the document each time, since stylesheets are
+//! small enough that there's nothing worth caching.
+
+mod analysis;
+mod completion;
+mod document;
+
+use std::collections::HashMap;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    request::{
+        ColorPresentationRequest, Completion, DocumentColor, DocumentSymbolRequest, GotoDefinition,
+        HoverRequest, Request as _,
+    },
+    ColorInformation, ColorPresentation, ColorPresentationParams, ColorProviderCapability,
+    CompletionOptions, CompletionParams, CompletionResponse, DiagnosticSeverity,
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    DocumentColorParams, DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, HoverProviderCapability, Location,
+    MarkupContent, MarkupKind, OneOf, PublishDiagnosticsParams, ServerCapabilities,
+    TextDocumentPositionParams, TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Url,
+};
+
+use self::document::Document;
+
+fn server_capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["$".to_owned(), ".".to_owned()]),
+            ..Default::default()
+        }),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        color_provider: Some(ColorProviderCapability::Simple(true)),
+        ..Default::default()
+    }
+}
+
+/// Runs the language server on stdin and stdout until the client exits.
+pub fn run_stdio() -> anyhow::Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    serve(&connection)?;
+
+    drop(connection);
+    io_threads.join()?;
+
+    Ok(())
+}
+
+/// Runs the language server on any connection until the client shuts it down.
+pub fn serve(connection: &Connection) -> anyhow::Result<()> {
+    connection.initialize(serde_json::to_value(server_capabilities())?)?;
+
+    let mut server = Server::default();
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+
+                connection
+                    .sender
+                    .send(Message::Response(server.handle_request(request)))?;
+            }
+            Message::Notification(notification) => {
+                if let Some(diagnostics) = server.handle_notification(notification) {
+                    connection
+                        .sender
+                        .send(Message::Notification(Notification::new(
+                            PublishDiagnostics::METHOD.to_owned(),
+                            diagnostics,
+                        )))?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<Url, Document>,
+}
+
+impl Server {
+    fn handle_request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            Completion::METHOD => self.respond::<Completion>(request, Self::completion),
+            HoverRequest::METHOD => self.respond::<HoverRequest>(request, Self::hover),
+            GotoDefinition::METHOD => self.respond::<GotoDefinition>(request, Self::definition),
+            DocumentSymbolRequest::METHOD => {
+                self.respond::<DocumentSymbolRequest>(request, Self::document_symbols)
+            }
+            DocumentColor::METHOD => self.respond::<DocumentColor>(request, Self::colors),
+            ColorPresentationRequest::METHOD => {
+                self.respond::<ColorPresentationRequest>(request, Self::color_presentations)
+            }
+            _ => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("Unsupported request '{}'", request.method),
+            ),
+        }
+    }
+
+    fn respond<R: lsp_types::request::Request>(
+        &self,
+        request: Request,
+        handler: fn(&Self, R::Params) -> R::Result,
+    ) -> Response {
+        match serde_json::from_value(request.params) {
+            Ok(params) => Response::new_ok(request.id, handler(self, params)),
+            Err(err) => {
+                Response::new_err(request.id, ErrorCode::InvalidParams as i32, err.to_string())
+            }
+        }
+    }
+
+    /// Keeps track of open documents. Returns new diagnostics to publish if a
+    /// document changed.
+    fn handle_notification(
+        &mut self,
+        notification: Notification,
+    ) -> Option<PublishDiagnosticsParams> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                let uri = params.text_document.uri;
+
+                self.documents
+                    .insert(uri.clone(), Document::new(params.text_document.text));
+                Some(self.diagnostics(uri))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                let uri = params.text_document.uri;
+
+                // Documents are synced in full, so the last change holds the
+                // whole text.
+                let change = params.content_changes.into_iter().last()?;
+                self.documents
+                    .insert(uri.clone(), Document::new(change.text));
+                Some(self.diagnostics(uri))
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                let uri = params.text_document.uri;
+
+                self.documents.remove(&uri);
+                Some(PublishDiagnosticsParams::new(uri, Vec::new(), None))
+            }
+            _ => None,
+        }
+    }
+
+    /// Finds the document and byte offset that a request points at.
+    fn locate(&self, position: &TextDocumentPositionParams) -> Option<(&Document, usize)> {
+        let document = self.documents.get(&position.text_document.uri)?;
+        Some((document, document.offset(position.position)))
+    }
+
+    fn diagnostics(&self, uri: Url) -> PublishDiagnosticsParams {
+        let diagnostics = match self.documents.get(&uri) {
+            Some(document) => {
+                let syntax = rbx_rsml::parse_syntax(&document.text);
+
+                rbx_rsml::check_syntax(&syntax)
+                    .into_iter()
+                    .map(|diagnostic| lsp_types::Diagnostic {
+                        range: document.range(diagnostic.span),
+                        severity: Some(match diagnostic.severity {
+                            rbx_rsml::Severity::Error => DiagnosticSeverity::ERROR,
+                            rbx_rsml::Severity::Warning => DiagnosticSeverity::WARNING,
+                        }),
+                        source: Some("rsml".to_owned()),
+                        message: diagnostic.message,
+                        ..Default::default()
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        PublishDiagnosticsParams::new(uri, diagnostics, None)
+    }
+
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let (document, offset) = self.locate(&params.text_document_position)?;
+        let items = completion::completions(document, offset);
+
+        Some(CompletionResponse::Array(items))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let (document, offset) = self.locate(&params.text_document_position_params)?;
+        let contents = analysis::hover(document, offset)?;
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: contents,
+            }),
+            range: None,
+        })
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let position = &params.text_document_position_params;
+        let (document, offset) = self.locate(position)?;
+        let span = analysis::definition(document, offset)?;
+
+        Some(GotoDefinitionResponse::Scalar(Location::new(
+            position.text_document.uri.clone(),
+            document.range(span),
+        )))
+    }
+
+    fn document_symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let document = self.documents.get(&params.text_document.uri)?;
+
+        Some(DocumentSymbolResponse::Nested(analysis::document_symbols(
+            document,
+        )))
+    }
+
+    fn colors(&self, params: DocumentColorParams) -> Vec<ColorInformation> {
+        match self.documents.get(&params.text_document.uri) {
+            Some(document) => analysis::colors(document),
+            None => Vec::new(),
+        }
+    }
+
+    fn color_presentations(&self, params: ColorPresentationParams) -> Vec<ColorPresentation> {
+        let to_byte = |component: f32| (component.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let label = format!(
+            "#{:02x}{:02x}{:02x}",
+            to_byte(params.color.red),
+            to_byte(params.color.green),
+            to_byte(params.color.blue)
+        );
+
+        vec![ColorPresentation {
+            text_edit: Some(TextEdit::new(params.range, label.clone())),
+            label,
+            additional_text_edits: None,
+        }]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::thread::{self, JoinHandle};
+
+    use lsp_server::RequestId;
+    use lsp_types::{
+        notification::{Exit, Initialized},
+        request::{Initialize, Shutdown},
+        CompletionItem, CompletionTextEdit, DocumentSymbol, InitializeParams, InitializedParams,
+        Position, TextDocumentContentChangeEvent, TextDocumentIdentifier, TextDocumentItem,
+        VersionedTextDocumentIdentifier,
+    };
+
+    /// Drives the server the way an editor would, over an in-memory connection.
+    struct TestClient {
+        connection: Connection,
+        server: Option<JoinHandle<anyhow::Result<()>>>,
+        next_id: i32,
+    }
+
+    impl TestClient {
+        fn start() -> Self {
+            let (server_connection, connection) = Connection::memory();
+            let server = thread::spawn(move || serve(&server_connection));
+
+            let mut client = TestClient {
+                connection,
+                server: Some(server),
+                next_id: 0,
+            };
+
+            client.request::<Initialize>(InitializeParams::default());
+            client.notify::<Initialized>(InitializedParams {});
+
+            client
+        }
+
+        fn uri() -> Url {
+            Url::parse("file:///project/styles.rsml").unwrap()
+        }
+
+        fn request<R: lsp_types::request::Request>(&mut self, params: R::Params) -> R::Result {
+            self.next_id += 1;
+            let id = RequestId::from(self.next_id);
+
+            self.connection
+                .sender
+                .send(Message::Request(Request::new(
+                    id.clone(),
+                    R::METHOD.to_owned(),
+                    params,
+                )))
+                .unwrap();
+
+            loop {
+                match self.connection.receiver.recv().unwrap() {
+                    Message::Response(response) if response.id == id => {
+                        assert!(response.error.is_none(), "{:?}", response.error);
+                        return serde_json::from_value(response.result.unwrap()).unwrap();
+                    }
+                    _ => continue,
+                }
+            }
+        }
+
+        fn notify<N: lsp_types::notification::Notification>(&self, params: N::Params) {
+            self.connection
+                .sender
+                .send(Message::Notification(Notification::new(
+                    N::METHOD.to_owned(),
+                    params,
+                )))
+                .unwrap();
+        }
+
+        fn next_diagnostics(&self) -> PublishDiagnosticsParams {
+            loop {
+                if let Message::Notification(notification) =
+                    self.connection.receiver.recv().unwrap()
+                {
+                    if notification.method == PublishDiagnostics::METHOD {
+                        return serde_json::from_value(notification.params).unwrap();
+                    }
+                }
+            }
+        }
+
+        fn open(&self, text: &str) -> PublishDiagnosticsParams {
+            self.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(
+                    Self::uri(),
+                    "rsml".to_owned(),
+                    1,
+                    text.to_owned(),
+                ),
+            });
+
+            self.next_diagnostics()
+        }
+
+        fn position(line: u32, character: u32) -> TextDocumentPositionParams {
+            TextDocumentPositionParams::new(
+                TextDocumentIdentifier::new(Self::uri()),
+                Position::new(line, character),
+            )
+        }
+
+        fn completions(&mut self, line: u32, character: u32) -> Vec<CompletionItem> {
+            let response = self.request::<Completion>(CompletionParams {
+                text_document_position: Self::position(line, character),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+                context: None,
+            });
+
+            match response {
+                Some(CompletionResponse::Array(items)) => items,
+                other => panic!("unexpected completion response {:?}", other),
+            }
+        }
+
+        fn hover(&mut self, line: u32, character: u32) -> Option<String> {
+            let hover = self.request::<HoverRequest>(HoverParams {
+                text_document_position_params: Self::position(line, character),
+                work_done_progress_params: Default::default(),
+            })?;
+
+            match hover.contents {
+                HoverContents::Markup(markup) => Some(markup.value),
+                other => panic!("unexpected hover contents {:?}", other),
+            }
+        }
+
+        fn definition(&mut self, line: u32, character: u32) -> Option<lsp_types::Range> {
+            let response = self.request::<GotoDefinition>(GotoDefinitionParams {
+                text_document_position_params: Self::position(line, character),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })?;
+
+            match response {
+                GotoDefinitionResponse::Scalar(location) => Some(location.range),
+                other => panic!("unexpected definition response {:?}", other),
+            }
+        }
+    }
+
+    impl Drop for TestClient {
+        fn drop(&mut self) {
+            self.request::<Shutdown>(());
+            self.notify::<Exit>(());
+
+            self.server.take().unwrap().join().unwrap().unwrap();
+        }
+    }
+
+    fn range(start: (u32, u32), end: (u32, u32)) -> lsp_types::Range {
+        lsp_types::Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1))
+    }
+
+    fn labels(items: &[CompletionItem]) -> Vec<&str> {
+        items.iter().map(|item| item.label.as_str()).collect()
+    }
+
+    #[test]
+    fn diagnostics_follow_edits() {
+        let client = TestClient::start();
+
+        let diagnostics = client.open("Frame {\n    Size = ;\n");
+        let messages = diagnostics
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.range, diagnostic.message.as_str()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                (range((0, 6), (0, 7)), "this rule is never closed"),
+                (range((1, 9), (1, 10)), "expected a value after `=`"),
+            ]
+        );
+
+        client.notify::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier::new(TestClient::uri(), 2),
+            content_changes: vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: "Frame {\n    Size = udim2(1, 0, 1, 0);\n}\n".to_owned(),
+            }],
+        });
+
+        assert!(client.next_diagnostics().diagnostics.is_empty());
+    }
+
+    #[test]
+    fn completes_properties_and_enums() {
+        let mut client = TestClient::start();
+        client.open("$Accent = #ff0000;\nTextLabel {\n    Tex\n    Font = \n}\n");
+
+        let properties = client.completions(2, 7);
+        let property_labels = labels(&properties);
+        assert!(property_labels.contains(&"TextColor3"));
+        assert!(property_labels.contains(&"BackgroundColor3"));
+        assert!(
+            !property_labels.contains(&"AbsoluteSize"),
+            "read-only properties are not offered"
+        );
+
+        let text_color = properties
+            .iter()
+            .find(|item| item.label == "TextColor3")
+            .unwrap();
+        assert_eq!(text_color.detail.as_deref(), Some("Color3"));
+        assert_eq!(
+            text_color.text_edit,
+            Some(CompletionTextEdit::Edit(TextEdit::new(
+                range((2, 4), (2, 7)),
+                "TextColor3".to_owned()
+            )))
+        );
+
+        let values = client.completions(3, 11);
+        let value_labels = labels(&values);
+        assert!(value_labels.contains(&"$Accent"));
+        assert!(value_labels.contains(&"Enum.Font.GothamBold"));
+    }
+
+    #[test]
+    fn hovers_resolved_values() {
+        let mut client = TestClient::start();
+        client.open(
+            "$Gap = 2px * 4;\nFrame {\n    Size = udim2(50% + 2px, 10px);\n    $Inner = $Gap;\n}\n",
+        );
+
+        assert_eq!(
+            client.hover(2, 6).unwrap(),
+            "```rsml\nSize = udim2(50% + 2px, 10px)\n```\n\n`GuiObject.Size`: `UDim2`"
+        );
+        assert_eq!(client.hover(3, 15).unwrap(), "```rsml\n$Gap = 8px\n```");
+        assert_eq!(client.hover(3, 6).unwrap(), "```rsml\n$Inner = 8px\n```");
+        assert_eq!(client.hover(1, 2), None);
+    }
+
+    #[test]
+    fn goes_to_definitions() {
+        let mut client = TestClient::start();
+        client.open("$Color = #fff;\n@macro Rounded {\n    CornerRadius = 4px;\n}\nFrame {\n    $Color = #000;\n    TextLabel { TextColor3 = $Color; Rounded; }\n}\n");
+
+        assert_eq!(client.definition(6, 31), Some(range((5, 4), (5, 10))));
+        assert_eq!(client.definition(6, 38), Some(range((1, 7), (1, 14))));
+        assert_eq!(client.definition(6, 16), None);
+    }
+
+    #[test]
+    fn document_symbols_and_colors() {
+        let mut client = TestClient::start();
+        client.open("$Primary = tw:blue:500;\nFrame > TextLabel {\n    TextColor3 = css:red;\n    TextButton:hover { BackgroundColor3 = #00ff00; }\n}\n");
+
+        let symbols = client
+            .request::<DocumentSymbolRequest>(DocumentSymbolParams {
+                text_document: TextDocumentIdentifier::new(TestClient::uri()),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .unwrap();
+
+        let DocumentSymbolResponse::Nested(symbols) = symbols else {
+            panic!("expected nested symbols");
+        };
+
+        fn names(symbols: &[DocumentSymbol]) -> Vec<String> {
+            symbols
+                .iter()
+                .flat_map(|symbol| {
+                    std::iter::once(symbol.name.clone())
+                        .chain(names(symbol.children.as_deref().unwrap_or_default()))
+                })
+                .collect()
+        }
+
+        assert_eq!(
+            names(&symbols),
+            ["$Primary", "Frame > TextLabel", "TextButton:hover"]
+        );
+        assert_eq!(symbols[1].range, range((1, 0), (4, 1)));
+
+        let colors = client.request::<DocumentColor>(DocumentColorParams {
+            text_document: TextDocumentIdentifier::new(TestClient::uri()),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+
+        let ranges = colors.iter().map(|color| color.range).collect::<Vec<_>>();
+        assert_eq!(
+            ranges,
+            [
+                range((0, 11), (0, 22)),
+                range((2, 17), (2, 24)),
+                range((3, 42), (3, 49)),
+            ]
+        );
+        assert_eq!((colors[1].color.red, colors[1].color.green), (1.0, 0.0));
+    }
+}
@@ -21,7 +21,7 @@ use crate::{
         apply_patch_set, compute_patch_set, AppliedPatchSet, InstanceContext, InstanceSnapshot,
         PatchSet, RojoTree,
     },
-    snapshot_middleware::snapshot_from_vfs,
+    snapshot_middleware::{snapshot_from_vfs, RsmlCache},
 };
 
 /// Contains all of the state for a Rojo serve session. A serve session is used
@@ -115,8 +115,9 @@ impl ServeSession {
 
         let root_id = tree.get_root_id();
 
-        let instance_context =
+        let mut instance_context =
             InstanceContext::with_emit_legacy_scripts(root_project.emit_legacy_scripts);
+        instance_context.set_rsml_cache(RsmlCache::new());
 
         log::trace!("Generating snapshot of instances from VFS");
         let snapshot = snapshot_from_vfs(&instance_context, &vfs, start_path)?;
@@ -11,7 +11,7 @@ use crate::{
     glob::Glob,
     path_serializer,
     project::ProjectNode,
-    snapshot_middleware::{emit_legacy_scripts_default, Middleware},
+    snapshot_middleware::{emit_legacy_scripts_default, Middleware, RsmlCache, RsmlUtilities},
     RojoRef,
 };
 
@@ -62,6 +62,27 @@ pub struct InstanceMetadata {
 
     /// Indicates the ID used for Ref properties pointing to this Instance.
     pub specified_id: Option<RojoRef>,
+
+    /// For instances generated from declarations in a file that can produce
+    /// siblings with the same name, like the StyleRules of a `.rsml` file,
+    /// which of those siblings this instance was declared as, counting from
+    /// zero.
+    ///
+    /// Patch computation pairs siblings up by name and class name, giving
+    /// siblings whose properties haven't changed the first pick. The
+    /// declaration index only breaks ties between siblings that would both
+    /// do, so an instance can be paired with a different declaration than
+    /// the one it came from, and is given that declaration's index.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub declaration_index: Option<usize>,
+
+    /// For instances generated from a file, whether they come from
+    /// declarations that aren't written out in it as they are, like the
+    /// StyleRules made by the `@each` and `@if` blocks of a `.rsml` file.
+    /// Changes to these can't be written back to the file. They have no
+    /// declaration index.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub generated: bool,
 }
 
 impl InstanceMetadata {
@@ -72,6 +93,8 @@ impl InstanceMetadata {
             relevant_paths: Vec::new(),
             context: InstanceContext::default(),
             specified_id: None,
+            declaration_index: None,
+            generated: false,
         }
     }
 
@@ -109,6 +132,17 @@ impl InstanceMetadata {
             ..self
         }
     }
+
+    pub fn declaration_index(self, declaration_index: usize) -> Self {
+        Self {
+            declaration_index: Some(declaration_index),
+            ..self
+        }
+    }
+
+    pub fn generated(self, generated: bool) -> Self {
+        Self { generated, ..self }
+    }
 }
 
 impl Default for InstanceMetadata {
@@ -122,8 +156,28 @@ pub struct InstanceContext {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub path_ignore_rules: Arc<Vec<PathIgnoreRule>>,
     pub emit_legacy_scripts: bool,
+    /// Whether `.rsml` files get a `Variables` ModuleScript with their root
+    /// variables, for game code to use.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub emit_rsml_variables: bool,
+    /// Where utility classes for `@utilities` come from, if the project
+    /// turned them on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rsml_utilities: Option<RsmlUtilities>,
+    /// The asset manifest that `asset("name")` looks names up in, if the
+    /// project has one.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "path_serializer::serialize_option_absolute"
+    )]
+    pub rsml_assets: Option<PathBuf>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub sync_rules: Vec<SyncRule>,
+    /// Parsed `.rsml` files, shared between snapshots so that unchanged files
+    /// aren't parsed again. Only serve sessions set this.
+    #[serde(skip)]
+    pub rsml_cache: Option<RsmlCache>,
 }
 
 impl InstanceContext {
@@ -131,7 +185,11 @@ impl InstanceContext {
         Self {
             path_ignore_rules: Arc::new(Vec::new()),
             emit_legacy_scripts: emit_legacy_scripts_default().unwrap(),
+            emit_rsml_variables: false,
+            rsml_utilities: None,
+            rsml_assets: None,
             sync_rules: Vec::new(),
+            rsml_cache: None,
         }
     }
 
@@ -179,6 +237,22 @@ impl InstanceContext {
         self.emit_legacy_scripts = emit_legacy_scripts;
     }
 
+    pub fn set_emit_rsml_variables(&mut self, emit_rsml_variables: bool) {
+        self.emit_rsml_variables = emit_rsml_variables;
+    }
+
+    pub fn set_rsml_utilities(&mut self, rsml_utilities: Option<RsmlUtilities>) {
+        self.rsml_utilities = rsml_utilities;
+    }
+
+    pub fn set_rsml_assets(&mut self, rsml_assets: Option<PathBuf>) {
+        self.rsml_assets = rsml_assets;
+    }
+
+    pub fn set_rsml_cache(&mut self, rsml_cache: RsmlCache) {
+        self.rsml_cache = Some(rsml_cache);
+    }
+
     /// Returns the middleware specified by the first sync rule that
     /// matches the provided path. This does not handle default syncing rules.
     pub fn get_user_sync_rule(&self, path: &Path) -> Option<&SyncRule> {
@@ -192,40 +192,66 @@ fn compute_children_patches(
         .expect("Instance did not exist in tree");
 
     let instance_children = instance.children();
+    let snapshot_children = take(&mut snapshot.children);
 
     let mut paired_instances = vec![false; instance_children.len()];
+    let mut pairings = vec![None; snapshot_children.len()];
+
+    // Children are paired up by name and class name. Children whose properties
+    // haven't changed at all get first pick, so that adding, removing or
+    // reordering one of several children with the same name only touches that
+    // child instead of shifting every sibling after it. When more than one
+    // child would do, the one declared at the same position is preferred.
+    for exact in [true, false] {
+        for (snapshot_child, pairing) in snapshot_children.iter().zip(pairings.iter_mut()) {
+            if pairing.is_some() {
+                continue;
+            }
 
-    for snapshot_child in take(&mut snapshot.children) {
-        let matching_instance =
-            instance_children
+            let mut candidates = instance_children
                 .iter()
                 .enumerate()
-                .find(|(instance_index, instance_child_id)| {
-                    if paired_instances[*instance_index] {
-                        return false;
-                    }
-
+                .filter(|(instance_index, _)| !paired_instances[*instance_index])
+                .map(|(instance_index, instance_child_id)| {
                     let instance_child = tree
-                        .get_instance(**instance_child_id)
+                        .get_instance(*instance_child_id)
                         .expect("Instance did not exist in tree");
 
-                    if snapshot_child.name == instance_child.name()
+                    (instance_index, instance_child)
+                })
+                .filter(|(_, instance_child)| {
+                    snapshot_child.name == instance_child.name()
                         && snapshot_child.class_name == instance_child.class_name()
-                    {
-                        paired_instances[*instance_index] = true;
-                        return true;
-                    }
+                        && (!exact || &snapshot_child.properties == instance_child.properties())
+                })
+                .collect::<Vec<_>>();
 
-                    false
-                });
+            if candidates.is_empty() {
+                continue;
+            }
 
-        match matching_instance {
-            Some((_, instance_child_id)) => {
+            let position = candidates
+                .iter()
+                .position(|(_, instance_child)| {
+                    snapshot_child.metadata.declaration_index
+                        == instance_child.metadata().declaration_index
+                })
+                .unwrap_or(0);
+
+            let (instance_index, instance_child) = candidates.swap_remove(position);
+            paired_instances[instance_index] = true;
+            *pairing = Some(instance_child.id());
+        }
+    }
+
+    for (snapshot_child, pairing) in snapshot_children.into_iter().zip(pairings) {
+        match pairing {
+            Some(instance_child_id) => {
                 compute_patch_set_internal(
                     context,
                     snapshot_child,
                     tree,
-                    *instance_child_id,
+                    instance_child_id,
                     patch_set,
                 );
             }
@@ -388,4 +414,40 @@ mod test {
 
         assert_eq!(patch_set, expected_patch_set);
     }
+
+    /// Inserting a child in front of siblings with the same name should only
+    /// add that child, rather than shifting every sibling's properties along.
+    #[test]
+    fn unchanged_children_keep_their_instances() {
+        let rule = |priority: i32| {
+            InstanceSnapshot::new()
+                .name("Frame")
+                .class_name("StyleRule")
+                .property("Priority", priority)
+        };
+
+        let tree = RojoTree::new(
+            InstanceSnapshot::new()
+                .name("foo")
+                .class_name("StyleSheet")
+                .children(vec![rule(1), rule(2)]),
+        );
+
+        let root_id = tree.get_root_id();
+
+        let snapshot = InstanceSnapshot::new()
+            .name("foo")
+            .class_name("StyleSheet")
+            .children(vec![rule(0), rule(1), rule(2)]);
+
+        let patch_set = compute_patch_set(Some(snapshot), &tree, root_id);
+
+        assert!(patch_set.removed_instances.is_empty());
+        assert!(patch_set.updated_instances.is_empty());
+        assert_eq!(patch_set.added_instances.len(), 1);
+        assert_eq!(
+            patch_set.added_instances[0].instance.properties["Priority"],
+            Variant::Int32(0)
+        );
+    }
 }
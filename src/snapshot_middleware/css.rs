@@ -0,0 +1,100 @@
+use std::path::Path;
+
+use memofs::{IoResultExt, Vfs};
+use rbx_rsml::{css_to_rsml, line_column};
+
+use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
+
+use super::{
+    meta_file::AdjacentMetadata,
+    rsml::{compile_source, stylesheet_ref},
+};
+
+/// Converts a `.css` file into RSML and compiles it into a StyleSheet, like a
+/// `.rsml` file. Anything that can't be converted is left out with a warning.
+/// This middleware is only used through sync rules.
+pub fn snapshot_css(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let contents = vfs.read_to_string(path)?;
+    let conversion = css_to_rsml(&contents);
+
+    for diagnostic in &conversion.diagnostics {
+        let (line, column) = line_column(&contents, diagnostic.span.start);
+        log::warn!(
+            "{}:{}:{}: {}",
+            path.display(),
+            line,
+            column,
+            diagnostic.message
+        );
+    }
+
+    let (snapshot, read_paths) = compile_source(context, vfs, path, &conversion.rsml, name)?;
+
+    let meta_path = path.with_file_name(format!("{}.meta.json", name));
+
+    let mut relevant_paths = vec![path.to_path_buf(), meta_path.clone()];
+    relevant_paths.extend(read_paths);
+
+    let mut snapshot = snapshot.metadata(
+        InstanceMetadata::new()
+            .instigating_source(path)
+            .relevant_paths(relevant_paths)
+            .context(context),
+    );
+
+    if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
+        let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
+        metadata.apply_all(&mut snapshot)?;
+    }
+
+    snapshot
+        .metadata
+        .specified_id
+        .get_or_insert_with(|| stylesheet_ref(path));
+
+    Ok(Some(snapshot))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+    use rbx_dom_weak::types::Variant;
+
+    #[test]
+    fn converted_stylesheet() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/theme.css",
+            VfsSnapshot::file(".card { background-color: #ffffff; float: left; }"),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let snapshot = snapshot_css(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/theme.css"),
+            "theme",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(snapshot.class_name, "StyleSheet");
+        assert_eq!(snapshot.children.len(), 1);
+
+        let rule = &snapshot.children[0];
+        assert_eq!(rule.class_name, "StyleRule");
+        assert_eq!(
+            rule.properties.get("Selector"),
+            Some(&Variant::String(".card".to_owned()))
+        );
+    }
+}
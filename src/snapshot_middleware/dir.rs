@@ -88,6 +88,7 @@ pub fn snapshot_dir_no_meta(
         path.join("init.client.lua"),
         path.join("init.client.luau"),
         path.join("init.csv"),
+        path.join("init.rsml"),
     ];
 
     let snapshot = InstanceSnapshot::new()
@@ -6,6 +6,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::{resolution::UnresolvedValue, snapshot::InstanceSnapshot, RojoRef};
 
+use super::rsml::style_link;
+
 /// Represents metadata in a sibling file with the same basename.
 ///
 /// As an example, hello.meta.json next to hello.lua would allow assigning
@@ -28,6 +30,9 @@ pub struct AdjacentMetadata {
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub attributes: HashMap<String, UnresolvedValue>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub style_sheet: Option<String>,
+
     #[serde(skip)]
     pub path: PathBuf,
 }
@@ -93,9 +98,17 @@ impl AdjacentMetadata {
         self.apply_ignore_unknown_instances(snapshot);
         self.apply_properties(snapshot)?;
         self.apply_id(snapshot)?;
+        self.apply_style_sheet(snapshot);
         Ok(())
     }
 
+    fn apply_style_sheet(&mut self, snapshot: &mut InstanceSnapshot) {
+        if let Some(style_sheet) = self.style_sheet.take() {
+            let folder = self.path.parent().unwrap();
+            snapshot.children.push(style_link(folder, &style_sheet));
+        }
+    }
+
     // TODO: Add method to allow selectively applying parts of metadata and
     // throwing errors if invalid parts are specified.
 }
@@ -122,6 +135,9 @@ pub struct DirectoryMetadata {
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub attributes: HashMap<String, UnresolvedValue>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub style_sheet: Option<String>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub class_name: Option<String>,
 
@@ -147,6 +163,7 @@ impl DirectoryMetadata {
         self.apply_class_name(snapshot)?;
         self.apply_properties(snapshot)?;
         self.apply_id(snapshot)?;
+        self.apply_style_sheet(snapshot);
 
         Ok(())
     }
@@ -210,4 +227,11 @@ impl DirectoryMetadata {
         snapshot.metadata.specified_id = self.id.take().map(RojoRef::new);
         Ok(())
     }
+
+    fn apply_style_sheet(&mut self, snapshot: &mut InstanceSnapshot) {
+        if let Some(style_sheet) = self.style_sheet.take() {
+            let folder = self.path.parent().unwrap();
+            snapshot.children.push(style_link(folder, &style_sheet));
+        }
+    }
 }
@@ -5,6 +5,7 @@
 
 #![allow(dead_code)]
 
+mod css;
 mod csv;
 mod dir;
 mod json;
@@ -17,6 +18,7 @@ mod rbxmx;
 mod toml;
 mod txt;
 mod rsml;
+mod rsml_module;
 mod util;
 
 use std::{
@@ -42,10 +44,16 @@ use self::{
     rbxmx::snapshot_rbxmx,
     toml::snapshot_toml,
     txt::snapshot_txt,
-    rsml::snapshot_rsml,
+    rsml::{snapshot_rsml, snapshot_rsml_init},
+    rsml_module::snapshot_rsml_module,
+    css::snapshot_css,
 };
 
-pub use self::{project::snapshot_project_node, util::emit_legacy_scripts_default};
+pub use self::{
+    project::snapshot_project_node,
+    rsml::{RsmlCache, RsmlUtilities},
+    util::emit_legacy_scripts_default,
+};
 
 /// Returns an `InstanceSnapshot` for the provided path.
 /// This will inspect the path and find the appropriate middleware for it,
@@ -91,6 +99,8 @@ pub fn snapshot_from_vfs(
 
                         Middleware::Csv => snapshot_csv_init(context, vfs, &init_path),
 
+                        Middleware::Rsml => snapshot_rsml_init(context, vfs, &init_path),
+
                         _ => snapshot_dir(context, vfs, path),
                     };
                 }
@@ -108,7 +118,7 @@ pub fn snapshot_from_vfs(
         // TODO: Is this even necessary anymore?
         match file_name {
             "init.server.luau" | "init.server.lua" | "init.client.luau" | "init.client.lua"
-            | "init.luau" | "init.lua" | "init.csv" => return Ok(None),
+            | "init.luau" | "init.lua" | "init.csv" | "init.rsml" => return Ok(None),
             _ => {}
         }
 
@@ -162,6 +172,11 @@ fn get_init_path<P: AsRef<Path>>(vfs: &Vfs, dir: P) -> anyhow::Result<Option<Pat
         return Ok(Some(init_path));
     }
 
+    let init_path = path.join("init.rsml");
+    if vfs.metadata(&init_path).with_not_found()?.is_some() {
+        return Ok(Some(init_path));
+    }
+
     Ok(None)
 }
 
@@ -210,6 +225,8 @@ pub enum Middleware {
     Toml,
     Text,
     Rsml,
+    RsmlModule,
+    Css,
     Ignore,
 }
 
@@ -236,6 +253,8 @@ impl Middleware {
             Self::Toml => snapshot_toml(context, vfs, path, name),
             Self::Text => snapshot_txt(context, vfs, path, name),
             Self::Rsml => snapshot_rsml(context, vfs, path, name),
+            Self::RsmlModule => snapshot_rsml_module(context, vfs, path, name),
+            Self::Css => snapshot_css(context, vfs, path, name),
             Self::Ignore => Ok(None),
         }
     }
@@ -14,7 +14,11 @@ use crate::{
     RojoRef,
 };
 
-use super::{emit_legacy_scripts_default, snapshot_from_vfs};
+use super::{
+    emit_legacy_scripts_default,
+    rsml::{style_link, tag_registry, RsmlUtilities},
+    snapshot_from_vfs,
+};
 
 pub fn snapshot_project(
     context: &InstanceContext,
@@ -50,6 +54,17 @@ pub fn snapshot_project(
             .or_else(emit_legacy_scripts_default)
             .unwrap(),
     );
+    context.set_emit_rsml_variables(project.emit_rsml_variables.unwrap_or(false));
+    context.set_rsml_utilities(project.rsml_utilities.clone().map(|scale| RsmlUtilities {
+        root: project.folder_location().to_path_buf(),
+        scale,
+    }));
+    context.set_rsml_assets(
+        project
+            .rsml_assets
+            .as_ref()
+            .map(|path| project.folder_location().join(path)),
+    );
 
     match snapshot_project_node(&context, path, project_name, &project.tree, vfs, None)? {
         Some(found_snapshot) => {
@@ -224,6 +239,18 @@ pub fn snapshot_project_node(
         }
     }
 
+    if let Some(style_sheet) = &node.style_sheet {
+        children.push(style_link(project_folder, style_sheet));
+    }
+
+    // The tags module depends on files all over the project, so they're
+    // relevant to this node, which gets snapshotted again when they change.
+    if node.rsml_tags == Some(true) {
+        let (tags, read_paths) = tag_registry(context, vfs, project_folder)?;
+        children.push(tags);
+        metadata.relevant_paths.extend(read_paths);
+    }
+
     for (key, unresolved) in &node.properties {
         let value = unresolved
             .clone()
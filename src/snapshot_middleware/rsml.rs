@@ -1,62 +1,563 @@
 // Modules -------------------------------------------------------------------------------------------
-use std::{collections::HashMap, path::Path};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ffi::OsStr;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
+use anyhow::{bail, format_err, Context};
 use memofs::{IoResultExt, Vfs};
 
+use crate::lua_ast::{Chunk, Expression, Statement};
+use crate::path_serializer;
 use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
+use crate::{RojoRef, REF_POINTER_ATTRIBUTE_PREFIX};
 
-use super::meta_file::AdjacentMetadata;
+use super::{
+    dir::{dir_meta, snapshot_dir_no_meta},
+    meta_file::AdjacentMetadata,
+};
 
-use rbx_dom_weak::types::{Attributes, Variant};
+use rbx_dom_weak::types::{Attributes, Color3, Tags, UDim, Variant};
+use rbx_rsml::{
+    asset_names, candidate_tags, compile_stylesheet, design_token_file_path, line_column, normalize_path,
+    parse_asset_manifest, parse_ast, parse_design_tokens, parse_stylesheet, selector_names, utility_rules, CompileOptions,
+    CompiledInstance, Diagnostics, StyleSheet, UtilityScale,
+};
+use serde::{Deserialize, Serialize};
+// ---------------------------------------------------------------------------------------------------
+
+
+// Cache ---------------------------------------------------------------------------------------------
+type Parsed = Arc<Result<StyleSheet, Diagnostics>>;
+
+struct CachedStyleSheet {
+    content_hash: u64,
+    parsed: Parsed,
+}
+
+/// The parsed form of every `.rsml` file snapshotted so far, so that a file is only parsed again
+/// once its contents change. Serve sessions keep one of these in their `InstanceContext`; clones
+/// share the same entries.
+#[derive(Clone, Default)]
+pub struct RsmlCache(Arc<Mutex<HashMap<PathBuf, CachedStyleSheet>>>);
+
+impl RsmlCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn parse(&self, path: &Path, contents: &str) -> Parsed {
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        let content_hash = hasher.finish();
+
+        let mut entries = self.0.lock().unwrap();
+        if let Some(entry) = entries.get(path) {
+            if entry.content_hash == content_hash { return Arc::clone(&entry.parsed) }
+        }
 
-use rbx_rsml::{lex_rsml, parse_rsml, Arena, TokenTreeNode};
+        let parsed = Arc::new(parse_stylesheet(contents));
+        entries.insert(path.to_path_buf(), CachedStyleSheet { content_hash, parsed: Arc::clone(&parsed) });
+
+        parsed
+    }
+}
+
+impl fmt::Debug for RsmlCache {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "RsmlCache({} files)", self.0.lock().unwrap().len())
+    }
+}
+
+// The cache only saves work, it never changes what a context snapshots into.
+impl PartialEq for RsmlCache {
+    fn eq(&self, _other: &Self) -> bool { true }
+}
 // ---------------------------------------------------------------------------------------------------
 
 
 // Functions -----------------------------------------------------------------------------------------
-fn attributes_from_hashmap(variables: &HashMap<&str, Variant>) -> Attributes {
-    let mut attributes = Attributes::new();
-    if !variables.is_empty() {
-        for (key, value) in variables {
-            attributes.insert(key.to_string(), value.clone());
+fn snapshot_from_compiled(compiled: CompiledInstance) -> InstanceSnapshot {
+    let mut declarations: HashMap<String, usize> = HashMap::new();
+
+    // Rules are named after their selectors, so the same selector used twice makes two siblings with
+    // the same name. Which of them each one is gets recorded so that live syncing tells them apart.
+    // Only rules written out in the source are counted, since those are what edits get written to.
+    let children = compiled.children.into_iter().map(|child| {
+        if child.generated { return snapshot_from_compiled(child).metadata(InstanceMetadata::new().generated(true)) }
+
+        let count = declarations.entry(child.name.clone()).or_default();
+        let declaration_index = *count;
+        *count += 1;
+
+        snapshot_from_compiled(child).metadata(InstanceMetadata::new().declaration_index(declaration_index))
+    }).collect::<Vec<_>>();
+
+    InstanceSnapshot::new()
+        .name(compiled.name)
+        .class_name(compiled.class_name)
+        .properties(compiled.properties)
+        .children(children)
+}
+
+// Luau ----------------------------------------------------------------------------------------------
+/// Numbers go through `f32`'s formatting, so `1.2` doesn't become `1.2000000476837158`.
+fn lua_number(number: f32) -> Expression {
+    Expression::Number(number.to_string().parse().unwrap_or(number as f64))
+}
+
+pub(super) fn lua_call(function: &str, arguments: Vec<Expression>) -> Expression {
+    Expression::Call(function.to_owned(), arguments)
+}
+
+fn lua_color3(color: &Color3) -> Expression {
+    let channel = |channel: f32| Expression::Number((channel * 255.0).round() as f64);
+
+    lua_call("Color3.fromRGB", vec![channel(color.r), channel(color.g), channel(color.b)])
+}
+
+fn lua_udim(udim: &UDim) -> Vec<Expression> {
+    vec![lua_number(udim.scale), Expression::Number(udim.offset as f64)]
+}
+
+/// The Luau expression and type of a value that RSML produces. Strings naming enum items, like
+/// `Enum.Font.Gotham`, become the item itself. Values with no Luau equivalent give `None`.
+pub(super) fn lua_value(value: &Variant) -> Option<(Expression, String)> {
+    let (expression, value_type) = match value {
+        Variant::String(string) => {
+            if let Some((enum_type, _)) = string.strip_prefix("Enum.").and_then(|path| path.rsplit_once('.')) {
+                return Some((Expression::Name(string.clone()), format!("Enum.{}", enum_type)));
+            }
+
+            (Expression::String(string.clone()), "string")
+        }
+        Variant::Bool(value) => (Expression::Bool(*value), "boolean"),
+        Variant::Int32(number) => (Expression::Number(*number as f64), "number"),
+        Variant::Float32(number) => (lua_number(*number), "number"),
+        Variant::Color3(color) => (lua_color3(color), "Color3"),
+        Variant::UDim(udim) => (lua_call("UDim.new", lua_udim(udim)), "UDim"),
+        Variant::UDim2(udim2) => {
+            let mut arguments = lua_udim(&udim2.x);
+            arguments.extend(lua_udim(&udim2.y));
+
+            (lua_call("UDim2.new", arguments), "UDim2")
+        }
+        Variant::Vector2(vector) => (
+            lua_call("Vector2.new", vec![lua_number(vector.x), lua_number(vector.y)]),
+            "Vector2",
+        ),
+        Variant::Vector3(vector) => (
+            lua_call(
+                "Vector3.new",
+                vec![lua_number(vector.x), lua_number(vector.y), lua_number(vector.z)],
+            ),
+            "Vector3",
+        ),
+        Variant::Rect(rect) => (
+            lua_call(
+                "Rect.new",
+                vec![
+                    lua_number(rect.min.x),
+                    lua_number(rect.min.y),
+                    lua_number(rect.max.x),
+                    lua_number(rect.max.y),
+                ],
+            ),
+            "Rect",
+        ),
+        Variant::Font(font) => (
+            lua_call(
+                "Font.new",
+                vec![
+                    Expression::String(font.family.clone()),
+                    Expression::Name(format!("Enum.FontWeight.{:?}", font.weight)),
+                    Expression::Name(format!("Enum.FontStyle.{:?}", font.style)),
+                ],
+            ),
+            "Font",
+        ),
+        _ => return None,
+    };
+
+    Some((expression, value_type.to_string()))
+}
+
+/// Like [`lua_value`], but references to other variables are followed to the values they refer to.
+fn lua_variable(variables: &Attributes, value: &Variant, depth: usize) -> Option<(Expression, String)> {
+    match value {
+        Variant::String(string) if string.starts_with('$') => {
+            if depth > variables.len() {
+                return None;
+            }
+
+            lua_variable(variables, variables.get(&string[1..])?, depth + 1)
+        }
+        _ => lua_value(value),
+    }
+}
+
+/// A ModuleScript that returns a stylesheet's root variables as a frozen table, along with a type
+/// for them, so that game code can share the values its stylesheets use.
+fn variables_module(variables: &Attributes) -> InstanceSnapshot {
+    let (entries, fields): (Vec<_>, Vec<_>) = variables
+        .iter()
+        .filter_map(|(name, value)| {
+            let (expression, value_type) = lua_variable(variables, value, 0)?;
+            Some(((name.as_str().into(), expression), (name.clone(), value_type)))
+        })
+        .unzip();
+
+    let chunk = Chunk(vec![
+        vec![Statement::ExportType {
+            name: "Variables".to_owned(),
+            fields,
+        }],
+        vec![Statement::Local {
+            name: "variables".to_owned(),
+            type_annotation: Some("Variables".to_owned()),
+            value: lua_call("table.freeze", vec![Expression::table(entries)]),
+        }],
+        vec![Statement::Return(Expression::Name("variables".to_owned()))],
+    ]);
+
+    InstanceSnapshot::new()
+        .name("Variables")
+        .class_name("ModuleScript")
+        .property("Source", chunk.to_string())
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Utilities -----------------------------------------------------------------------------------------
+/// Where the tags that `@utilities` makes classes for are looked for, and the scale the classes use.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RsmlUtilities {
+    /// The folder of the project that turned utility classes on, which is scanned for tags.
+    #[serde(serialize_with = "path_serializer::serialize_absolute")]
+    pub root: PathBuf,
+    pub scale: UtilityScale,
+}
+
+/// The tags on the instances in a `.rbxm` or `.rbxmx` file.
+fn model_tags(path: &Path, contents: &[u8]) -> anyhow::Result<Vec<String>> {
+    let dom = match path.extension().and_then(OsStr::to_str) {
+        Some("rbxm") => rbx_binary::from_reader(contents)?,
+        _ => {
+            let options = rbx_xml::DecodeOptions::new().property_behavior(rbx_xml::DecodePropertyBehavior::ReadUnknown);
+            rbx_xml::from_reader(contents, options)?
+        }
+    };
+
+    let mut tags = Vec::new();
+
+    for instance in dom.descendants() {
+        match instance.properties.get("Tags") {
+            Some(Variant::Tags(instance_tags)) => tags.extend(instance_tags.iter().map(str::to_owned)),
+            // Older models keep their tags in a null-separated BinaryString.
+            Some(Variant::BinaryString(bytes)) => {
+                if let Ok(instance_tags) = Tags::decode(bytes.as_ref()) {
+                    tags.extend(instance_tags.iter().map(str::to_owned));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(tags)
+}
+
+/// Calls `visit` with every file in `path` that isn't hidden or ignored. The paths of the directories
+/// walked through are collected, along with those of the files that `visit` read, which it says by
+/// returning `true`.
+fn walk_files(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    scanned_paths: &mut Vec<PathBuf>,
+    visit: &mut dyn FnMut(&Path) -> anyhow::Result<bool>,
+) -> anyhow::Result<()> {
+    scanned_paths.push(path.to_path_buf());
+
+    for entry in vfs.read_dir(path)? {
+        let entry = entry?;
+        let entry_path = entry.path();
+
+        let is_hidden = entry_path.file_name().and_then(OsStr::to_str).map_or(true, |name| name.starts_with('.'));
+        if is_hidden || !context.path_ignore_rules.iter().all(|rule| rule.passes(entry_path)) {
+            continue;
+        }
+
+        if vfs.metadata(entry_path)?.is_dir() {
+            walk_files(context, vfs, entry_path, scanned_paths, visit)?;
+        } else if visit(entry_path)? {
+            scanned_paths.push(entry_path.to_path_buf());
+        }
+    }
+
+    Ok(())
+}
+
+/// Collects the words that could be tags from a Luau, JSON or model file, returning whether `path`
+/// was one of those.
+fn file_tags(vfs: &Vfs, path: &Path, tags: &mut BTreeSet<String>) -> anyhow::Result<bool> {
+    match path.extension().and_then(OsStr::to_str) {
+        Some("lua" | "luau" | "json") => {
+            let contents = vfs.read_to_string(path)?;
+            tags.extend(candidate_tags(&contents).map(str::to_owned));
+        }
+        Some("rbxm" | "rbxmx") => {
+            let model_tags = model_tags(path, &vfs.read(path)?)
+                .with_context(|| format!("Could not read tags from {}", path.display()))?;
+            tags.extend(model_tags);
+        }
+        _ => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+/// Collects the words that could be tags from the Luau, JSON and model files in `path`, along with
+/// the paths of those files and of the directories they're in. Changes to any of them can change
+/// which utility classes a stylesheet needs.
+fn scan_tags(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    tags: &mut BTreeSet<String>,
+    scanned_paths: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    walk_files(context, vfs, path, scanned_paths, &mut |file| file_tags(vfs, file, tags))
+}
+// ---------------------------------------------------------------------------------------------------
+
+
+// Tag Registry --------------------------------------------------------------------------------------
+/// A frozen table of names to themselves, so that game code can refer to them without typos.
+fn lua_constants(names: &[String]) -> Expression {
+    let entries = names.iter().map(|name| (name.as_str().into(), name.as_str().into())).collect();
+    lua_call("table.freeze", vec![Expression::table(entries)])
+}
+
+/// Makes the `Tags` ModuleScript that `$rsmlTags` asks for, from the project in `root`. It has a
+/// constant for every tag and state used in the selectors of the project's stylesheets, along with
+/// `Tag` and `State` types that are unions of them. Styled tags which nothing in the project applies
+/// are warned about, since they're most likely typos. The files read are returned too, since they're
+/// relevant paths.
+pub(super) fn tag_registry(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    root: &Path,
+) -> anyhow::Result<(InstanceSnapshot, Vec<PathBuf>)> {
+    // Styled tags are kept with the first stylesheet they're in, to point the warnings at.
+    let mut styled: BTreeMap<String, PathBuf> = BTreeMap::new();
+    let mut states = BTreeSet::new();
+    let mut applied = BTreeSet::new();
+    let mut read_paths = Vec::new();
+
+    walk_files(context, vfs, root, &mut read_paths, &mut |file| {
+        if file.extension().and_then(OsStr::to_str) != Some("rsml") {
+            return file_tags(vfs, file, &mut applied);
+        }
+
+        let names = selector_names(&parse_ast(&vfs.read_to_string(file)?));
+        for tag in names.tags {
+            styled.entry(tag).or_insert_with(|| file.to_path_buf());
+        }
+        states.extend(names.states);
+
+        Ok(true)
+    })?;
+
+    for (tag, path) in &styled {
+        if !applied.contains(tag) {
+            log::warn!("{} styles the tag '{}', but nothing in the project applies it", path.display(), tag);
         }
     }
 
-    attributes
+    let tags = styled.into_keys().collect::<Vec<_>>();
+    let states = states.into_iter().collect::<Vec<_>>();
+
+    let chunk = Chunk(vec![
+        vec![
+            Statement::ExportUnion { name: "Tag".to_owned(), members: tags.clone() },
+            Statement::ExportUnion { name: "State".to_owned(), members: states.clone() },
+        ],
+        vec![Statement::Local {
+            name: "tags".to_owned(),
+            type_annotation: None,
+            value: lua_call(
+                "table.freeze",
+                vec![Expression::table(vec![
+                    ("Tag".into(), lua_constants(&tags)),
+                    ("State".into(), lua_constants(&states)),
+                ])],
+            ),
+        }],
+        vec![Statement::Return(Expression::Name("tags".to_owned()))],
+    ]);
+
+    let snapshot = InstanceSnapshot::new()
+        .name("Tags")
+        .class_name("ModuleScript")
+        .property("Source", chunk.to_string());
+
+    Ok((snapshot, read_paths))
 }
+// ---------------------------------------------------------------------------------------------------
 
-fn apply_token_tree_to_stylesheet_snapshot(
-    mut snapshot: InstanceSnapshot, selector: &str, data: &TokenTreeNode, arena: &Arena<TokenTreeNode>
-) -> InstanceSnapshot {
-    for (selector, children) in &data.rules.0 {
-        for child_idx in children {
-            let mut style_rule = InstanceSnapshot::new()
-            .class_name("StyleRule")
-            .name(selector.to_owned());
 
-            let child_data = arena.get(*child_idx).unwrap();
-            style_rule = apply_token_tree_to_stylesheet_snapshot(style_rule, &selector, &child_data, &arena);
+/// The ID that the StyleSheet made from `path` has when it isn't given one, so that `$styleSheet`
+/// can point at it by path. An `init.rsml` file stands for the directory it's in.
+pub(super) fn stylesheet_ref(path: &Path) -> RojoRef {
+    let path = match path.file_name().and_then(|name| name.to_str()) {
+        Some("init.rsml") => path.parent().unwrap_or(path),
+        _ => path,
+    };
+
+    RojoRef::new(format!("rsml:{}", normalize_path(path).display()))
+}
+
+/// The ID of the StyleSheet that an `@sheet` block named `name` in the file at `path` becomes.
+fn sheet_ref(path: &Path, name: &str) -> RojoRef {
+    RojoRef::new(format!("{}#{}", stylesheet_ref(path), name))
+}
+
+fn is_stylesheet_path(path: &str) -> bool {
+    path.ends_with(".rsml") || path.ends_with(".css")
+}
+
+/// A StyleLink or StyleDerive, whose `StyleSheet` points at the StyleSheet with the ID `target`.
+fn style_sheet_pointer(class_name: &'static str, target: &RojoRef) -> InstanceSnapshot {
+    let mut attributes = Attributes::new();
+    attributes.insert(format!("{}StyleSheet", REF_POINTER_ATTRIBUTE_PREFIX), Variant::String(target.to_string()));
+
+    InstanceSnapshot::new()
+        .name(class_name)
+        .class_name(class_name)
+        .property("Attributes", attributes)
+}
+
+/// Makes the StyleLink for a `$styleSheet` key, pointing at either a `.rsml` or `.css` file
+/// relative to `base_path`, an `@sheet` block in a file like `./Components.rsml#Button`, or a
+/// StyleSheet with that ID. The link is resolved like any other Ref attribute.
+pub fn style_link(base_path: &Path, style_sheet: &str) -> InstanceSnapshot {
+    let target = match style_sheet.split_once('#') {
+        Some((file, sheet)) if is_stylesheet_path(file) => sheet_ref(&base_path.join(file), sheet),
+        _ if is_stylesheet_path(style_sheet) => stylesheet_ref(&base_path.join(style_sheet)),
+        _ => RojoRef::new(style_sheet.to_owned()),
+    };
+
+    style_sheet_pointer("StyleLink", &target)
+}
+
+/// Compiles the stylesheet at `path` into a StyleSheet named `name`, without any metadata. The
+/// other files it reads, like design token files and the files scanned for `@utilities`, are
+/// returned too, since they're relevant paths.
+pub(super) fn compile_file(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+) -> anyhow::Result<(InstanceSnapshot, Vec<PathBuf>)> {
+    let contents = vfs.read_to_string(path)?;
+    compile_source(context, vfs, path, &contents, name)
+}
 
-            snapshot.children.push(style_rule);
+/// Like `compile_file`, but for RSML that didn't come straight from `path`, like a converted CSS
+/// file. `path` is still used for the cache, for errors and to find the files the RSML refers to.
+pub(super) fn compile_source(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    contents: &str,
+    name: &str,
+) -> anyhow::Result<(InstanceSnapshot, Vec<PathBuf>)> {
+    let parsed = match &context.rsml_cache {
+        Some(cache) => cache.parse(path, contents),
+        None => Arc::new(parse_stylesheet(contents)),
+    };
+
+    let stylesheet = parsed.as_ref().as_ref().map_err(|diagnostics| {
+        let errors = diagnostics.0.iter().map(|diagnostic| {
+            let (line, column) = line_column(contents, diagnostic.span.start);
+            format!("{}:{}:{}: {}", path.display(), line, column, diagnostic.message)
+        }).collect::<Vec<_>>();
+
+        format_err!("Could not compile stylesheet:\n{}", errors.join("\n"))
+    })?;
+
+    let mut options = CompileOptions { name: name.to_owned(), ..Default::default() };
+    let mut read_paths = Vec::new();
+
+    for tokens in &stylesheet.design_tokens {
+        let tokens_path = design_token_file_path(path, tokens);
+        let json = vfs.read_to_string(&tokens_path)?;
+        let variables = parse_design_tokens(&json)
+            .with_context(|| format!("Could not read design tokens from {}", tokens_path.display()))?;
+
+        options.imported_variables.extend(variables);
+        read_paths.push(tokens_path);
+    }
+
+    if stylesheet.utilities {
+        let utilities = context.rsml_utilities.as_ref().ok_or_else(|| {
+            format_err!("{} uses @utilities, but its project doesn't set rsmlUtilities", path.display())
+        })?;
+
+        let mut tags = BTreeSet::new();
+        scan_tags(context, vfs, &utilities.root, &mut tags, &mut read_paths)?;
+
+        options.extra_rules = utility_rules(tags.iter().map(String::as_str), &utilities.scale);
+    }
+
+    let names = asset_names(stylesheet);
+
+    if !names.is_empty() {
+        let manifest_path = context.rsml_assets.as_ref().ok_or_else(|| {
+            format_err!("{} looks up assets by name, but its project doesn't set rsmlAssets", path.display())
+        })?;
+
+        let json = vfs.read_to_string(manifest_path)?;
+        let assets = parse_asset_manifest(&json)
+            .with_context(|| format!("Could not read the asset manifest {}", manifest_path.display()))?;
+
+        let missing = names.into_iter().filter(|name| !assets.contains_key(*name)).collect::<Vec<_>>();
+        if !missing.is_empty() {
+            bail!("{} uses assets that aren't in {}: {}", path.display(), manifest_path.display(), missing.join(", "));
         }
+
+        options.assets = assets;
+        read_paths.push(manifest_path.clone());
     }
 
-    let attributes = attributes_from_hashmap(&data.variables);
-    let styled_properties = attributes_from_hashmap(&data.properties);
+    let compiled = compile_stylesheet(stylesheet, &options);
+    let mut snapshot = snapshot_from_compiled(compiled);
+
+    // With `@sheet` blocks the file becomes a Folder, and each StyleSheet in it gets an ID so that it
+    // can be linked to on its own.
+    let sheets = if stylesheet.sheets.is_empty() {
+        std::slice::from_mut(&mut snapshot)
+    } else {
+        for sheet in &mut snapshot.children {
+            sheet.metadata.specified_id = Some(sheet_ref(path, &sheet.name));
+        }
 
-    let priority = match data.priority {
-        Some(some_priority) => Variant::Int32(some_priority),
-        None => Variant::Int32(0)
+        &mut snapshot.children[..]
     };
 
-    let properties = [
-        ("Selector".into(), Variant::String(selector.to_string())),
-        ("Priority".into(), priority),
-        ("Attributes".into(), attributes.into()),
-        ("StyledProperties".into(), styled_properties.into())
-    ];
+    if context.emit_rsml_variables {
+        for sheet in sheets {
+            if let Some(Variant::Attributes(variables)) = sheet.properties.get("Attributes") {
+                let module = variables_module(variables);
+                sheet.children.push(module);
+            }
+        }
+    }
 
-    snapshot.properties(properties)
+    Ok((snapshot, read_paths))
 }
 // ---------------------------------------------------------------------------------------------------
 
@@ -67,54 +568,85 @@ pub fn snapshot_rsml(
     path: &Path,
     name: &str,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
-    let contents = vfs.read_to_string(path)?;
-    let contents_str = contents.as_str();
+    let meta_path = path.with_file_name(format!("{}.meta.json", name));
 
-    let tokens = lex_rsml(contents_str);
-    let token_tree_arena = parse_rsml(&tokens);
+    let (snapshot, read_paths) = compile_file(context, vfs, path, name)?;
 
-    let meta_path = path.with_file_name(format!("{}.meta.json", name));
+    let mut relevant_paths = vec![path.to_path_buf(), meta_path.clone()];
+    relevant_paths.extend(read_paths);
 
-    let mut snapshot = InstanceSnapshot::new()
-        .name(name)
-        .class_name("StyleSheet")
-        .metadata(
-            InstanceMetadata::new()
-                .instigating_source(path)
-                .relevant_paths(vec![path.to_path_buf(), meta_path.clone()])
-                .context(context),
-        );
+    let mut snapshot = snapshot.metadata(
+        InstanceMetadata::new()
+            .instigating_source(path)
+            .relevant_paths(relevant_paths)
+            .context(context),
+    );
 
     if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
         let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
         metadata.apply_all(&mut snapshot)?;
     }
 
-    let root_node = &token_tree_arena.get(0).unwrap();
+    // A Folder of `@sheet` blocks isn't something a StyleLink can point at.
+    if snapshot.class_name == "StyleSheet" {
+        snapshot.metadata.specified_id.get_or_insert_with(|| stylesheet_ref(path));
+    }
 
-    let root_attributes = attributes_from_hashmap(&root_node.variables);
+    Ok(Some(snapshot))
+}
 
-    snapshot = snapshot.properties([
-        ("Attributes".into(), root_attributes.into()),
-    ]);
+/// Turns a directory with an `init.rsml` file in it into a StyleSheet. The sheet's own rules come
+/// first, followed by a StyleDerive for every StyleSheet in the directory, so that linking the
+/// directory's sheet brings in the stylesheets of its components too. Everything else in the
+/// directory comes after.
+///
+/// An `init.rsml` with `@sheet` blocks makes the directory a Folder of those sheets instead. There's
+/// no one sheet to link then, so none of them derive from the directory's other StyleSheets.
+pub fn snapshot_rsml_init(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    init_path: &Path,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let folder_path = init_path.parent().unwrap();
+    let dir_snapshot = snapshot_dir_no_meta(context, vfs, folder_path)?.unwrap();
+
+    if dir_snapshot.class_name != "Folder" {
+        anyhow::bail!(
+            "init.rsml can only be used if the instance produced by \
+             the containing directory would be a Folder.\n\
+             \n\
+             The directory {} turned into an instance of class {}.",
+            folder_path.display(),
+            dir_snapshot.class_name
+        );
+    }
 
-    for (selector, children) in &root_node.rules.0 {
-        for child_idx in children {
-            let mut rule_snapshot = InstanceSnapshot::new()
-            .class_name("StyleRule")
-            .name(selector.to_owned());
+    let (mut init_snapshot, read_paths) = compile_file(context, vfs, init_path, &dir_snapshot.name)?;
 
-            rule_snapshot = apply_token_tree_to_stylesheet_snapshot(
-                rule_snapshot, selector, &token_tree_arena.get(child_idx.to_owned()).unwrap(), &token_tree_arena
-            );
+    if init_snapshot.class_name == "StyleSheet" {
+        let derives = dir_snapshot.children.iter()
+            .filter(|child| child.class_name == "StyleSheet")
+            .filter_map(|child| child.metadata.specified_id.as_ref())
+            .map(|target| style_sheet_pointer("StyleDerive", target))
+            .collect::<Vec<_>>();
 
-            snapshot.children.push(rule_snapshot);
-        }
+        init_snapshot.children.extend(derives);
     }
 
-    Ok(Some(snapshot))
-}
+    init_snapshot.children.extend(dir_snapshot.children);
+    init_snapshot.metadata = dir_snapshot.metadata;
+    init_snapshot.metadata.relevant_paths.extend(read_paths);
+
+    if let Some(mut meta) = dir_meta(vfs, folder_path)? {
+        meta.apply_all(&mut init_snapshot)?;
+    }
+
+    if init_snapshot.class_name == "StyleSheet" {
+        init_snapshot.metadata.specified_id.get_or_insert_with(|| stylesheet_ref(init_path));
+    }
 
+    Ok(Some(init_snapshot))
+}
 
 #[cfg(test)]
 mod test {
@@ -122,11 +654,18 @@ mod test {
 
     use memofs::{InMemoryFs, VfsSnapshot};
 
+    use crate::snapshot::{apply_patch_set, compute_patch_set, RojoTree};
+    use crate::snapshot_middleware::snapshot_from_vfs;
+    use rbx_dom_weak::types::{Color3, Ref, UDim};
+
     #[test]
     fn instance_from_vfs() {
         let mut imfs = InMemoryFs::new();
-        imfs.load_snapshot("/foo.rsml", VfsSnapshot::file("TextButton {  }"))
-            .unwrap();
+        imfs.load_snapshot(
+            "/foo.rsml",
+            VfsSnapshot::file("$Gap = 4px;\nTextButton { @priority 2; BackgroundColor3 = #ffffff; }"),
+        )
+        .unwrap();
 
         let mut vfs = Vfs::new(imfs.clone());
 
@@ -139,6 +678,493 @@ mod test {
         .unwrap()
         .unwrap();
 
-        insta::assert_yaml_snapshot!(instance_snapshot);
+        insta::with_settings!({ sort_maps => true }, {
+            insta::assert_yaml_snapshot!(instance_snapshot);
+        });
+    }
+    #[test]
+    fn syntax_errors() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.rsml", VfsSnapshot::file("Frame {\n    Size = ;\n}"))
+            .unwrap();
+
+        let mut vfs = Vfs::new(imfs.clone());
+
+        let error = snapshot_rsml(
+            &InstanceContext::default(),
+            &mut vfs,
+            Path::new("/foo.rsml"),
+            "foo",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Could not compile stylesheet:\n/foo.rsml:2:10: expected a value after `=`"
+        );
+    }
+
+    #[test]
+    fn cached_edits() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.rsml", VfsSnapshot::file("Frame { Size = 1; }\nTextLabel { Size = 2; }"))
+            .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let mut context = InstanceContext::default();
+        context.set_rsml_cache(RsmlCache::new());
+        let cache = context.rsml_cache.clone().unwrap();
+
+        let snapshot = snapshot_rsml(&context, &vfs, Path::new("/foo.rsml"), "foo").unwrap().unwrap();
+        let tree = RojoTree::new(snapshot);
+        let root_id = tree.get_root_id();
+        let text_label = tree.get_instance(root_id).unwrap().children()[1];
+
+        let first = cache.parse(Path::new("/foo.rsml"), "Frame { Size = 1; }\nTextLabel { Size = 2; }");
+        let second = cache.parse(Path::new("/foo.rsml"), "Frame { Size = 1; }\nTextLabel { Size = 2; }");
+        assert!(Arc::ptr_eq(&first, &second));
+
+        vfs.write("/foo.rsml", "TextButton { }\nFrame { Size = 1; }\nTextLabel { Size = 3; }").unwrap();
+
+        let snapshot = snapshot_rsml(&context, &vfs, Path::new("/foo.rsml"), "foo").unwrap();
+        let patch_set = compute_patch_set(snapshot, &tree, root_id);
+
+        assert!(patch_set.removed_instances.is_empty());
+        assert_eq!(patch_set.added_instances.len(), 1);
+        assert_eq!(patch_set.updated_instances.len(), 1);
+        assert_eq!(patch_set.updated_instances[0].id, text_label);
+        assert_eq!(patch_set.updated_instances[0].changed_properties.keys().collect::<Vec<_>>(), ["StyledProperties"]);
+    }
+
+    #[test]
+    fn duplicate_selectors() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.rsml", VfsSnapshot::file("Frame { Size = 1; }\nFrame { Size = 2; }\nFrame { Size = 3; }"))
+            .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let snapshot = snapshot_rsml(&InstanceContext::default(), &vfs, Path::new("/foo.rsml"), "foo").unwrap().unwrap();
+        let tree = RojoTree::new(snapshot);
+        let root_id = tree.get_root_id();
+        let frames = tree.get_instance(root_id).unwrap().children().to_vec();
+
+        let declaration_indices = frames.iter()
+            .map(|id| tree.get_instance(*id).unwrap().metadata().declaration_index)
+            .collect::<Vec<_>>();
+        assert_eq!(declaration_indices, [Some(0), Some(1), Some(2)]);
+
+        // Adding a rule in front of the others should only add that rule. The others keep their
+        // instances and properties, and only learn that they were declared one place later.
+        vfs.write("/foo.rsml", "Frame { }\nFrame { Size = 1; }\nFrame { Size = 2; }\nFrame { Size = 3; }").unwrap();
+
+        let snapshot = snapshot_rsml(&InstanceContext::default(), &vfs, Path::new("/foo.rsml"), "foo").unwrap();
+        let patch_set = compute_patch_set(snapshot, &tree, root_id);
+
+        assert_eq!(patch_set.added_instances.len(), 1);
+        assert_eq!(patch_set.added_instances[0].instance.metadata.declaration_index, Some(0));
+        assert!(patch_set.removed_instances.is_empty());
+
+        for (update, frame) in patch_set.updated_instances.iter().zip(&frames) {
+            assert_eq!(update.id, *frame);
+            assert!(update.changed_properties.is_empty());
+            assert!(update.changed_name.is_none() && update.changed_class_name.is_none());
+        }
+
+        let declaration_indices = patch_set.updated_instances.iter()
+            .map(|update| update.changed_metadata.as_ref().unwrap().declaration_index)
+            .collect::<Vec<_>>();
+        assert_eq!(declaration_indices, [Some(1), Some(2), Some(3)]);
+    }
+
+    #[test]
+    fn duplicate_selector_edits() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.rsml", VfsSnapshot::file("Frame { Size = 1; }\nFrame { Size = 2; }\nFrame { Size = 3; }"))
+            .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let snapshot = snapshot_rsml(&InstanceContext::default(), &vfs, Path::new("/foo.rsml"), "foo").unwrap().unwrap();
+        let tree = RojoTree::new(snapshot);
+        let root_id = tree.get_root_id();
+        let frames = tree.get_instance(root_id).unwrap().children().to_vec();
+
+        // With two of the rules edited, neither has an unchanged instance to pair with, so each goes to
+        // the instance declared in the same place.
+        vfs.write("/foo.rsml", "Frame { Size = 10; }\nFrame { Size = 2; }\nFrame { Size = 30; }").unwrap();
+
+        let snapshot = snapshot_rsml(&InstanceContext::default(), &vfs, Path::new("/foo.rsml"), "foo").unwrap();
+        let patch_set = compute_patch_set(snapshot, &tree, root_id);
+
+        assert!(patch_set.added_instances.is_empty());
+        assert!(patch_set.removed_instances.is_empty());
+
+        let updates = patch_set.updated_instances.iter()
+            .map(|update| {
+                let Some(Some(Variant::Attributes(properties))) = update.changed_properties.get("StyledProperties") else { panic!() };
+                (update.id, properties.get("Size").cloned())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(updates, [(frames[0], Some(Variant::Float32(10.0))), (frames[2], Some(Variant::Float32(30.0)))]);
+    }
+
+    #[test]
+    fn init_stylesheet() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/Theme",
+            VfsSnapshot::dir([
+                ("init.rsml", VfsSnapshot::file("$Gap = 4px;\nFrame { }")),
+                ("Button.rsml", VfsSnapshot::file("TextButton { }")),
+                ("init.meta.json", VfsSnapshot::file(r#"{ "ignoreUnknownInstances": true }"#)),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let snapshot = snapshot_from_vfs(&InstanceContext::default(), &vfs, Path::new("/Theme")).unwrap().unwrap();
+
+        assert_eq!(snapshot.name, "Theme");
+        assert_eq!(snapshot.class_name, "StyleSheet");
+        assert_eq!(snapshot.metadata.instigating_source, Some(Path::new("/Theme").into()));
+
+        let Some(Variant::Attributes(attributes)) = snapshot.properties.get("Attributes") else { panic!() };
+        assert!(attributes.get("Gap").is_some());
+        assert!(snapshot.metadata.ignore_unknown_instances);
+
+        let children = snapshot.children.iter()
+            .map(|child| (child.name.as_ref(), child.class_name.as_ref()))
+            .collect::<Vec<_>>();
+        assert_eq!(children, [("Frame", "StyleRule"), ("StyleDerive", "StyleDerive"), ("Button", "StyleSheet")]);
+
+        let mut tree = RojoTree::new(InstanceSnapshot::new());
+        let root_id = tree.get_root_id();
+        let patch_set = compute_patch_set(Some(snapshot), &tree, root_id);
+        apply_patch_set(&mut tree, patch_set);
+
+        let theme = tree.get_instance(root_id).unwrap().children().to_vec();
+        let derive = tree.get_instance(theme[1]).unwrap();
+        assert_eq!(derive.properties().get("StyleSheet"), Some(&Variant::Ref(theme[2])));
+    }
+
+    #[test]
+    fn init_sheets() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/Theme",
+            VfsSnapshot::dir([
+                ("init.rsml", VfsSnapshot::file("@sheet A { Frame { } }\n@sheet B { TextLabel { } }")),
+                ("Button.rsml", VfsSnapshot::file("TextButton { }")),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let snapshot = snapshot_from_vfs(&InstanceContext::default(), &vfs, Path::new("/Theme")).unwrap().unwrap();
+        assert_eq!(snapshot.class_name, "Folder");
+
+        let children = snapshot.children.iter()
+            .map(|child| (child.name.as_ref(), child.class_name.as_ref()))
+            .collect::<Vec<_>>();
+        assert_eq!(children, [("A", "StyleSheet"), ("B", "StyleSheet"), ("Button", "StyleSheet")]);
+
+        let sheet_children = snapshot.children[..2].iter()
+            .flat_map(|sheet| sheet.children.iter().map(|child| child.class_name.as_ref()))
+            .collect::<Vec<_>>();
+        assert_eq!(sheet_children, ["StyleRule", "StyleRule"]);
+    }
+
+    #[test]
+    fn style_links() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir([
+                ("default.project.json", VfsSnapshot::file(r#"{
+                    "name": "foo",
+                    "tree": {
+                        "$className": "Folder",
+                        "Theme": { "$path": "Theme.rsml" },
+                        "Dark": { "$className": "StyleSheet", "$id": "dark" },
+                        "Hud": { "$className": "ScreenGui", "$styleSheet": "./Theme.rsml" },
+                        "Menu": { "$path": "Menu" },
+                        "Shop": { "$path": "src/Shop" },
+                        "Button": { "$path": "src/Theme/Button.rsml" },
+                        "Store": { "$className": "ScreenGui", "$styleSheet": "src/Theme/../Theme/Button.rsml" }
+                    }
+                }"#)),
+                ("Theme.rsml", VfsSnapshot::file("Frame { }")),
+                ("Menu", VfsSnapshot::dir([
+                    ("init.meta.json", VfsSnapshot::file(r#"{ "className": "ScreenGui", "styleSheet": "dark" }"#)),
+                ])),
+                ("src", VfsSnapshot::dir([
+                    ("Shop", VfsSnapshot::dir([
+                        ("init.meta.json", VfsSnapshot::file(r#"{ "className": "ScreenGui", "styleSheet": "../../Theme.rsml" }"#)),
+                    ])),
+                    ("Theme", VfsSnapshot::dir([("Button.rsml", VfsSnapshot::file("TextButton { }"))])),
+                ])),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let snapshot = snapshot_from_vfs(&InstanceContext::default(), &vfs, Path::new("/foo")).unwrap();
+
+        let mut tree = RojoTree::new(InstanceSnapshot::new());
+        let root_id = tree.get_root_id();
+        let patch_set = compute_patch_set(snapshot, &tree, root_id);
+        apply_patch_set(&mut tree, patch_set);
+
+        let find = |parent: Ref, name: &str| {
+            tree.get_instance(parent).unwrap().children().iter().copied()
+                .find(|id| tree.get_instance(*id).unwrap().name() == name)
+                .unwrap()
+        };
+
+        for (gui, sheet) in [("Hud", "Theme"), ("Menu", "Dark"), ("Shop", "Theme"), ("Store", "Button")] {
+            let link = tree.get_instance(find(find(root_id, gui), "StyleLink")).unwrap();
+
+            assert_eq!(link.class_name(), "StyleLink");
+            assert_eq!(link.properties().get("StyleSheet"), Some(&Variant::Ref(find(root_id, sheet))));
+        }
+    }
+
+    #[test]
+    fn design_tokens() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir([
+                ("Theme.rsml", VfsSnapshot::file("@tokens \"tokens/theme.json\";\n$space-sm = 4px;")),
+                ("tokens", VfsSnapshot::dir([
+                    ("theme.json", VfsSnapshot::file(r##"{
+                        "color": { "$type": "color", "brand": { "$value": "#ff0000" } },
+                        "space": { "$type": "dimension", "sm": { "$value": "8px" } }
+                    }"##)),
+                ])),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let snapshot = snapshot_rsml(&InstanceContext::default(), &vfs, Path::new("/foo/Theme.rsml"), "Theme")
+            .unwrap()
+            .unwrap();
+
+        let Some(Variant::Attributes(attributes)) = snapshot.properties.get("Attributes") else { panic!() };
+        assert_eq!(attributes.get("color-brand"), Some(&Variant::Color3(Color3::new(1.0, 0.0, 0.0))));
+        assert_eq!(attributes.get("space-sm"), Some(&Variant::UDim(UDim::new(0.0, 4))));
+
+        assert!(snapshot.metadata.relevant_paths.contains(&PathBuf::from("/foo/tokens/theme.json")));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn sheets() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir([
+                ("default.project.json", VfsSnapshot::file(r#"{
+                    "name": "foo",
+                    "emitRsmlVariables": true,
+                    "tree": {
+                        "$className": "Folder",
+                        "Components": { "$path": "Components.rsml" },
+                        "Hud": { "$className": "ScreenGui", "$styleSheet": "./Components.rsml#Card" }
+                    }
+                }"#)),
+                ("Components.rsml", VfsSnapshot::file("$Gap = 4px;\n@sheet Button { TextButton { } }\n@sheet Card { $Radius = 8px; Frame { } }")),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let snapshot = snapshot_from_vfs(&InstanceContext::default(), &vfs, Path::new("/foo")).unwrap();
+
+        let mut tree = RojoTree::new(InstanceSnapshot::new());
+        let root_id = tree.get_root_id();
+        let patch_set = compute_patch_set(snapshot, &tree, root_id);
+        apply_patch_set(&mut tree, patch_set);
+
+        let find = |parent: Ref, name: &str| {
+            tree.get_instance(parent).unwrap().children().iter().copied()
+                .find(|id| tree.get_instance(*id).unwrap().name() == name)
+                .unwrap()
+        };
+
+        let components = find(root_id, "Components");
+        assert_eq!(tree.get_instance(components).unwrap().class_name(), "Folder");
+
+        let card = find(components, "Card");
+        assert_eq!(tree.get_instance(card).unwrap().class_name(), "StyleSheet");
+        assert_eq!(tree.get_instance(find(card, "Variables")).unwrap().class_name(), "ModuleScript");
+
+        let link = tree.get_instance(find(find(root_id, "Hud"), "StyleLink")).unwrap();
+        assert_eq!(link.properties().get("StyleSheet"), Some(&Variant::Ref(card)));
+    }
+
+    #[test]
+    fn tags() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir([
+                ("Styles.rsml", VfsSnapshot::file(".Primary :Hover { }\n@sheet Alerts { .Danger { } }")),
+                ("Button.model.json", VfsSnapshot::file(r#"{ "className": "TextButton", "tags": ["Primary"] }"#)),
+                ("Notes.txt", VfsSnapshot::file("Danger")),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let (snapshot, read_paths) = tag_registry(&InstanceContext::default(), &vfs, Path::new("/foo")).unwrap();
+
+        assert_eq!(snapshot.name, "Tags");
+        assert_eq!(snapshot.class_name, "ModuleScript");
+        assert_eq!(
+            snapshot.properties.get("Source"),
+            Some(&Variant::String(
+                "export type Tag = \"Danger\" | \"Primary\"\nexport type State = \"Hover\"\n\n\
+                 local tags = table.freeze({\n\tTag = table.freeze({\n\t\tDanger = \"Danger\",\n\t\tPrimary = \"Primary\",\n\t}),\n\t\
+                 State = table.freeze({\n\t\tHover = \"Hover\",\n\t}),\n})\n\nreturn tags\n"
+                    .to_owned()
+            ))
+        );
+
+        assert!(read_paths.contains(&PathBuf::from("/foo/Styles.rsml")));
+        assert!(read_paths.contains(&PathBuf::from("/foo/Button.model.json")));
+        assert!(!read_paths.contains(&PathBuf::from("/foo/Notes.txt")));
+    }
+
+    #[test]
+    fn utilities() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir([
+                ("Styles.rsml", VfsSnapshot::file("@utilities;\nFrame { }")),
+                ("src", VfsSnapshot::dir([
+                    ("Card.luau", VfsSnapshot::file("CollectionService:AddTag(card, \"bg-slate-100\")\ncard:AddTag(\"p-4\")")),
+                    ("Button.model.json", VfsSnapshot::file(r#"{ "className": "TextButton", "attributes": {}, "tags": ["rounded-lg"] }"#)),
+                    ("Notes.txt", VfsSnapshot::file("text-white")),
+                ])),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let error = snapshot_rsml(&InstanceContext::default(), &vfs, Path::new("/foo/Styles.rsml"), "Styles").unwrap_err();
+        assert_eq!(error.to_string(), "/foo/Styles.rsml uses @utilities, but its project doesn't set rsmlUtilities");
+
+        let mut context = InstanceContext::default();
+        context.set_rsml_utilities(Some(RsmlUtilities {
+            root: PathBuf::from("/foo"),
+            scale: UtilityScale { spacing: 2.0, ..Default::default() },
+        }));
+
+        let snapshot = snapshot_rsml(&context, &vfs, Path::new("/foo/Styles.rsml"), "Styles").unwrap().unwrap();
+
+        let names = snapshot.children.iter().map(|child| &*child.name).collect::<Vec<_>>();
+        assert_eq!(names, ["Frame", ".bg-slate-100", ".p-4", ".rounded-lg"]);
+
+        let padding = &snapshot.children[2].children[0];
+        let Some(Variant::Attributes(padding)) = padding.properties.get("StyledProperties") else { panic!() };
+        assert_eq!(padding.get("PaddingTop"), Some(&Variant::UDim(UDim::new(0.0, 8))));
+
+        let relevant_paths = &snapshot.metadata.relevant_paths;
+        assert!(relevant_paths.contains(&PathBuf::from("/foo/src")));
+        assert!(relevant_paths.contains(&PathBuf::from("/foo/src/Card.luau")));
+        assert!(!relevant_paths.contains(&PathBuf::from("/foo/src/Notes.txt")));
+    }
+
+    #[test]
+    fn assets() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir([
+                ("Styles.rsml", VfsSnapshot::file("ImageButton { Image = asset(\"icons/close\"); HoverImage = asset(123); }")),
+                ("Missing.rsml", VfsSnapshot::file("ImageButton { Image = asset(\"icons/open\"); }")),
+                ("assets.json", VfsSnapshot::file(r#"{ "icons": { "close": 456 } }"#)),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let error = snapshot_rsml(&InstanceContext::default(), &vfs, Path::new("/foo/Styles.rsml"), "Styles").unwrap_err();
+        assert_eq!(error.to_string(), "/foo/Styles.rsml looks up assets by name, but its project doesn't set rsmlAssets");
+
+        let mut context = InstanceContext::default();
+        context.set_rsml_assets(Some(PathBuf::from("/foo/assets.json")));
+
+        let snapshot = snapshot_rsml(&context, &vfs, Path::new("/foo/Styles.rsml"), "Styles").unwrap().unwrap();
+
+        let Some(Variant::Attributes(properties)) = snapshot.children[0].properties.get("StyledProperties") else { panic!() };
+        assert_eq!(properties.get("Image"), Some(&Variant::String(String::from("rbxassetid://456"))));
+        assert_eq!(properties.get("HoverImage"), Some(&Variant::String(String::from("rbxassetid://123"))));
+        assert!(snapshot.metadata.relevant_paths.contains(&PathBuf::from("/foo/assets.json")));
+
+        let error = snapshot_rsml(&context, &vfs, Path::new("/foo/Missing.rsml"), "Missing").unwrap_err();
+        assert_eq!(error.to_string(), "/foo/Missing.rsml uses assets that aren't in /foo/assets.json: icons/open");
+    }
+
+    #[test]
+    fn variables_module() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/Theme.rsml",
+            VfsSnapshot::file(
+                "$Accent = #ff8000;\n$Gap = udim2(50%, 4px);\n$Tint = $Accent;\n$Align = Enum.TextXAlignment.Left;\n\
+                 $Ratio = 1.2;\n$font-body = font(\"rbxasset://fonts/families/GothamSSm.json\", \"Bold\");\n$Missing = $Nope;",
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let mut context = InstanceContext::default();
+        context.set_emit_rsml_variables(true);
+
+        let snapshot = snapshot_rsml(&context, &vfs, Path::new("/Theme.rsml"), "Theme").unwrap().unwrap();
+
+        let module = snapshot.children.iter().find(|child| child.name == "Variables").unwrap();
+        assert_eq!(module.class_name, "ModuleScript");
+
+        let Some(Variant::String(source)) = module.properties.get("Source") else { panic!() };
+        assert_eq!(
+            source,
+            "export type Variables = {\n\
+             \tAccent: Color3,\n\
+             \tAlign: Enum.TextXAlignment,\n\
+             \tGap: UDim2,\n\
+             \tRatio: number,\n\
+             \tTint: Color3,\n\
+             \t[\"font-body\"]: Font,\n\
+             }\n\n\
+             local variables: Variables = table.freeze({\n\
+             \tAccent = Color3.fromRGB(255, 128, 0),\n\
+             \tAlign = Enum.TextXAlignment.Left,\n\
+             \tGap = UDim2.new(0.5, 0, 0, 4),\n\
+             \tRatio = 1.2,\n\
+             \tTint = Color3.fromRGB(255, 128, 0),\n\
+             \t[\"font-body\"] = Font.new(\"rbxasset://fonts/families/GothamSSm.json\", Enum.FontWeight.Bold, Enum.FontStyle.Normal),\n\
+             })\n\n\
+             return variables\n"
+        );
+
+        let without = snapshot_rsml(&InstanceContext::default(), &vfs, Path::new("/Theme.rsml"), "Theme")
+            .unwrap()
+            .unwrap();
+        assert!(without.children.is_empty());
+    }
+}
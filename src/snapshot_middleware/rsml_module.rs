@@ -0,0 +1,219 @@
+use std::path::Path;
+
+use memofs::{IoResultExt, Vfs};
+use rbx_dom_weak::types::Variant;
+
+use crate::{
+    lua_ast::{Chunk, Expression, Statement},
+    snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot},
+};
+
+use super::{
+    meta_file::AdjacentMetadata,
+    rsml::{compile_file, lua_call, lua_value},
+};
+
+/// Compiles a `.rsml` file into a ModuleScript that builds the StyleSheet
+/// when it's required, for places where shipping the StyleSheet itself is
+/// awkward. This middleware is only used through sync rules.
+pub fn snapshot_rsml_module(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let (stylesheet, read_paths) = compile_file(context, vfs, path, name)?;
+
+    let mut blocks = Vec::new();
+    build_instance(&stylesheet, "styleSheet", None, &mut 0, &mut blocks);
+    blocks.push(vec![Statement::Return(Expression::Name(
+        "styleSheet".to_owned(),
+    ))]);
+
+    let properties = [("Source".to_owned(), Chunk(blocks).to_string().into())];
+
+    let meta_path = path.with_file_name(format!("{}.meta.json", name));
+
+    let mut relevant_paths = vec![path.to_path_buf(), meta_path.clone()];
+    relevant_paths.extend(read_paths);
+
+    let mut snapshot = InstanceSnapshot::new()
+        .name(name)
+        .class_name("ModuleScript")
+        .properties(properties)
+        .metadata(
+            InstanceMetadata::new()
+                .instigating_source(path)
+                .relevant_paths(relevant_paths)
+                .context(context),
+        );
+
+    if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
+        let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
+        metadata.apply_all(&mut snapshot)?;
+    }
+
+    Ok(Some(snapshot))
+}
+
+/// Adds the statements that create `instance` and its StyleRules to
+/// `blocks`, one block per instance. `Attributes` become `SetAttribute` calls
+/// and `StyledProperties` a `SetProperties` call; other properties are set
+/// directly. Files with `@sheet` blocks compile into a Folder, whose
+/// StyleSheets are built the same way.
+fn build_instance(
+    instance: &InstanceSnapshot,
+    variable: &str,
+    parent: Option<&str>,
+    instance_count: &mut usize,
+    blocks: &mut Vec<Vec<Statement>>,
+) {
+    let mut block = vec![
+        Statement::Local {
+            name: variable.to_owned(),
+            type_annotation: None,
+            value: lua_call("Instance.new", vec![instance.class_name.as_ref().into()]),
+        },
+        Statement::Assign {
+            target: format!("{}.Name", variable),
+            value: instance.name.as_ref().into(),
+        },
+    ];
+
+    let mut properties = instance.properties.iter().collect::<Vec<_>>();
+    properties.sort_by_key(|(key, _)| key.as_str());
+
+    for (key, value) in properties {
+        match (key.as_str(), value) {
+            ("Attributes", Variant::Attributes(attributes)) => {
+                for (name, value) in attributes.iter() {
+                    let Some((expression, _)) = lua_value(value) else {
+                        continue;
+                    };
+
+                    block.push(Statement::Expression(lua_call(
+                        &format!("{}:SetAttribute", variable),
+                        vec![name.as_str().into(), expression],
+                    )));
+                }
+            }
+            ("StyledProperties", Variant::Attributes(styled_properties)) => {
+                let entries = styled_properties
+                    .iter()
+                    .filter_map(|(name, value)| Some((name.as_str().into(), lua_value(value)?.0)))
+                    .collect::<Vec<_>>();
+
+                if !entries.is_empty() {
+                    block.push(Statement::Expression(lua_call(
+                        &format!("{}:SetProperties", variable),
+                        vec![Expression::table(entries)],
+                    )));
+                }
+            }
+            _ => {
+                if let Some((expression, _)) = lua_value(value) {
+                    block.push(Statement::Assign {
+                        target: format!("{}.{}", variable, key),
+                        value: expression,
+                    });
+                }
+            }
+        }
+    }
+
+    if let Some(parent) = parent {
+        block.push(Statement::Assign {
+            target: format!("{}.Parent", variable),
+            value: Expression::Name(parent.to_owned()),
+        });
+    }
+
+    blocks.push(block);
+
+    for child in &instance.children {
+        let prefix = match child.class_name.as_ref() {
+            "StyleRule" => "rule",
+            "StyleSheet" => "sheet",
+            _ => continue,
+        };
+
+        *instance_count += 1;
+        let child_variable = format!("{}{}", prefix, instance_count);
+
+        build_instance(
+            child,
+            &child_variable,
+            Some(variable),
+            instance_count,
+            blocks,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    #[test]
+    fn builder_source() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/Theme.rsml",
+            VfsSnapshot::file(
+                "$Gap = 4px;\nTextButton { @priority 2; BackgroundColor3 = #ffffff; \
+                 TextXAlignment = Enum.TextXAlignment.Left; Hover { TextColor3 = $Tint; } }",
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let snapshot = snapshot_rsml_module(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/Theme.rsml"),
+            "Theme",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(snapshot.class_name, "ModuleScript");
+
+        let Some(Variant::String(source)) = snapshot.properties.get("Source") else {
+            panic!()
+        };
+
+        insta::assert_snapshot!(source);
+    }
+
+    #[test]
+    fn sheets() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/Components.rsml",
+            VfsSnapshot::file(
+                "$Gap = 4px;\n@sheet Button { TextButton { Size = $Gap; } }\n@sheet Card { Frame { } }",
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let snapshot = snapshot_rsml_module(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/Components.rsml"),
+            "Components",
+        )
+        .unwrap()
+        .unwrap();
+
+        let Some(Variant::String(source)) = snapshot.properties.get("Source") else {
+            panic!()
+        };
+
+        insta::assert_snapshot!(source);
+    }
+}